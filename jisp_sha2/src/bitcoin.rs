@@ -0,0 +1,262 @@
+//! Double SHA-256 and the hashes Bitcoin builds on it: block hashes and transaction IDs.
+//!
+//! Bitcoin shows these digests byte-reversed, so the hex of a block hash starts with the zeros of its proof of work.
+//! [to_display_hex] and [from_display_hex] convert between a [Sha256Digest] and that display order.
+//! # Examples
+//! ```
+//! use jisp_sha2::bitcoin::{block_hash, to_display_hex};
+//!
+//! let mut header = [0u8; 80];
+//! header[0] = 1;
+//! let hash = block_hash(&header);
+//! assert_eq!(to_display_hex(&hash).len(), 64);
+//! ```
+use crate::digest::Sha256Digest;
+use crate::error::{Error, Result};
+use crate::sha256::sha256;
+
+/// SHA-256 applied twice, `SHA-256(SHA-256(msg))`
+/// # Examples
+/// ```
+/// use jisp_sha2::bitcoin::sha256d;
+///
+/// assert_eq!(sha256d(b"hello").to_hex(), "9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50");
+/// ```
+pub fn sha256d(msg:&[u8]) -> Sha256Digest {
+    sha256(sha256(msg).as_bytes())
+}
+
+/// Same as [sha256d] on [preprocessed](crate::preprocessing::sha256_preprocessing) message blocks
+#[cfg(feature = "bigint")]
+pub fn sha_256d(m:Vec<crypto_bigint::U512>) -> crypto_bigint::U256 {
+    let first = Sha256Digest::from(crate::sha_256(m));
+    crate::sha_256(crate::preprocessing::sha256_byte_preprocessing(first.as_bytes()))
+}
+
+/// The digest as hex in the byte-reversed order Bitcoin displays hashes in
+pub fn to_display_hex(digest:&Sha256Digest) -> String {
+    digest.as_bytes().iter().rev().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Reads a hash shown in Bitcoin's byte-reversed display order, such as a block hash from a block explorer
+/// # Examples
+/// ```
+/// use jisp_sha2::bitcoin::{from_display_hex, to_display_hex};
+///
+/// let hex = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+/// let digest = from_display_hex(hex).unwrap();
+/// assert_eq!(digest.as_bytes()[31], 0x00);
+/// assert_eq!(to_display_hex(&digest), hex);
+/// ```
+pub fn from_display_hex(hex:&str) -> Result<Sha256Digest> {
+    let hex = hex.trim();
+    if hex.len() != 64 {
+        return Err(Error::InvalidLength { expected: 64, found: hex.len() });
+    }
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(Error::InvalidParameters(format!("{} is not a hex string", hex)));
+    }
+    let mut bytes = [0u8; 32];
+    for (i, b) in bytes.iter_mut().rev().enumerate() {
+        *b = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|e| Error::InvalidParameters(e.to_string()))?;
+    }
+    Ok(Sha256Digest::from(bytes))
+}
+
+/// The hash of a serialized 80 byte block header
+pub fn block_hash(header:&[u8;80]) -> Sha256Digest {
+    sha256d(header)
+}
+
+/// The transaction ID of a raw serialized transaction.
+/// For a segwit transaction the marker, flag and witnesses are left out, as the txid does not commit to them.
+/// Returns [InvalidLength](Error::InvalidLength) if the transaction is truncated or followed by extra bytes
+/// and [InvalidParameters](Error::InvalidParameters) if a segwit flag is not `0x01`.
+pub fn txid(raw:&[u8]) -> Result<Sha256Digest> {
+    let mut reader = Reader { bytes: raw, pos: 0 };
+    let version = reader.take(4)?;
+    if reader.peek() != Some(0) {
+        reader.skip_legacy_body()?;
+        reader.take(4)?;
+        reader.finish()?;
+        return Ok(sha256d(raw));
+    }
+
+    //segwit: marker 0x00 and flag 0x01, then the inputs and outputs, then a witness per input
+    let flag = reader.take(2)?[1];
+    if flag != 0x01 {
+        return Err(Error::InvalidParameters(format!("unknown segwit flag {:#04x}", flag)));
+    }
+    let body_start = reader.pos;
+    let inputs = reader.skip_legacy_body()?;
+    let body = &raw[body_start..reader.pos];
+    for _ in 0..inputs {
+        let items = reader.varint()?;
+        for _ in 0..items {
+            let len = reader.varint()?;
+            reader.take(len)?;
+        }
+    }
+    let lock_time = reader.take(4)?;
+    reader.finish()?;
+
+    let mut stripped = Vec::with_capacity(raw.len());
+    stripped.extend_from_slice(version);
+    stripped.extend_from_slice(body);
+    stripped.extend_from_slice(lock_time);
+    Ok(sha256d(&stripped))
+}
+
+/// The witness transaction ID, the hash of the whole serialized transaction including any witnesses
+pub fn wtxid(raw:&[u8]) -> Sha256Digest {
+    sha256d(raw)
+}
+
+/// Walks over a serialized transaction without interpreting more of it than needed
+struct Reader<'a> {
+    bytes:&'a [u8],
+    pos:usize
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n:u64) -> Result<&'a [u8]> {
+        let end = usize::try_from(n).ok().and_then(|n| self.pos.checked_add(n));
+        match end {
+            Some(end) if end <= self.bytes.len() => {
+                let slice = &self.bytes[self.pos..end];
+                self.pos = end;
+                Ok(slice)
+            },
+            _ => Err(Error::InvalidLength { expected: end.unwrap_or(usize::MAX), found: self.bytes.len() }),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    /// Bitcoin's variable length integer, one byte or a prefix of `fd`, `fe` or `ff` followed by 2, 4 or 8 bytes
+    fn varint(&mut self) -> Result<u64> {
+        let size = match self.take(1)?[0] {
+            0xfd => 2,
+            0xfe => 4,
+            0xff => 8,
+            n => return Ok(n as u64),
+        };
+        let mut bytes = [0u8; 8];
+        bytes[..size as usize].copy_from_slice(self.take(size)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    /// Skips the inputs and outputs and returns the number of inputs
+    fn skip_legacy_body(&mut self) -> Result<u64> {
+        let inputs = self.varint()?;
+        for _ in 0..inputs {
+            //previous txid and output index
+            self.take(36)?;
+            let script = self.varint()?;
+            self.take(script)?;
+            //sequence
+            self.take(4)?;
+        }
+        let outputs = self.varint()?;
+        for _ in 0..outputs {
+            //value
+            self.take(8)?;
+            let script = self.varint()?;
+            self.take(script)?;
+        }
+        Ok(inputs)
+    }
+
+    fn finish(&self) -> Result<()> {
+        if self.pos != self.bytes.len() {
+            return Err(Error::InvalidLength { expected: self.pos, found: self.bytes.len() });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(hex:&str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn header(hex:&str) -> [u8;80] {
+        bytes(hex).try_into().unwrap()
+    }
+
+    const GENESIS_HEADER:&str = concat!(
+        "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e",
+        "67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c"
+    );
+
+    const GENESIS_COINBASE:&str = concat!(
+        "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d01",
+        "04455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f662073",
+        "65636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe55482719",
+        "67f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a",
+        "4c702b6bf11d5fac00000000"
+    );
+
+    #[test]
+    fn mainnet_block_hashes() {
+        let genesis = block_hash(&header(GENESIS_HEADER));
+        assert_eq!(to_display_hex(&genesis), "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f");
+
+        let block1 = header(concat!(
+            "010000006fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000982051fd1e4ba744bbbe680e",
+            "1fee14677ba1a3c3540bf7b1cdb606e857233e0e61bc6649ffff001d01e36299"
+        ));
+        assert_eq!(to_display_hex(&block_hash(&block1)), "00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048");
+        //block 1 points back to the genesis block
+        assert_eq!(block1[4..36], genesis.0);
+    }
+
+    #[test]
+    fn genesis_coinbase_txid() {
+        let id = txid(&bytes(GENESIS_COINBASE)).unwrap();
+        assert_eq!(to_display_hex(&id), "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b");
+        //with a single transaction the merkle root in the header is its txid
+        assert_eq!(header(GENESIS_HEADER)[36..68], id.0);
+        assert_eq!(wtxid(&bytes(GENESIS_COINBASE)), id);
+    }
+
+    const SEGWIT_TX:&str = concat!(
+        "0200000000010111111111111111111111111111111111111111111111111111111111111111110100000000fdffffff",
+        "01e80300000000000016001422222222222222222222222222222222222222220203aabbcc02ddee00000000"
+    );
+
+    #[test]
+    fn segwit_txid_leaves_out_witnesses() {
+        let raw = bytes(SEGWIT_TX);
+        assert_eq!(to_display_hex(&txid(&raw).unwrap()), "424c4ff3d83cd94effc7a81ebc42cfddc0db74e53908ea5e9bf2bfb4ddcd8544");
+        assert_eq!(to_display_hex(&wtxid(&raw)), "0246605d0dd845e8e18fb87e446ef7d06b8a1125c2a1e9d2ef9ffad2dc5d5207");
+    }
+
+    #[test]
+    fn malformed_transactions() {
+        let raw = bytes(GENESIS_COINBASE);
+        assert!(matches!(txid(&raw[..raw.len() - 1]), Err(Error::InvalidLength { .. })));
+        let mut extra = raw.clone();
+        extra.push(0);
+        assert!(matches!(txid(&extra), Err(Error::InvalidLength { .. })));
+        //only flag 0x01 is defined after the segwit marker
+        let mut segwit = bytes(SEGWIT_TX);
+        segwit[5] = 0x02;
+        assert!(matches!(txid(&segwit), Err(Error::InvalidParameters(_))));
+        assert!(from_display_hex("00").is_err());
+        assert!(from_display_hex(&"g".repeat(64)).is_err());
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn double_hash_of_blocks() {
+        let msg = b"hello";
+        let hash = sha_256d(crate::preprocessing::sha256_byte_preprocessing(msg));
+        assert_eq!(Sha256Digest::from(hash), sha256d(msg));
+    }
+}
@@ -0,0 +1,151 @@
+//! Computes the SHA-2 constants from their definition instead of copying them from a table.
+//! The round constants are the first bits of the fractional parts of the cube roots of the first 64 or 80 primes,
+//! the initial hash values are the first bits of the fractional parts of the square roots of the first 8 or 16 primes.
+//!
+//! The roots are computed with exact integer arithmetic, the fractional part of the `k`-th root of `p` to 64 bits
+//! is the integer `k`-th root of `p · 2^(64·k)` modulo `2^64`, so no floating point rounding is involved.
+//! The numbers involved need up to 256 bits, which a small fixed size multiplication on four 64 bit limbs covers.
+//! # Examples
+//! ```
+//! use jisp_sha2::constants::derivation::{cube_root_fraction, first_primes};
+//!
+//! assert_eq!(first_primes(4), vec![2, 3, 5, 7]);
+//! assert_eq!(cube_root_fraction(2), 0x428a2f98d728ae22);
+//! ```
+use std::cmp::Ordering;
+
+/// A 256 bit integer as four 64 bit limbs, least significant limb first
+type Wide = [u64;4];
+
+/// The first `n` prime numbers
+pub fn first_primes(n:usize) -> Vec<u64> {
+    let mut primes:Vec<u64> = Vec::with_capacity(n);
+    let mut candidate = 2;
+    while primes.len() < n {
+        if primes.iter().take_while(|p| *p * *p <= candidate).all(|p| candidate % p != 0) {
+            primes.push(candidate);
+        }
+        candidate += 1;
+    }
+    primes
+}
+
+/// The first 64 bits of the fractional part of the square root of `n`
+pub fn square_root_fraction(n:u64) -> u64 {
+    root_fraction(n, 2)
+}
+
+/// The first 64 bits of the fractional part of the cube root of `n`
+pub fn cube_root_fraction(n:u64) -> u64 {
+    root_fraction(n, 3)
+}
+
+fn root_fraction(n:u64, k:u32) -> u64 {
+    //shifting by a multiple of 64 bits just moves n up by k limbs
+    let mut scaled = [0u64; 4];
+    scaled[k as usize] = n;
+    integer_root(&scaled, k) as u64
+}
+
+/// The largest integer `r` with `r^k <= n` for `k` of at least 2, found one bit at a time from the top.
+/// Roots are below `2^(256/k)`, so they fit into a `u128` and their `k`-th power always fits into 256 bits.
+fn integer_root(n:&Wide, k:u32) -> u128 {
+    let mut root = 0u128;
+    for bit in (0..256 / k).rev() {
+        let candidate = root | (1 << bit);
+        let mut power = [1, 0, 0, 0];
+        for _ in 0..k {
+            power = mul_wide(&power, candidate);
+        }
+        if compare_wide(&power, n) != Ordering::Greater {
+            root = candidate;
+        }
+    }
+    root
+}
+
+/// `a · b` modulo `2^256`
+fn mul_wide(a:&Wide, b:u128) -> Wide {
+    let b = [b as u64, (b >> 64) as u64];
+    let mut res = [0u64; 4];
+    for (j, b_j) in b.iter().enumerate() {
+        let mut carry = 0u128;
+        for i in 0..4 - j {
+            let t = res[i + j] as u128 + a[i] as u128 * *b_j as u128 + carry;
+            res[i + j] = t as u64;
+            carry = t >> 64;
+        }
+    }
+    res
+}
+
+fn compare_wide(a:&Wide, b:&Wide) -> Ordering {
+    a.iter().rev().cmp(b.iter().rev())
+}
+
+/// The 80 round constants of SHA-512 and SHA-384
+pub fn sha512_round_constants() -> [u64;80] {
+    let primes = first_primes(80);
+    std::array::from_fn(|i| cube_root_fraction(primes[i]))
+}
+
+/// The 64 round constants of SHA-256 and SHA-224, the first 32 bits of those of [SHA-512](sha512_round_constants)
+pub fn sha256_round_constants() -> [u32;64] {
+    let k = sha512_round_constants();
+    std::array::from_fn(|i| (k[i] >> 32) as u32)
+}
+
+/// The initial hash value of SHA-512, from the first 8 primes
+pub fn sha512_initial_hash() -> [u64;8] {
+    let primes = first_primes(8);
+    std::array::from_fn(|i| square_root_fraction(primes[i]))
+}
+
+/// The initial hash value of SHA-384, from the 9th to the 16th prime
+pub fn sha384_initial_hash() -> [u64;8] {
+    let primes = first_primes(16);
+    std::array::from_fn(|i| square_root_fraction(primes[8 + i]))
+}
+
+/// The initial hash value of SHA-256, the first 32 bits of the [SHA-512 one](sha512_initial_hash)
+pub fn sha256_initial_hash() -> [u32;8] {
+    sha512_initial_hash().map(|w| (w >> 32) as u32)
+}
+
+/// The initial hash value of SHA-224, the second 32 bits of the [SHA-384 one](sha384_initial_hash)
+pub fn sha224_initial_hash() -> [u32;8] {
+    sha384_initial_hash().map(|w| w as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{Constants, Sha224, Sha256, Sha384, Sha512};
+
+    #[test]
+    fn round_constants_match_tables() {
+        assert_eq!(sha512_round_constants(), Sha512.constant_words());
+        assert_eq!(sha512_round_constants(), Sha384.constant_words());
+        assert_eq!(sha256_round_constants(), Sha256.constant_words());
+        assert_eq!(sha256_round_constants(), Sha224.constant_words());
+    }
+
+    #[test]
+    fn initial_hashes_match_tables() {
+        assert_eq!(sha512_initial_hash(), Sha512.initial_hash());
+        assert_eq!(sha384_initial_hash(), Sha384.initial_hash());
+        assert_eq!(sha256_initial_hash(), Sha256.initial_hash());
+        assert_eq!(sha224_initial_hash(), Sha224.initial_hash());
+    }
+
+    #[test]
+    fn exact_roots() {
+        assert_eq!(integer_root(&[1 << 20, 0, 0, 0], 2), 1 << 10);
+        assert_eq!(integer_root(&[26, 0, 0, 0], 3), 2);
+        assert_eq!(integer_root(&[27, 0, 0, 0], 3), 3);
+        //2^128 squared is just past the largest 256 bit number
+        assert_eq!(integer_root(&[u64::MAX; 4], 2), u128::MAX);
+        //perfect squares have no fractional part
+        assert_eq!(square_root_fraction(4), 0);
+    }
+}
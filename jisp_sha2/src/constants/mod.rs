@@ -1,12 +1,93 @@
 //! A collection of constant words for each of the different SHA-2 variations
-//! To use the SHA-2 algorithm with different constants you can implement the [Constants] trait
+//! To use the SHA-2 algorithm with different constants you can implement the [Constants] trait,
+//! or use [CustomConstants] for a set of constants that is only known at runtime.
+//! The [derivation] module computes the standard constants from the prime numbers they are defined by.
+//! With the `serde` feature both the standard constants and [CustomConstants] can be saved and loaded.
 
 /// Implement this for your constants to be used in the SHA-2 algorithm
 /// Note that the SHA-256 algorithm uses a list of 64 constant `u32` words.
 /// The SHA-512 algorithm uses a list of 80 constant `u64` words. 
 pub trait Constants<const KLEN:usize, T> {
-    fn constant_words() -> [T;KLEN];
-    fn initial_hash() -> [T;8];
+    fn constant_words(&self) -> [T;KLEN];
+    fn initial_hash(&self) -> [T;8];
+}
+
+/// Constants that are chosen at runtime, for example read from a file or generated while sweeping initial hash values.
+/// # Examples
+/// ```
+/// use jisp_sha2::constants::{Constants, CustomConstants, Sha256};
+/// use jisp_sha2::sha256::{Sha256Hasher, sha256};
+///
+/// let mut constants = CustomConstants::from_constants(&Sha256);
+/// let mut hasher = Sha256Hasher::with_constants(constants);
+/// hasher.update(b"abc");
+/// assert_eq!(hasher.finalize(), sha256(b"abc"));
+///
+/// constants.iv[0] ^= 1;
+/// let mut hasher = Sha256Hasher::with_constants(constants);
+/// hasher.update(b"abc");
+/// assert_ne!(hasher.finalize(), sha256(b"abc"));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CustomConstants<const KLEN:usize, T> {
+    /// the round constants
+    pub k:[T;KLEN],
+    /// the initial hash value
+    pub iv:[T;8]
+}
+
+impl<const KLEN:usize, T> CustomConstants<KLEN, T> {
+    pub fn new(k:[T;KLEN], iv:[T;8]) -> Self {
+        Self { k, iv }
+    }
+
+    /// A copy of another set of constants, such as the standard ones, to start changing from
+    pub fn from_constants(constants:&impl Constants<KLEN, T>) -> Self {
+        Self { k: constants.constant_words(), iv: constants.initial_hash() }
+    }
+}
+
+impl<const KLEN:usize, T:Copy> Constants<KLEN, T> for CustomConstants<KLEN, T> {
+    fn constant_words(&self) -> [T;KLEN] {
+        self.k
+    }
+
+    fn initial_hash(&self) -> [T;8] {
+        self.iv
+    }
+}
+
+/// Written as the two lists `k` and `iv`, reading checks that they have `KLEN` and 8 words
+#[cfg(feature = "serde")]
+impl<const KLEN:usize, T:serde::Serialize> serde::Serialize for CustomConstants<KLEN, T> {
+    fn serialize<S:serde::Serializer>(&self, serializer:S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("CustomConstants", 2)?;
+        state.serialize_field("k", &self.k[..])?;
+        state.serialize_field("iv", &self.iv[..])?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const KLEN:usize, T:serde::Deserialize<'de>> serde::Deserialize<'de> for CustomConstants<KLEN, T> {
+    fn deserialize<D:serde::Deserializer<'de>>(deserializer:D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "CustomConstants")]
+        struct Words<T> {
+            k:Vec<T>,
+            iv:Vec<T>
+        }
+
+        let words = Words::<T>::deserialize(deserializer)?;
+        let (k_len, iv_len) = (words.k.len(), words.iv.len());
+        let malformed = |name:&str, expected:usize, found:usize| {
+            serde::de::Error::custom(crate::Error::MalformedState(format!("expected {} words in {}, found {}", expected, name, found)))
+        };
+        let k = words.k.try_into().map_err(|_| malformed("k", KLEN, k_len))?;
+        let iv = words.iv.try_into().map_err(|_| malformed("iv", 8, iv_len))?;
+        Ok(Self { k, iv })
+    }
 }
 
 pub use sha256_constants::Sha256Constants as Sha256;
@@ -15,5 +96,32 @@ pub use sha256_constants::Sha224Constants as Sha224;
 pub use sha512_constants::Sha512Constants as Sha512;
 pub use sha512_constants::Sha384Constants as Sha384;
 
+pub mod derivation;
 mod sha256_constants;
-mod sha512_constants;
\ No newline at end of file
+mod sha512_constants;
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_round_trip() {
+        let mut constants = CustomConstants::from_constants(&Sha512);
+        constants.iv[3] = 42;
+        let json = serde_json::to_string(&constants).unwrap();
+        assert_eq!(serde_json::from_str::<CustomConstants<80, u64>>(&json).unwrap(), constants);
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&constants, &mut cbor).unwrap();
+        assert_eq!(ciborium::from_reader::<CustomConstants<80, u64>, _>(&cbor[..]).unwrap(), constants);
+
+        assert_eq!(serde_json::from_str::<Sha256>(&serde_json::to_string(&Sha256).unwrap()).unwrap(), Sha256);
+    }
+
+    #[test]
+    fn wrong_number_of_words() {
+        let json = serde_json::to_string(&CustomConstants::from_constants(&Sha256)).unwrap();
+        //SHA-256 constants have 16 round constants too few for SHA-512
+        let err = serde_json::from_str::<CustomConstants<80, u32>>(&json).unwrap_err();
+        assert!(err.to_string().contains("expected 80 words in k, found 64"));
+    }
+}
@@ -1,8 +1,10 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sha256Constants;
 
 impl super::Constants<64, u32> for Sha256Constants {
-    fn constant_words() -> [u32; 64] {
-        return [
+    fn constant_words(&self) -> [u32; 64] {
+        [
             0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
             0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
             0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
@@ -13,26 +15,28 @@ impl super::Constants<64, u32> for Sha256Constants {
             0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
             0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
             0xc67178f2,
-        ];
+        ]
     }
 
-    fn initial_hash() -> [u32; 8] {
-        return [
+    fn initial_hash(&self) -> [u32; 8] {
+        [
             0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
             0x5be0cd19,
-        ];
+        ]
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sha224Constants;
 
 impl super::Constants<64, u32> for Sha224Constants {
-    fn constant_words() -> [u32; 64] {
-        Sha256Constants::constant_words()
+    fn constant_words(&self) -> [u32; 64] {
+        Sha256Constants.constant_words()
     }
 
-    fn initial_hash() -> [u32; 8] {
-        return [
+    fn initial_hash(&self) -> [u32; 8] {
+        [
             0xc1059ed8, 
             0x367cd507, 
             0x3070dd17, 
@@ -41,7 +45,7 @@ impl super::Constants<64, u32> for Sha224Constants {
             0x68581511, 
             0x64f98fa7,
             0xbefa4fa4
-        ];
+        ]
     }
 }
 
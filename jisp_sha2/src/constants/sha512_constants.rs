@@ -1,8 +1,10 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sha512Constants;
 
 impl super::Constants<80, u64> for Sha512Constants {
-    fn constant_words() -> [u64; 80] {
-        return [
+    fn constant_words(&self) -> [u64; 80] {
+        [
             0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
             0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
             0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
@@ -23,11 +25,11 @@ impl super::Constants<80, u64> for Sha512Constants {
             0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
             0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
             0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817
-        ];
+        ]
     }
 
-    fn initial_hash() -> [u64; 8] {
-        return [
+    fn initial_hash(&self) -> [u64; 8] {
+        [
             0x6a09e667f3bcc908,
             0xbb67ae8584caa73b,
             0x3c6ef372fe94f82b,
@@ -36,19 +38,21 @@ impl super::Constants<80, u64> for Sha512Constants {
             0x9b05688c2b3e6c1f,
             0x1f83d9abfb41bd6b,
             0x5be0cd19137e2179
-        ];
+        ]
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sha384Constants;
 
 impl super::Constants<80, u64> for Sha384Constants {
-    fn constant_words() -> [u64; 80] {
-        Sha512Constants::constant_words()
+    fn constant_words(&self) -> [u64; 80] {
+        Sha512Constants.constant_words()
     }
 
-    fn initial_hash() -> [u64; 8] {
-        return [
+    fn initial_hash(&self) -> [u64; 8] {
+        [
             0xcbbb9d5dc1059ed8
             , 0x629a292a367cd507
             , 0x9159015a3070dd17
@@ -57,6 +61,6 @@ impl super::Constants<80, u64> for Sha384Constants {
             , 0x8eb44a8768581511
             , 0xdb0c2e0d64f98fa7
             , 0x47b5481dbefa4fa4
-        ];
+        ]
     }
 }
\ No newline at end of file
@@ -1,9 +1,30 @@
-//! Some functions to transform [Uint](crypto_bigint::Uint) integers into and from lists of `u32` and `u64` integers.
+//! Some functions to transform [Uint](crypto_bigint::Uint) integers into and from lists of `u32` and `u64` integers,
+//! and to transform word arrays into and from byte arrays. Only the word and byte functions are available without the `bigint` feature.
+//!
+//! The `_array` and byte functions never modify their input and return arrays of a fixed length.
+//! Their lengths are checked when compiling, a mismatch such as 15 `u32` words for a [U512](crypto_bigint::U512) does not build.
+//! # Examples
+//! ```
+//! use jisp_sha2::conversions::{u32_words_from_be_bytes, u32_words_to_be_bytes, u32_words_to_le_bytes};
+//!
+//! let words = [0x01234567, 0x89abcdef];
+//! let bytes:[u8;8] = u32_words_to_be_bytes(&words);
+//! assert_eq!(bytes, [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef]);
+//! assert_eq!(u32_words_from_be_bytes::<2, 8>(&bytes), words);
+//!
+//! let little:[u8;8] = u32_words_to_le_bytes(&words);
+//! assert_eq!(little[..4], [0x67, 0x45, 0x23, 0x01]);
+//! ```
+#[cfg(feature = "bigint")]
 use crypto_bigint::Uint;
 
+#[cfg(feature = "bigint")]
+use crate::error::{Error, Result};
 
 
-/// Takes an array of words and transforms it into a big integer. Resets the array to all 0s in the process
+
+/// Takes an array of words and transforms it into a big integer. Resets the array to all 0s in the process,
+/// [from_u64_array] does the same without touching the array
 /// # Examples
 /// ```
 /// use jisp_sha2::conversions::from_u64_words;
@@ -15,11 +36,13 @@ use crypto_bigint::Uint;
 /// assert_eq!(block, U128::from(1u8));
 /// assert_eq!(words, [0;2]);
 /// ```
+#[deprecated(note = "zeroes the words as a side effect, use from_u64_array instead")]
+#[cfg(feature = "bigint")]
 pub fn from_u64_words<const BLOCK: usize>(words: &mut [u64; BLOCK]) -> Uint<BLOCK> {
     words.reverse();
     let res = Uint::<BLOCK>::from_words(*words);
     *words = [0; BLOCK];
-    return res;
+    res
 }
 
 /// Seperates a big integer into its u64 words. It does this in big endian oder instead of the default little endian order i.e. the words are ordered most significant word first
@@ -32,13 +55,15 @@ pub fn from_u64_words<const BLOCK: usize>(words: &mut [u64; BLOCK]) -> Uint<BLOC
 ///
 /// assert_eq!(words, [0, 1]);
 /// ```
+#[cfg(feature = "bigint")]
 pub fn to_u64_words<const BLOCK: usize>(u: Uint<BLOCK>) -> [u64; BLOCK] {
-    let mut words = u.as_words().clone();
+    let mut words = *u.as_words();
     words.reverse();
-    return words;
+    words
 }
 
-/// Takes an array of u32 words and transforms it into a big integer. Panics if the length of the vector is not 2 times the block size
+/// Takes an array of u32 words and transforms it into a big integer. Panics if the length of the vector is not 2 times the block size,
+/// use [try_from_u32_words] to get an [Error](crate::Error) instead
 /// # Examples
 /// ```
 /// use jisp_sha2::conversions::from_u32_words;
@@ -49,13 +74,20 @@ pub fn to_u64_words<const BLOCK: usize>(u: Uint<BLOCK>) -> [u64; BLOCK] {
 ///
 /// assert_eq!(block, U64::from(1u8));
 /// ```
+#[cfg(feature = "bigint")]
+#[allow(clippy::ptr_arg)]
 pub fn from_u32_words<const BLOCK: usize>(words_vec: &Vec<u32>) -> Uint<BLOCK> {
+    match try_from_u32_words(words_vec) {
+        Ok(res) => res,
+        Err(e) => panic!("Length of words_vec does not match 2 times the BLOCK length: {}", e),
+    }
+}
+
+/// Same as [from_u32_words] but returns [InvalidLength](crate::Error::InvalidLength) if the length of the slice is not 2 times the block size
+#[cfg(feature = "bigint")]
+pub fn try_from_u32_words<const BLOCK: usize>(words_vec: &[u32]) -> Result<Uint<BLOCK>> {
     if words_vec.len() != 2 * BLOCK {
-        panic!(
-            "Length of words_vec: {}\n is not 2 times the BLOCK length: {}",
-            words_vec.len(),
-            BLOCK
-        );
+        return Err(Error::InvalidLength { expected: 2 * BLOCK, found: words_vec.len() });
     }
 
     let mut words = [0u64; BLOCK];
@@ -66,7 +98,7 @@ pub fn from_u32_words<const BLOCK: usize>(words_vec: &Vec<u32>) -> Uint<BLOCK> {
     }
     words.reverse();
     let res = Uint::<BLOCK>::from_words(words);
-    return res;
+    Ok(res)
 }
 
 /// Seperates a big integer into its u32 words. It does this in big endian oder instead of the default little endian order i.e. the words are ordered most significant word first.
@@ -80,8 +112,10 @@ pub fn from_u32_words<const BLOCK: usize>(words_vec: &Vec<u32>) -> Uint<BLOCK> {
 ///
 /// assert_eq!(words, [0, 1]);
 /// ```
+#[deprecated(note = "use to_u32_array")]
+#[cfg(feature = "bigint")]
 pub fn to_u32_words<const BLOCK: usize>(u: Uint<BLOCK>) -> Vec<u32> {
-    let mut words = u.as_words().clone();
+    let mut words = *u.as_words();
     words.reverse();
     let mut split = vec![0; BLOCK * 2];
 
@@ -92,5 +126,162 @@ pub fn to_u32_words<const BLOCK: usize>(u: Uint<BLOCK>) -> Vec<u32> {
         split[2 * i + 1] = lower;
     }
 
-    return split.to_vec();
-}
\ No newline at end of file
+    split.to_vec()
+}
+
+/// Transforms an array of u64 words, most significant word first, into a big integer without modifying the words
+/// # Examples
+/// ```
+/// use jisp_sha2::conversions::from_u64_array;
+/// use crypto_bigint::U128;
+///
+/// let words = [0, 1];
+/// assert_eq!(from_u64_array(&words), U128::from(1u8));
+/// assert_eq!(words, [0, 1]);
+/// ```
+#[cfg(feature = "bigint")]
+pub fn from_u64_array<const BLOCK: usize>(words: &[u64; BLOCK]) -> Uint<BLOCK> {
+    let mut words = *words;
+    words.reverse();
+    Uint::<BLOCK>::from_words(words)
+}
+
+/// Transforms an array of u32 words, most significant word first, into a big integer. `N` has to be twice `BLOCK`
+/// # Examples
+/// A wrong number of words does not compile
+/// ```compile_fail
+/// use jisp_sha2::conversions::from_u32_array;
+///
+/// let u = from_u32_array::<8, 15>(&[0; 15]);
+/// ```
+#[cfg(feature = "bigint")]
+pub fn from_u32_array<const BLOCK: usize, const N: usize>(words: &[u32; N]) -> Uint<BLOCK> {
+    const { assert!(N == 2 * BLOCK, "N has to be 2 times BLOCK") };
+    let mut res = [0u64; BLOCK];
+    for i in 0..BLOCK {
+        res[i] = ((words[2 * i] as u64) << 32) | words[2 * i + 1] as u64;
+    }
+    from_u64_array(&res)
+}
+
+/// Seperates a big integer into its u32 words, most significant word first, `N` has to be twice `BLOCK`
+/// # Examples
+/// ```
+/// use jisp_sha2::conversions::to_u32_array;
+/// use crypto_bigint::U64;
+///
+/// let words:[u32;2] = to_u32_array(U64::from(1u8));
+/// assert_eq!(words, [0, 1]);
+/// ```
+#[cfg(feature = "bigint")]
+pub fn to_u32_array<const BLOCK: usize, const N: usize>(u: Uint<BLOCK>) -> [u32; N] {
+    const { assert!(N == 2 * BLOCK, "N has to be 2 times BLOCK") };
+    let words = to_u64_words(u);
+    std::array::from_fn(|i| (words[i / 2] >> (32 * (1 - i % 2))) as u32)
+}
+
+/// The big endian bytes of a big integer, `N` has to be 8 times `BLOCK`
+/// # Examples
+/// ```
+/// use jisp_sha2::conversions::{from_be_bytes, to_be_bytes, to_u32_array};
+/// use crypto_bigint::U64;
+///
+/// let u = U64::from_be_hex("0123456789abcdef");
+/// let bytes:[u8;8] = to_be_bytes(u);
+/// assert_eq!(bytes, [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef]);
+/// assert_eq!(from_be_bytes::<1, 8>(&bytes), u);
+///
+/// let words:[u32;2] = to_u32_array(u);
+/// assert_eq!(words, [0x01234567, 0x89abcdef]);
+/// ```
+#[cfg(feature = "bigint")]
+pub fn to_be_bytes<const BLOCK: usize, const N: usize>(u: Uint<BLOCK>) -> [u8; N] {
+    const { assert!(N == 8 * BLOCK, "N has to be 8 times BLOCK") };
+    u64_words_to_be_bytes(&to_u64_words(u))
+}
+
+/// Reads a big integer from big endian bytes, `N` has to be 8 times `BLOCK`
+#[cfg(feature = "bigint")]
+pub fn from_be_bytes<const BLOCK: usize, const N: usize>(bytes: &[u8; N]) -> Uint<BLOCK> {
+    const { assert!(N == 8 * BLOCK, "N has to be 8 times BLOCK") };
+    from_u64_array(&u64_words_from_be_bytes(bytes))
+}
+
+macro_rules! word_bytes {
+    ($t:ty, $size:expr, $to_be:ident, $to_le:ident, $from_be:ident, $from_le:ident) => {
+        #[doc = concat!("The bytes of `", stringify!($t), "` words, each word big endian. `N` has to be ", stringify!($size), " times `W`")]
+        pub fn $to_be<const W: usize, const N: usize>(words: &[$t; W]) -> [u8; N] {
+            const { assert!(N == $size * W, "the byte array does not fit the words") };
+            std::array::from_fn(|i| words[i / $size].to_be_bytes()[i % $size])
+        }
+
+        #[doc = concat!("The bytes of `", stringify!($t), "` words, each word little endian. `N` has to be ", stringify!($size), " times `W`")]
+        pub fn $to_le<const W: usize, const N: usize>(words: &[$t; W]) -> [u8; N] {
+            const { assert!(N == $size * W, "the byte array does not fit the words") };
+            std::array::from_fn(|i| words[i / $size].to_le_bytes()[i % $size])
+        }
+
+        #[doc = concat!("Reads `", stringify!($t), "` words from big endian bytes. `N` has to be ", stringify!($size), " times `W`")]
+        pub fn $from_be<const W: usize, const N: usize>(bytes: &[u8; N]) -> [$t; W] {
+            const { assert!(N == $size * W, "the byte array does not fit the words") };
+            std::array::from_fn(|i| {
+                let mut word = [0u8; $size];
+                word.copy_from_slice(&bytes[$size * i..$size * (i + 1)]);
+                <$t>::from_be_bytes(word)
+            })
+        }
+
+        #[doc = concat!("Reads `", stringify!($t), "` words from little endian bytes. `N` has to be ", stringify!($size), " times `W`")]
+        pub fn $from_le<const W: usize, const N: usize>(bytes: &[u8; N]) -> [$t; W] {
+            const { assert!(N == $size * W, "the byte array does not fit the words") };
+            std::array::from_fn(|i| {
+                let mut word = [0u8; $size];
+                word.copy_from_slice(&bytes[$size * i..$size * (i + 1)]);
+                <$t>::from_le_bytes(word)
+            })
+        }
+    };
+}
+
+word_bytes!(u32, 4, u32_words_to_be_bytes, u32_words_to_le_bytes, u32_words_from_be_bytes, u32_words_from_le_bytes);
+word_bytes!(u64, 8, u64_words_to_be_bytes, u64_words_to_le_bytes, u64_words_from_be_bytes, u64_words_from_le_bytes);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    #[allow(deprecated)]
+    fn conversions_round_trip() {
+        use crypto_bigint::{U256, U512};
+        let u = U512::from_be_hex(concat!(
+            "00112233445566778899aabbccddeeff0123456789abcdeffedcba9876543210",
+            "0f1e2d3c4b5a69788796a5b4c3d2e1f0deadbeefcafebabe0badf00d12345678"
+        ));
+        let words:[u32;16] = to_u32_array(u);
+        assert_eq!(words.to_vec(), to_u32_words(u));
+        assert_eq!(from_u32_array::<8, 16>(&words), u);
+        assert_eq!(from_u64_array(&to_u64_words(u)), u);
+
+        let bytes:[u8;64] = to_be_bytes(u);
+        assert_eq!(bytes[..4], [0x00, 0x11, 0x22, 0x33]);
+        assert_eq!(from_be_bytes::<8, 64>(&bytes), u);
+        assert_eq!(u32_words_to_be_bytes::<16, 64>(&words), bytes);
+        assert_eq!(u32_words_from_be_bytes::<16, 64>(&bytes), words);
+        assert_eq!(from_u64_array(&u64_words_from_be_bytes::<4, 32>(&to_be_bytes(U256::ONE))), U256::ONE);
+    }
+
+    #[test]
+    fn little_endian_words() {
+        let words = [0x01234567u32, 0x89abcdef];
+        let bytes:[u8;8] = u32_words_to_le_bytes(&words);
+        assert_eq!(bytes, [0x67, 0x45, 0x23, 0x01, 0xef, 0xcd, 0xab, 0x89]);
+        assert_eq!(u32_words_from_le_bytes::<2, 8>(&bytes), words);
+
+        let long:[u8;16] = u64_words_to_le_bytes(&[1u64, 2]);
+        assert_eq!(long[0], 1);
+        assert_eq!(long[8], 2);
+        assert_eq!(u64_words_from_le_bytes::<2, 16>(&long), [1, 2]);
+    }
+}
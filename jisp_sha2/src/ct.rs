@@ -0,0 +1,333 @@
+//! The Merkle tree hash of Certificate Transparency from RFC 6962 and RFC 9162, on SHA-256.
+//!
+//! Unlike the configurable trees in [merkle](crate::merkle) this follows the RFCs exactly: leaves are hashed as `SHA-256(0x00 || entry)`,
+//! nodes as `SHA-256(0x01 || left || right)` and a tree of `n` leaves splits after the largest power of two below `n`.
+//! A [Log] keeps the leaf hashes, so it can give the root, audit paths and consistency proofs for any earlier size of the tree,
+//! and [verify_inclusion] and [verify_consistency] check them against a signed tree head with only the roots at hand.
+//! # Examples
+//! ```
+//! use jisp_sha2::ct::{leaf_hash, verify_consistency, verify_inclusion, Log};
+//!
+//! let mut log = Log::new();
+//! for entry in ["a", "b", "c", "d", "e"] {
+//!     log.append(entry.as_bytes());
+//! }
+//! let old_root = log.root_at(3).unwrap();
+//!
+//! let path = log.audit_path(1, 5).unwrap();
+//! assert!(verify_inclusion(1, 5, &leaf_hash(b"b"), &path, &log.root()));
+//!
+//! let proof = log.consistency_proof(3, 5).unwrap();
+//! assert!(verify_consistency(3, 5, &old_root, &log.root(), &proof));
+//! ```
+use crate::constants::Sha256;
+use crate::digest::Sha256Digest;
+use crate::error::{Error, Result};
+use crate::sha256::{sha256, Sha256Hasher};
+
+const LEAF_PREFIX:u8 = 0x00;
+const NODE_PREFIX:u8 = 0x01;
+
+/// The hash of a log entry as it enters the tree, `SHA-256(0x00 || entry)`
+pub fn leaf_hash(entry:&[u8]) -> Sha256Digest {
+    let mut hasher = Sha256Hasher::<Sha256>::new();
+    hasher.update(&[LEAF_PREFIX]);
+    hasher.update(entry);
+    hasher.finalize()
+}
+
+/// The hash of an inner node, `SHA-256(0x01 || left || right)`
+pub fn node_hash(left:&Sha256Digest, right:&Sha256Digest) -> Sha256Digest {
+    let mut hasher = Sha256Hasher::<Sha256>::new();
+    hasher.update(&[NODE_PREFIX]);
+    hasher.update(left.as_bytes());
+    hasher.update(right.as_bytes());
+    hasher.finalize()
+}
+
+/// MTH, the root of a tree with these leaf hashes. The root of the empty tree is the hash of the empty string.
+pub fn tree_hash(leaves:&[Sha256Digest]) -> Sha256Digest {
+    match leaves.len() {
+        0 => sha256(&[]),
+        1 => leaves[0],
+        n => {
+            let k = split(n);
+            node_hash(&tree_hash(&leaves[..k]), &tree_hash(&leaves[k..]))
+        },
+    }
+}
+
+/// The largest power of two smaller than `n`, where a tree of `n > 1` leaves is split
+fn split(n:usize) -> usize {
+    let p = n.next_power_of_two();
+    if p == n { n / 2 } else { p / 2 }
+}
+
+/// PATH(m, D[n]) of RFC 6962, the siblings from leaf `m` up to the root
+fn path(m:usize, leaves:&[Sha256Digest]) -> Vec<Sha256Digest> {
+    let n = leaves.len();
+    if n <= 1 {
+        return Vec::new();
+    }
+    let k = split(n);
+    let (mut proof, sibling) = if m < k {
+        (path(m, &leaves[..k]), tree_hash(&leaves[k..]))
+    } else {
+        (path(m - k, &leaves[k..]), tree_hash(&leaves[..k]))
+    };
+    proof.push(sibling);
+    proof
+}
+
+/// SUBPROOF(m, D[n], b) of RFC 6962, `complete` tells whether the subtree of size `m` is a complete subtree of the old tree
+fn subproof(m:usize, leaves:&[Sha256Digest], complete:bool) -> Vec<Sha256Digest> {
+    let n = leaves.len();
+    if m == n {
+        return if complete { Vec::new() } else { vec![tree_hash(leaves)] };
+    }
+    let k = split(n);
+    let (mut proof, sibling) = if m <= k {
+        (subproof(m, &leaves[..k], complete), tree_hash(&leaves[k..]))
+    } else {
+        (subproof(m - k, &leaves[k..], false), tree_hash(&leaves[..k]))
+    };
+    proof.push(sibling);
+    proof
+}
+
+/// An append-only log that keeps every leaf hash
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Log {
+    leaves:Vec<Sha256Digest>
+}
+
+impl Log {
+    pub fn new() -> Self {
+        Self { leaves: Vec::new() }
+    }
+
+    /// A log from leaf hashes that were computed before, for example with [leaf_hash]
+    pub fn from_leaf_hashes(leaves:Vec<Sha256Digest>) -> Self {
+        Self { leaves }
+    }
+
+    /// Adds an entry and returns its index
+    pub fn append(&mut self, entry:&[u8]) -> usize {
+        self.leaves.push(leaf_hash(entry));
+        self.leaves.len() - 1
+    }
+
+    /// The number of entries
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn leaf_hashes(&self) -> &[Sha256Digest] {
+        &self.leaves
+    }
+
+    /// The root of the current tree
+    pub fn root(&self) -> Sha256Digest {
+        tree_hash(&self.leaves)
+    }
+
+    /// The root the tree had when it held the first `size` entries
+    pub fn root_at(&self, size:usize) -> Result<Sha256Digest> {
+        self.check_size(size)?;
+        Ok(tree_hash(&self.leaves[..size]))
+    }
+
+    /// The audit path of the entry at `index` in the tree of the first `size` entries
+    pub fn audit_path(&self, index:usize, size:usize) -> Result<Vec<Sha256Digest>> {
+        self.check_size(size)?;
+        if index >= size {
+            return Err(Error::InvalidParameters(format!("entry {} is not in a tree of {} entries", index, size)));
+        }
+        Ok(path(index, &self.leaves[..size]))
+    }
+
+    /// The proof that the tree of the first `new` entries extends the tree of the first `old`, for `0 < old <= new`
+    pub fn consistency_proof(&self, old:usize, new:usize) -> Result<Vec<Sha256Digest>> {
+        self.check_size(new)?;
+        if old == 0 || old > new {
+            return Err(Error::InvalidParameters(format!("no consistency proof from size {} to size {}", old, new)));
+        }
+        Ok(subproof(old, &self.leaves[..new], true))
+    }
+
+    fn check_size(&self, size:usize) -> Result<()> {
+        if size > self.leaves.len() {
+            return Err(Error::InvalidParameters(format!("the log has {} entries, not {}", self.leaves.len(), size)));
+        }
+        Ok(())
+    }
+}
+
+/// Checks an audit path with the algorithm of RFC 9162 section 2.1.3.2
+pub fn verify_inclusion(index:u64, size:u64, leaf:&Sha256Digest, path:&[Sha256Digest], root:&Sha256Digest) -> bool {
+    if index >= size {
+        return false;
+    }
+    let (mut f, mut s) = (index, size - 1);
+    let mut r = *leaf;
+    for p in path {
+        if s == 0 {
+            return false;
+        }
+        if f & 1 == 1 || f == s {
+            r = node_hash(p, &r);
+            while f & 1 == 0 && f != 0 {
+                f >>= 1;
+                s >>= 1;
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+        f >>= 1;
+        s >>= 1;
+    }
+    s == 0 && r == *root
+}
+
+/// Checks a consistency proof between two tree heads with the algorithm of RFC 9162 section 2.1.4.2.
+/// Equal sizes are consistent with an empty proof and equal roots, an empty old tree is never accepted.
+pub fn verify_consistency(old:u64, new:u64, old_root:&Sha256Digest, new_root:&Sha256Digest, proof:&[Sha256Digest]) -> bool {
+    if old == 0 || old > new {
+        return false;
+    }
+    if old == new {
+        return proof.is_empty() && old_root == new_root;
+    }
+    //an old tree that is a complete subtree is left out of the proof
+    let first = if old.is_power_of_two() { Some(old_root) } else { None };
+    let mut path = first.into_iter().chain(proof);
+    let Some(start) = path.next() else { return false };
+
+    let (mut f, mut s) = (old - 1, new - 1);
+    while f & 1 == 1 {
+        f >>= 1;
+        s >>= 1;
+    }
+    let (mut fr, mut sr) = (*start, *start);
+    for c in path {
+        if s == 0 {
+            return false;
+        }
+        if f & 1 == 1 || f == s {
+            fr = node_hash(c, &fr);
+            sr = node_hash(c, &sr);
+            while f & 1 == 0 && f != 0 {
+                f >>= 1;
+                s >>= 1;
+            }
+        } else {
+            sr = node_hash(&sr, c);
+        }
+        f >>= 1;
+        s >>= 1;
+    }
+    fr == *old_root && sr == *new_root && s == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //the leaves of the test vectors used by the Certificate Transparency implementations
+    const ENTRIES:[&[u8]; 8] = [
+        b"", b"\x00", b"\x10", b"\x20\x21", b"\x30\x31", b"\x40\x41\x42\x43",
+        b"\x50\x51\x52\x53\x54\x55\x56\x57", b"\x60\x61\x62\x63\x64\x65\x66\x67\x68\x69\x6a\x6b\x6c\x6d\x6e\x6f"
+    ];
+
+    const ROOTS:[&str; 8] = [
+        "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+        "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+        "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+        "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+        "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+        "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+        "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+        "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328"
+    ];
+
+    fn log() -> Log {
+        let mut log = Log::new();
+        for entry in ENTRIES {
+            log.append(entry);
+        }
+        log
+    }
+
+    #[test]
+    fn roots() {
+        let log = log();
+        for (i, root) in ROOTS.iter().enumerate() {
+            assert_eq!(log.root_at(i + 1).unwrap().to_hex(), *root);
+        }
+        assert_eq!(Log::new().root().to_hex(), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert!(log.root_at(9).is_err());
+    }
+
+    //the tree of seven entries drawn in section 2.1.3 of RFC 6962
+    #[test]
+    fn rfc_examples() {
+        let log = log();
+        let [a, b, c, d, e, f, j] = <[Sha256Digest; 7]>::try_from(&log.leaf_hashes()[..7]).unwrap();
+        let (g, h, i) = (node_hash(&a, &b), node_hash(&c, &d), node_hash(&e, &f));
+        let (k, l) = (node_hash(&g, &h), node_hash(&i, &j));
+
+        assert_eq!(log.audit_path(0, 7).unwrap(), [b, h, l]);
+        assert_eq!(log.audit_path(3, 7).unwrap(), [c, g, l]);
+        assert_eq!(log.audit_path(4, 7).unwrap(), [f, j, k]);
+        assert_eq!(log.audit_path(6, 7).unwrap(), [i, k]);
+
+        assert_eq!(log.consistency_proof(3, 7).unwrap(), [c, d, g, l]);
+        assert_eq!(log.consistency_proof(4, 7).unwrap(), [l]);
+        assert_eq!(log.consistency_proof(6, 7).unwrap(), [i, j, k]);
+    }
+
+    #[test]
+    fn every_proof_verifies() {
+        let log = log();
+        for size in 1..=8 {
+            let root = log.root_at(size).unwrap();
+            for index in 0..size {
+                let path = log.audit_path(index, size).unwrap();
+                let leaf = log.leaf_hashes()[index];
+                assert!(verify_inclusion(index as u64, size as u64, &leaf, &path, &root));
+                if index ^ 1 < size {
+                    assert!(!verify_inclusion((index ^ 1) as u64, size as u64, &leaf, &path, &root));
+                }
+                assert!(!verify_inclusion(index as u64, size as u64, &leaf_hash(b"x"), &path, &root));
+                if !path.is_empty() {
+                    assert!(!verify_inclusion(index as u64, size as u64, &leaf, &path[1..], &root));
+                }
+            }
+            for old in 1..=size {
+                let old_root = log.root_at(old).unwrap();
+                let proof = log.consistency_proof(old, size).unwrap();
+                assert!(verify_consistency(old as u64, size as u64, &old_root, &root, &proof));
+                assert!(!verify_consistency(old as u64, size as u64, &root, &old_root, &proof) || old == size);
+                if let Some((_, rest)) = proof.split_first() {
+                    assert!(!verify_consistency(old as u64, size as u64, &old_root, &root, rest));
+                }
+            }
+        }
+        assert!(!verify_consistency(0, 3, &Log::new().root(), &log.root_at(3).unwrap(), &[]));
+        assert!(log.consistency_proof(0, 3).is_err());
+        assert!(log.audit_path(3, 3).is_err());
+    }
+
+    #[test]
+    fn matches_generic_tree() {
+        use crate::merkle::{Config, MerkleTree};
+        for size in 1..=8 {
+            let tree = MerkleTree::<Sha256Hasher<Sha256>>::new(Config::default(), &ENTRIES[..size]).unwrap();
+            assert_eq!(tree.root(), log().root_at(size).unwrap().as_bytes());
+        }
+    }
+}
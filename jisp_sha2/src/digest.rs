@@ -0,0 +1,185 @@
+//! The result of a hash as a plain array of big endian bytes, so no big integer type is needed to use it.
+//! With the `bigint` feature a [Digest] converts from and into the matching [Uint](crypto_bigint::Uint).
+//! With the `serde` feature it is written as a hex string in human readable formats such as JSON and as raw bytes in binary ones such as CBOR.
+//! # Examples
+//! ```
+//! use jisp_sha2::sha256;
+//!
+//! let digest = sha256(b"abc");
+//! assert_eq!(digest.as_bytes()[..4], [0xba, 0x78, 0x16, 0xbf]);
+//! assert_eq!(digest.to_string(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+//! ```
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Digest<const N:usize>(pub [u8;N]);
+
+/// The 32 byte result of SHA-256
+pub type Sha256Digest = Digest<32>;
+/// The 28 byte result of SHA-224
+pub type Sha224Digest = Digest<28>;
+/// The 64 byte result of SHA-512
+pub type Sha512Digest = Digest<64>;
+/// The 48 byte result of SHA-384
+pub type Sha384Digest = Digest<48>;
+
+impl<const N:usize> Digest<N> {
+    pub fn as_bytes(&self) -> &[u8;N] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> [u8;N] {
+        self.0
+    }
+
+    /// The bytes as lowercase hex without any spaces
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+impl<const N:usize> fmt::Display for Digest<N> {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl<const N:usize> From<[u8;N]> for Digest<N> {
+    fn from(bytes:[u8;N]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl<const N:usize> AsRef<[u8]> for Digest<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(feature = "bigint")]
+macro_rules! impl_uint {
+    ($($n:literal => $uint:ty),*) => {$(
+        impl From<Digest<$n>> for $uint {
+            fn from(digest:Digest<$n>) -> Self {
+                <$uint as crypto_bigint::Encoding>::from_be_bytes(digest.0)
+            }
+        }
+
+        impl From<$uint> for Digest<$n> {
+            fn from(u:$uint) -> Self {
+                Digest(crypto_bigint::Encoding::to_be_bytes(&u))
+            }
+        }
+    )*};
+}
+
+#[cfg(feature = "bigint")]
+impl_uint!(32 => crypto_bigint::U256, 48 => crypto_bigint::U384, 64 => crypto_bigint::U512);
+
+#[cfg(feature = "serde")]
+impl<const N:usize> serde::Serialize for Digest<N> {
+    fn serialize<S:serde::Serializer>(&self, serializer:S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_hex())
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const N:usize> serde::Deserialize<'de> for Digest<N> {
+    fn deserialize<D:serde::Deserializer<'de>>(deserializer:D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(DigestVisitor::<N>)
+        } else {
+            deserializer.deserialize_bytes(DigestVisitor::<N>)
+        }
+    }
+}
+
+/// Accepts a hex string, bytes or a sequence of bytes of exactly `N` bytes
+#[cfg(feature = "serde")]
+struct DigestVisitor<const N:usize>;
+
+#[cfg(feature = "serde")]
+impl<'de, const N:usize> serde::de::Visitor<'de> for DigestVisitor<N> {
+    type Value = Digest<N>;
+
+    fn expecting(&self, f:&mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a digest of {} bytes", N)
+    }
+
+    fn visit_str<E:serde::de::Error>(self, v:&str) -> Result<Self::Value, E> {
+        if !v.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(E::invalid_value(serde::de::Unexpected::Str(v), &self));
+        }
+        if v.len() != 2 * N {
+            return Err(E::invalid_length(v.len() / 2, &self));
+        }
+        let mut bytes = [0u8; N];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = u8::from_str_radix(&v[2 * i..2 * i + 2], 16).map_err(E::custom)?;
+        }
+        Ok(Digest(bytes))
+    }
+
+    fn visit_bytes<E:serde::de::Error>(self, v:&[u8]) -> Result<Self::Value, E> {
+        let bytes:[u8;N] = v.try_into().map_err(|_| E::invalid_length(v.len(), &self))?;
+        Ok(Digest(bytes))
+    }
+
+    fn visit_seq<A:serde::de::SeqAccess<'de>>(self, mut seq:A) -> Result<Self::Value, A::Error> {
+        let mut bytes = [0u8; N];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = seq.next_element()?.ok_or_else(|| serde::de::Error::invalid_length(i, &self))?;
+        }
+        if seq.next_element::<u8>()?.is_some() {
+            return Err(serde::de::Error::invalid_length(N + 1, &self));
+        }
+        Ok(Digest(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_and_bytes() {
+        let digest = Digest([0x00, 0x0f, 0xa0, 0xff]);
+        assert_eq!(digest.to_hex(), "000fa0ff");
+        assert_eq!(format!("{}", digest), "000fa0ff");
+        assert_eq!(Digest::from(digest.into_bytes()), digest);
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn uint_conversions() {
+        use crypto_bigint::U256;
+        let u = U256::from_be_hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        let digest = Sha256Digest::from(u);
+        assert_eq!(digest, crate::sha256(b"abc"));
+        assert_eq!(U256::from(digest), u);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let digest = crate::sha256(b"abc");
+        let json = serde_json::to_string(&digest).unwrap();
+        assert_eq!(json, format!("\"{}\"", digest.to_hex()));
+        assert_eq!(serde_json::from_str::<Sha256Digest>(&json).unwrap(), digest);
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&digest, &mut cbor).unwrap();
+        //a byte string header of two bytes followed by the raw digest
+        assert_eq!(cbor.len(), 34);
+        assert_eq!(ciborium::from_reader::<Sha256Digest, _>(&cbor[..]).unwrap(), digest);
+
+        assert!(serde_json::from_str::<Sha256Digest>("\"ba7816bf\"").is_err());
+        assert!(serde_json::from_str::<Sha224Digest>(&json).is_err());
+        assert!(serde_json::from_str::<Digest<2>>("\"zz00\"").is_err());
+        assert!(serde_json::from_str::<Digest<1>>("\"+f\"").is_err());
+    }
+}
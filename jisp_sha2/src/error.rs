@@ -0,0 +1,43 @@
+//! The errors returned by the `try_` variants of the functions in this crate, instead of panicking.
+//! # Examples
+//! ```
+//! # #[cfg(feature = "bigint")] {
+//! use jisp_sha2::Error;
+//! use jisp_sha2::conversions::try_from_u32_words;
+//!
+//! let res = try_from_u32_words::<1>(&[0, 1, 2]);
+//! assert_eq!(res, Err(Error::InvalidLength { expected: 2, found: 3 }));
+//! # }
+//! ```
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A list of words or bytes does not have the length the function needs
+    InvalidLength { expected:usize, found:usize },
+    /// The length of the message in bits does not fit into the length field of the padding
+    MessageTooLong { max_bits:u128 },
+    /// A combination of sizes or settings that the algorithm does not support, such as a suffix larger than a block
+    InvalidParameters(String),
+    /// Saved hasher state or constants that cannot be restored
+    MalformedState(String),
+    /// A one-time signing key that has already signed, or a many-time key with no signatures left
+    KeyExhausted,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidLength { expected, found } => write!(f, "expected a length of {}, found {}", expected, found),
+            Error::MessageTooLong { max_bits } => write!(f, "the message is longer than {} bits", max_bits),
+            Error::InvalidParameters(reason) => write!(f, "invalid parameters: {}", reason),
+            Error::MalformedState(reason) => write!(f, "malformed state: {}", reason),
+            Error::KeyExhausted => write!(f, "the signing key has no signatures left"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A [Result](std::result::Result) with the [Error] of this crate
+pub type Result<T> = std::result::Result<T, Error>;
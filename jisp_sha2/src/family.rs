@@ -0,0 +1,264 @@
+//! The structure shared by every SHA-2 variant, generic over the word type and the rotation amounts.
+//! [SHA-256](crate::sha256) and [SHA-512](crate::sha512) are built on this core with the [Sha256Words] and [Sha512Words] parameters.
+//!
+//! The same core also runs toy variants with 8 bit ([Toy8]) and 16 bit ([Toy16]) words.
+//! Their digests are only 64 or 128 bits long, small enough that collision searches on them or on truncated digests are feasible.
+//! **The toy variants are for teaching only**, their rotation amounts are simply scaled down from SHA-256.
+//! # Examples
+//! ```
+//! use jisp_sha2::family::{Toy8, derived_constants, hash};
+//!
+//! let (k, iv) = derived_constants::<Toy8>(64);
+//! let digest = hash::<Toy8>(b"abc", &k, &iv);
+//! assert_eq!(digest.len(), 8);
+//! assert_ne!(digest, hash::<Toy8>(b"abd", &k, &iv));
+//! ```
+use std::fmt::Debug;
+use std::ops::{BitAnd, BitXor, Not};
+
+use crate::constants::derivation::{cube_root_fraction, first_primes, square_root_fraction};
+
+/// An unsigned integer that can be used as the word of a SHA-2 variant
+pub trait Word: Copy + PartialEq + Debug + BitXor<Output = Self> + BitAnd<Output = Self> + Not<Output = Self> {
+    const BITS:u32;
+
+    fn wrapping_add(self, rhs:Self) -> Self;
+    fn rotate_right(self, n:u32) -> Self;
+    fn shift_right(self, n:u32) -> Self;
+    /// The lowest [BITS](Word::BITS) bits of `x`
+    fn truncate(x:u128) -> Self;
+}
+
+macro_rules! impl_word {
+    ($($t:ty),*) => {$(
+        impl Word for $t {
+            const BITS:u32 = <$t>::BITS;
+
+            fn wrapping_add(self, rhs:Self) -> Self {
+                <$t>::wrapping_add(self, rhs)
+            }
+
+            fn rotate_right(self, n:u32) -> Self {
+                <$t>::rotate_right(self, n)
+            }
+
+            fn shift_right(self, n:u32) -> Self {
+                self >> n
+            }
+
+            fn truncate(x:u128) -> Self {
+                x as $t
+            }
+        }
+    )*};
+}
+
+impl_word!(u8, u16, u32, u64);
+
+/// The word type and rotation amounts of a SHA-2 variant
+pub trait Parameters {
+    type Word:Word;
+    /// the three rotations of Σ0
+    const BIG_SIGMA0:[u32;3];
+    /// the three rotations of Σ1
+    const BIG_SIGMA1:[u32;3];
+    /// two rotations and a shift for σ0 of the message schedule
+    const SMALL_SIGMA0:[u32;3];
+    /// two rotations and a shift for σ1 of the message schedule
+    const SMALL_SIGMA1:[u32;3];
+}
+
+/// The parameters of SHA-256 and SHA-224
+pub struct Sha256Words;
+
+impl Parameters for Sha256Words {
+    type Word = u32;
+    const BIG_SIGMA0:[u32;3] = [2, 13, 22];
+    const BIG_SIGMA1:[u32;3] = [6, 11, 25];
+    const SMALL_SIGMA0:[u32;3] = [7, 18, 3];
+    const SMALL_SIGMA1:[u32;3] = [17, 19, 10];
+}
+
+/// The parameters of SHA-512 and SHA-384
+pub struct Sha512Words;
+
+impl Parameters for Sha512Words {
+    type Word = u64;
+    const BIG_SIGMA0:[u32;3] = [28, 34, 39];
+    const BIG_SIGMA1:[u32;3] = [14, 18, 41];
+    const SMALL_SIGMA0:[u32;3] = [1, 8, 7];
+    const SMALL_SIGMA1:[u32;3] = [19, 61, 6];
+}
+
+/// A toy variant with 8 bit words, the rotations of SHA-256 divided by 4
+pub struct Toy8;
+
+impl Parameters for Toy8 {
+    type Word = u8;
+    const BIG_SIGMA0:[u32;3] = [1, 3, 5];
+    const BIG_SIGMA1:[u32;3] = [2, 3, 6];
+    const SMALL_SIGMA0:[u32;3] = [2, 5, 1];
+    const SMALL_SIGMA1:[u32;3] = [4, 5, 3];
+}
+
+/// A toy variant with 16 bit words, the rotations of SHA-256 divided by 2
+pub struct Toy16;
+
+impl Parameters for Toy16 {
+    type Word = u16;
+    const BIG_SIGMA0:[u32;3] = [1, 7, 11];
+    const BIG_SIGMA1:[u32;3] = [3, 6, 13];
+    const SMALL_SIGMA0:[u32;3] = [4, 9, 2];
+    const SMALL_SIGMA1:[u32;3] = [9, 10, 5];
+}
+
+// ------ Six Logical Functions -------
+
+pub fn ch<W:Word>(x:W, y:W, z:W) -> W {
+    (x & y) ^ (!x & z)
+}
+
+pub fn maj<W:Word>(x:W, y:W, z:W) -> W {
+    (x & y) ^ (y & z) ^ (x & z)
+}
+
+pub fn big_sigma0<P:Parameters>(x:P::Word) -> P::Word {
+    let [a, b, c] = P::BIG_SIGMA0;
+    x.rotate_right(a) ^ x.rotate_right(b) ^ x.rotate_right(c)
+}
+
+pub fn big_sigma1<P:Parameters>(x:P::Word) -> P::Word {
+    let [a, b, c] = P::BIG_SIGMA1;
+    x.rotate_right(a) ^ x.rotate_right(b) ^ x.rotate_right(c)
+}
+
+pub fn small_sigma0<P:Parameters>(x:P::Word) -> P::Word {
+    let [a, b, c] = P::SMALL_SIGMA0;
+    x.rotate_right(a) ^ x.rotate_right(b) ^ x.shift_right(c)
+}
+
+pub fn small_sigma1<P:Parameters>(x:P::Word) -> P::Word {
+    let [a, b, c] = P::SMALL_SIGMA1;
+    x.rotate_right(a) ^ x.rotate_right(b) ^ x.shift_right(c)
+}
+
+// ------------------ Compression ----------------
+
+/// The compression function without the feed-forward, one round for every word of `k`.
+/// The message schedule is computed on the fly in a window of 16 words, so any number of rounds works.
+/// `record` is called with the registers after every round.
+pub fn compress<P:Parameters>(
+    intermediate_hash:&[P::Word;8],
+    block:&[P::Word;16],
+    k:&[P::Word],
+    mut record:impl FnMut([P::Word;8])
+) -> [P::Word;8] {
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *intermediate_hash;
+    let mut w = *block;
+
+    for (j, k_j) in k.iter().enumerate() {
+        if j >= 16 {
+            w[j % 16] = small_sigma1::<P>(w[(j - 2) % 16])
+                .wrapping_add(w[(j - 7) % 16])
+                .wrapping_add(small_sigma0::<P>(w[(j - 15) % 16]))
+                .wrapping_add(w[j % 16]);
+        }
+        let t1 = h
+            .wrapping_add(big_sigma1::<P>(e))
+            .wrapping_add(ch(e, f, g))
+            .wrapping_add(*k_j)
+            .wrapping_add(w[j % 16]);
+        let t2 = big_sigma0::<P>(a).wrapping_add(maj(a, b, c));
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+        record([a, b, c, d, e, f, g, h]);
+    }
+
+    [a, b, c, d, e, f, g, h]
+}
+
+/// Hashes a message of bytes, including the padding: a 1 bit, 0s and the message length in two words.
+/// The message length is taken modulo 2 to the power of twice the word size, which for the toy variants is a real limit.
+/// Returns the 8 words of the final chaining value without any truncation.
+pub fn hash<P:Parameters>(msg:&[u8], k:&[P::Word], iv:&[P::Word;8]) -> [P::Word;8] {
+    let word_bytes = (P::Word::BITS / 8) as usize;
+    let block_bytes = 16 * word_bytes;
+
+    let mut bytes = msg.to_vec();
+    bytes.push(0x80);
+    while !(bytes.len() + 2 * word_bytes).is_multiple_of(block_bytes) {
+        bytes.push(0);
+    }
+    let bits = (msg.len() as u128).wrapping_mul(8);
+    for i in (0..2 * word_bytes).rev() {
+        bytes.push(bits.checked_shr(8 * i as u32).unwrap_or(0) as u8);
+    }
+
+    let mut state = *iv;
+    for chunk in bytes.chunks_exact(block_bytes) {
+        let block:[P::Word;16] = std::array::from_fn(|i| {
+            let word = chunk[i * word_bytes..(i + 1) * word_bytes].iter().fold(0u128, |acc, b| (acc << 8) | *b as u128);
+            P::Word::truncate(word)
+        });
+        let registers = compress::<P>(&state, &block, k, |_| ());
+        for i in 0..8 {
+            state[i] = state[i].wrapping_add(registers[i]);
+        }
+    }
+    state
+}
+
+/// Round constants and an initial hash value made the same way as the standard ones:
+/// the first bits of the fractional parts of the cube roots of the first `rounds` primes and the square roots of the first 8.
+/// For [Sha256Words] and [Sha512Words] with 64 and 80 rounds these are the constants of SHA-256 and SHA-512.
+pub fn derived_constants<P:Parameters>(rounds:usize) -> (Vec<P::Word>, [P::Word;8]) {
+    let top = |fraction:u64| P::Word::truncate((fraction >> (64 - P::Word::BITS)) as u128);
+    let k = first_primes(rounds).into_iter().map(|p| top(cube_root_fraction(p))).collect();
+    let primes = first_primes(8);
+    let iv = std::array::from_fn(|i| top(square_root_fraction(primes[i])));
+    (k, iv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{Constants, Sha256, Sha512};
+    use crate::conversions::{u32_words_to_be_bytes, u64_words_to_be_bytes};
+
+    #[test]
+    fn standard_variants_are_bit_exact() {
+        for msg in ["", "abc", "a message that is long enough to need two blocks of sha-256, unlike abc"] {
+            let (k, iv) = derived_constants::<Sha256Words>(64);
+            assert_eq!(k, Sha256.constant_words().to_vec());
+            let digest = u32_words_to_be_bytes(&hash::<Sha256Words>(msg.as_bytes(), &k, &iv));
+            assert_eq!(digest, crate::sha256(msg.as_bytes()).0);
+
+            let (k, iv) = derived_constants::<Sha512Words>(80);
+            assert_eq!(iv, Sha512.initial_hash());
+            let digest = u64_words_to_be_bytes(&hash::<Sha512Words>(msg.as_bytes(), &k, &iv));
+            assert_eq!(digest, crate::sha512(msg.as_bytes()).0);
+        }
+    }
+
+    // a birthday search on the first 24 bits of the digest is expected to succeed after about 2^12 messages
+    #[test]
+    fn toy8_truncated_collision() {
+        let (k, iv) = derived_constants::<Toy8>(64);
+        let mut seen = std::collections::HashMap::new();
+        let collision = (0u32..1 << 16).find_map(|i| {
+            let digest = hash::<Toy8>(&i.to_be_bytes(), &k, &iv);
+            seen.insert([digest[0], digest[1], digest[2]], i).map(|j| (j, i))
+        });
+
+        let (a, b) = collision.expect("no collision among 2^16 messages");
+        assert_ne!(a, b);
+        assert_eq!(hash::<Toy8>(&a.to_be_bytes(), &k, &iv)[..3], hash::<Toy8>(&b.to_be_bytes(), &k, &iv)[..3]);
+    }
+}
@@ -0,0 +1,103 @@
+//! A common interface for the incremental hashers of the four standard SHA-2 variants.
+//! Constructions that are built on top of a hash function, such as [HMAC](crate::hmac) and the [key derivation functions](crate::kdf), work with any [Sha2Hasher].
+//!
+//! The variant is picked by the constants of the hasher, so `Sha256Hasher<Sha224>` is SHA-224 with a 28 byte result.
+//! ```
+//! use jisp_sha2::hasher::Sha2Hasher;
+//! use jisp_sha2::sha256::Sha256Hasher;
+//! use jisp_sha2::constants::Sha224;
+//!
+//! let hash = <Sha256Hasher<Sha224> as Sha2Hasher>::digest(b"abc");
+//! assert_eq!(hash.len(), 28);
+//! assert_eq!(hash[..4], [0x23, 0x09, 0x7d, 0x22]);
+//! ```
+use crate::constants::{Sha224, Sha256, Sha384, Sha512};
+use crate::sha256::Sha256Hasher;
+use crate::sha512::Sha512Hasher;
+
+pub trait Sha2Hasher: Clone {
+    /// The size of one message block in bytes
+    const BLOCK_BYTES:usize;
+    /// The size of the final hash in bytes
+    const DIGEST_BYTES:usize;
+
+    fn new() -> Self;
+    fn update(&mut self, bytes:&[u8]);
+    /// The final hash as big endian bytes, truncated for SHA-224 and SHA-384
+    fn finalize_bytes(self) -> Vec<u8>;
+
+    /// Hashes a complete message at once
+    fn digest(bytes:&[u8]) -> Vec<u8> {
+        let mut hasher = Self::new();
+        hasher.update(bytes);
+        hasher.finalize_bytes()
+    }
+}
+
+impl Sha2Hasher for Sha256Hasher<Sha256> {
+    const BLOCK_BYTES:usize = 64;
+    const DIGEST_BYTES:usize = 32;
+
+    fn new() -> Self {
+        Sha256Hasher::new()
+    }
+
+    fn update(&mut self, bytes:&[u8]) {
+        Sha256Hasher::update(self, bytes)
+    }
+
+    fn finalize_bytes(self) -> Vec<u8> {
+        self.finalize().0.to_vec()
+    }
+}
+
+impl Sha2Hasher for Sha256Hasher<Sha224> {
+    const BLOCK_BYTES:usize = 64;
+    const DIGEST_BYTES:usize = 28;
+
+    fn new() -> Self {
+        Sha256Hasher::new()
+    }
+
+    fn update(&mut self, bytes:&[u8]) {
+        Sha256Hasher::update(self, bytes)
+    }
+
+    fn finalize_bytes(self) -> Vec<u8> {
+        self.finalize().0[..28].to_vec()
+    }
+}
+
+impl Sha2Hasher for Sha512Hasher<Sha512> {
+    const BLOCK_BYTES:usize = 128;
+    const DIGEST_BYTES:usize = 64;
+
+    fn new() -> Self {
+        Sha512Hasher::new()
+    }
+
+    fn update(&mut self, bytes:&[u8]) {
+        Sha512Hasher::update(self, bytes)
+    }
+
+    fn finalize_bytes(self) -> Vec<u8> {
+        self.finalize().0.to_vec()
+    }
+}
+
+impl Sha2Hasher for Sha512Hasher<Sha384> {
+    const BLOCK_BYTES:usize = 128;
+    const DIGEST_BYTES:usize = 48;
+
+    fn new() -> Self {
+        Sha512Hasher::new()
+    }
+
+    fn update(&mut self, bytes:&[u8]) {
+        Sha512Hasher::update(self, bytes)
+    }
+
+    fn finalize_bytes(self) -> Vec<u8> {
+        self.finalize().0[..48].to_vec()
+    }
+}
@@ -0,0 +1,93 @@
+//! HMAC, the keyed message authentication code from RFC 2104, built on any of the SHA-2 [hashers](crate::hasher)
+//! # Examples
+//! ```
+//! use jisp_sha2::hmac::hmac;
+//! use jisp_sha2::sha256::Sha256Hasher;
+//! use jisp_sha2::constants::Sha256;
+//!
+//! let tag = hmac::<Sha256Hasher<Sha256>>(b"key", b"The quick brown fox jumps over the lazy dog");
+//! assert_eq!(tag[..4], [0xf7, 0xbc, 0x83, 0xf4]);
+//! ```
+use crate::hasher::Sha2Hasher;
+
+const IPAD:u8 = 0x36;
+const OPAD:u8 = 0x5c;
+
+/// Computes the HMAC of a message in one go
+pub fn hmac<H:Sha2Hasher>(key:&[u8], msg:&[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<H>::new(key);
+    mac.update(msg);
+    mac.finalize()
+}
+
+/// An incremental HMAC. The key is only processed once in [new](Hmac::new),
+/// so cloning an `Hmac` is a cheap way to authenticate many messages with the same key.
+pub struct Hmac<H:Sha2Hasher> {
+    inner:H,
+    outer:H
+}
+
+impl<H:Sha2Hasher> Hmac<H> {
+    pub fn new(key:&[u8]) -> Self {
+        //keys longer than a block are hashed first, shorter keys are padded with 0s
+        let mut block_key = if key.len() > H::BLOCK_BYTES {
+            H::digest(key)
+        } else {
+            key.to_vec()
+        };
+        block_key.resize(H::BLOCK_BYTES, 0);
+
+        let mut inner = H::new();
+        inner.update(&block_key.iter().map(|b| b ^ IPAD).collect::<Vec<u8>>());
+        let mut outer = H::new();
+        outer.update(&block_key.iter().map(|b| b ^ OPAD).collect::<Vec<u8>>());
+        Self { inner, outer }
+    }
+
+    pub fn update(&mut self, msg:&[u8]) {
+        self.inner.update(msg);
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        let mut outer = self.outer;
+        outer.update(&self.inner.finalize_bytes());
+        outer.finalize_bytes()
+    }
+}
+
+impl<H:Sha2Hasher> Clone for Hmac<H> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone(), outer: self.outer.clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{Sha224, Sha256, Sha384, Sha512};
+    use crate::sha256::Sha256Hasher;
+    use crate::sha512::Sha512Hasher;
+
+    fn hex(bytes:&[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // RFC 4231 test case 2
+    #[test]
+    fn rfc4231_short_key() {
+        let (key, msg) = (b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(hex(&hmac::<Sha256Hasher<Sha224>>(key, msg)), "a30e01098bc6dbbf45690f3a7e9e6d0f8bbea2a39e6148008fd05e44");
+        assert_eq!(hex(&hmac::<Sha256Hasher<Sha256>>(key, msg)), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+        assert_eq!(hex(&hmac::<Sha512Hasher<Sha384>>(key, msg)), "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47e42ec3736322445e8e2240ca5e69e2c78b3239ecfab21649");
+        assert_eq!(hex(&hmac::<Sha512Hasher<Sha512>>(key, msg)), "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737");
+    }
+
+    // RFC 4231 test case 6, the key is longer than a block and gets hashed first
+    #[test]
+    fn rfc4231_long_key() {
+        let key = [0xaa; 131];
+        let msg = b"Test Using Larger Than Block-Size Key - Hash Key First";
+        assert_eq!(hex(&hmac::<Sha256Hasher<Sha256>>(&key, msg)), "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54");
+        assert_eq!(hex(&hmac::<Sha512Hasher<Sha512>>(&key, msg)), "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f3526b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598");
+    }
+}
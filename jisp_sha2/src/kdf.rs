@@ -0,0 +1,192 @@
+//! Key derivation functions built on [HMAC](crate::hmac): HKDF from RFC 5869 and PBKDF2 from RFC 8018.
+//! Both work with any of the SHA-2 [hashers](crate::hasher).
+//! # Examples
+//! ```
+//! use jisp_sha2::kdf::pbkdf2;
+//! use jisp_sha2::sha256::Sha256Hasher;
+//! use jisp_sha2::constants::Sha256;
+//!
+//! let key = pbkdf2::<Sha256Hasher<Sha256>>(b"passwd", b"salt", 1, 16).unwrap();
+//! assert_eq!(key[..4], [0x55, 0xac, 0x04, 0x6e]);
+//! ```
+use std::ops::ControlFlow;
+
+use crate::error::{Error, Result};
+use crate::hasher::Sha2Hasher;
+use crate::hmac::{hmac, Hmac};
+
+/// The extract step of HKDF, concentrates the entropy of the input key material into a pseudorandom key.
+/// An empty salt is replaced by a string of 0s as long as the hash.
+pub fn hkdf_extract<H:Sha2Hasher>(salt:&[u8], ikm:&[u8]) -> Vec<u8> {
+    if salt.is_empty() {
+        hmac::<H>(&vec![0; H::DIGEST_BYTES], ikm)
+    } else {
+        hmac::<H>(salt, ikm)
+    }
+}
+
+/// The expand step of HKDF, stretches a pseudorandom key into `len` bytes of output keying material.
+/// Returns `None` if more than 255 times the hash length is requested.
+pub fn hkdf_expand<H:Sha2Hasher>(prk:&[u8], info:&[u8], len:usize) -> Option<Vec<u8>> {
+    if len > 255 * H::DIGEST_BYTES {
+        return None;
+    }
+
+    let mac = Hmac::<H>::new(prk);
+    let mut okm = Vec::with_capacity(len);
+    let mut t = Vec::new();
+    let mut counter = 1u8;
+    while okm.len() < len {
+        let mut m = mac.clone();
+        m.update(&t);
+        m.update(info);
+        m.update(&[counter]);
+        t = m.finalize();
+        okm.extend_from_slice(&t);
+        counter = counter.wrapping_add(1);
+    }
+    okm.truncate(len);
+    Some(okm)
+}
+
+/// Extract followed by expand
+/// # Examples
+/// ```
+/// use jisp_sha2::kdf::hkdf;
+/// use jisp_sha2::sha256::Sha256Hasher;
+/// use jisp_sha2::constants::Sha256;
+///
+/// let okm = hkdf::<Sha256Hasher<Sha256>>(&[0x0b; 22], &[], &[], 42).unwrap();
+/// assert_eq!(okm[..4], [0x8d, 0xa4, 0xe7, 0x75]);
+/// ```
+pub fn hkdf<H:Sha2Hasher>(ikm:&[u8], salt:&[u8], info:&[u8], len:usize) -> Option<Vec<u8>> {
+    let prk = hkdf_extract::<H>(salt, ikm);
+    hkdf_expand::<H>(&prk, info, len)
+}
+
+/// Derives a key of `len` bytes from a password.
+/// Returns `None` if `iterations` or `len` is 0 or if `len` is more than `2^32 - 1` blocks of the hash, the limit of RFC 8018
+pub fn pbkdf2<H:Sha2Hasher>(password:&[u8], salt:&[u8], iterations:u32, len:usize) -> Option<Vec<u8>> {
+    pbkdf2_with_progress::<H>(password, salt, iterations, len, |_, _| ControlFlow::Continue(()))
+}
+
+/// Same as [pbkdf2] but returns [InvalidParameters](Error::InvalidParameters) instead of `None`
+pub fn try_pbkdf2<H:Sha2Hasher>(password:&[u8], salt:&[u8], iterations:u32, len:usize) -> Result<Vec<u8>> {
+    if iterations == 0 || len == 0 {
+        return Err(Error::InvalidParameters("PBKDF2 needs at least one iteration and one byte of output".to_owned()));
+    }
+    if len.div_ceil(H::DIGEST_BYTES) > u32::MAX as usize {
+        return Err(Error::InvalidParameters(format!("PBKDF2 derives at most {} blocks, not {} bytes", u32::MAX, len)));
+    }
+    pbkdf2::<H>(password, salt, iterations, len).ok_or(Error::InvalidParameters("PBKDF2 failed".to_owned()))
+}
+
+/// Same as [pbkdf2] but `progress` is called with the number of finished and total iterations after every iteration,
+/// since a large number of iterations can take a long time. If `progress` breaks the derivation is stopped and `None` is returned.
+pub fn pbkdf2_with_progress<H:Sha2Hasher>(
+    password:&[u8],
+    salt:&[u8],
+    iterations:u32,
+    len:usize,
+    mut progress:impl FnMut(u64, u64) -> ControlFlow<()>
+) -> Option<Vec<u8>> {
+    let blocks = len.div_ceil(H::DIGEST_BYTES);
+    //the block index is a 32 bit counter
+    if iterations == 0 || len == 0 || blocks > u32::MAX as usize {
+        return None;
+    }
+
+    let mac = Hmac::<H>::new(password);
+    let total = blocks as u64 * iterations as u64;
+    let mut done = 0u64;
+    let mut key = Vec::with_capacity(blocks * H::DIGEST_BYTES);
+
+    for i in 1..=blocks as u32 {
+        let mut m = mac.clone();
+        m.update(salt);
+        m.update(&i.to_be_bytes());
+        let mut u = m.finalize();
+        let mut block = u.clone();
+
+        for _ in 1..iterations {
+            let mut m = mac.clone();
+            m.update(&u);
+            u = m.finalize();
+            for (b, x) in block.iter_mut().zip(&u) {
+                *b ^= x;
+            }
+            done += 1;
+            if progress(done, total).is_break() {
+                return None;
+            }
+        }
+        done += 1;
+        if progress(done, total).is_break() {
+            return None;
+        }
+        key.extend_from_slice(&block);
+    }
+    key.truncate(len);
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{Sha256, Sha512};
+    use crate::sha256::Sha256Hasher;
+    use crate::sha512::Sha512Hasher;
+
+    fn hex(bytes:&[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // RFC 5869 test case 1
+    #[test]
+    fn hkdf_rfc5869() {
+        let ikm = [0x0b; 22];
+        let salt:Vec<u8> = (0x00..=0x0c).collect();
+        let info:Vec<u8> = (0xf0..=0xf9).collect();
+        let prk = hkdf_extract::<Sha256Hasher<Sha256>>(&salt, &ikm);
+        assert_eq!(hex(&prk), "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5");
+
+        let okm = hkdf_expand::<Sha256Hasher<Sha256>>(&prk, &info, 42).unwrap();
+        assert_eq!(hex(&okm), "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865");
+        assert_eq!(hkdf_expand::<Sha256Hasher<Sha256>>(&prk, &info, 255 * 32 + 1), None);
+    }
+
+    // RFC 7914 section 11
+    #[test]
+    fn pbkdf2_sha256() {
+        let key = pbkdf2::<Sha256Hasher<Sha256>>(b"passwd", b"salt", 1, 64).unwrap();
+        assert_eq!(hex(&key), "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783");
+    }
+
+    #[test]
+    fn pbkdf2_sha512_progress() {
+        let mut calls = Vec::new();
+        let key = pbkdf2_with_progress::<Sha512Hasher<Sha512>>(b"password", b"salt", 2, 32, |done, total| {
+            calls.push((done, total));
+            ControlFlow::Continue(())
+        }).unwrap();
+        assert_eq!(hex(&key), "e1d9c16aa681708a45f5c7c4e215ceb66e011a2e9f0040713f18aefdb866d53c");
+        assert_eq!(calls, vec![(1, 2), (2, 2)]);
+
+        let stopped = pbkdf2_with_progress::<Sha512Hasher<Sha512>>(b"password", b"salt", 2, 32, |_, _| ControlFlow::Break(()));
+        assert_eq!(stopped, None);
+    }
+
+    #[test]
+    fn pbkdf2_limits() {
+        let key = try_pbkdf2::<Sha256Hasher<Sha256>>(b"passwd", b"salt", 1, 16).unwrap();
+        assert_eq!(key, pbkdf2::<Sha256Hasher<Sha256>>(b"passwd", b"salt", 1, 16).unwrap());
+        assert!(matches!(try_pbkdf2::<Sha256Hasher<Sha256>>(b"passwd", b"salt", 0, 16), Err(Error::InvalidParameters(_))));
+        assert!(matches!(try_pbkdf2::<Sha256Hasher<Sha256>>(b"passwd", b"salt", 1, 0), Err(Error::InvalidParameters(_))));
+
+        //one block more than the 32 bit counter allows, rejected before anything is derived
+        if let Some(len) = (u32::MAX as usize + 1).checked_mul(32) {
+            assert!(matches!(try_pbkdf2::<Sha256Hasher<Sha256>>(b"passwd", b"salt", 1, len), Err(Error::InvalidParameters(_))));
+            assert_eq!(pbkdf2::<Sha256Hasher<Sha256>>(b"passwd", b"salt", 1, len), None);
+        }
+    }
+}
@@ -6,39 +6,87 @@
 //! It should therefore not be used in any real-world applications, it is only meant for small personal projects such as mine. 
 //! 
 //! # Usage
-//! When using this crate it is important that you first [preprocess](preprocessing) your data  which can be given in either string form or as a vector of `u8` bytes.
-//! The algorithm will return a [BigInt](crypto_bigint::Uint) from the external [crypto_bigint] crate.
-//! If this is not your preferred data type there are functions in [conversions] to transform this output to a list of either u64 or u32 words.
+//! The simplest way to hash is with [sha256], [sha224], [sha512] and [sha384], which take bytes and return a [Digest], a plain array of bytes.
+//! For messages that arrive in pieces, such as files, you can use the incremental [Sha256Hasher](sha256::Sha256Hasher) and [Sha512Hasher](sha512::Sha512Hasher) instead.
+//! These hashers share the [Sha2Hasher](hasher::Sha2Hasher) trait, which the keyed constructions [HMAC](hmac) and [HKDF/PBKDF2](kdf) are built on.
+//! Functions that can fail on bad input have a `try_` variant that returns an [Error] instead of panicking.
+//! Both algorithms share the generic core in [family], which also runs toy variants with 8 and 16 bit words.
+//! Double SHA-256 and the Bitcoin block and transaction hashes built on it are in [bitcoin] and the BIP-340 tagged hashes in [tagged].
+//! A multi-threaded toy proof of work search on top of double SHA-256 is in [pow].
+//! Merkle trees with inclusion proofs over any [Sha2Hasher](hasher::Sha2Hasher) are in [merkle].
+//! The Certificate Transparency tree hash of RFC 6962 with audit paths and consistency proofs is in [ct].
+//! A sparse Merkle tree keyed by SHA-256 digests, with membership and non-membership proofs, is in [smt].
+//! An append-only Merkle Mountain Range with proofs for past leaves is in [mmr].
+//! Lamport and Winternitz one-time signatures are in [ots].
+//! Leighton-Micali LMS and HSS signatures from RFC 8554 are in [lms].
+//!
+//! # The `bigint` feature
+//! The default `bigint` feature adds the original interface on [BigInts](crypto_bigint::Uint) from the external [crypto_bigint] crate.
+//! There you first [preprocess](preprocessing) your data into message blocks and then apply [sha_256] or one of the other algorithms.
+//! The functions in [conversions] transform such a result to a list of either u64 or u32 words, and a [Digest] converts from and into a `Uint` with [From].
+//! Variants with fewer rounds for cryptanalysis are kept apart in [reduced], which also works on message blocks.
+//! Without the feature `crypto_bigint` is not a dependency at all.
+//!
+//! # The `serde` feature
+//! The optional `serde` feature implements `Serialize` and `Deserialize` for the [digests](digest), the state of the hashers and the [constants].
 //! 
 //! # Example
 //! ```
+//! use jisp_sha2::sha256;
+//!
+//! let hash = sha256(b"abc");
+//! assert_eq!(hash.to_hex(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+//! ```
+//! With the `bigint` feature
+//! ```
+//! # #[cfg(feature = "bigint")] {
 //! use jisp_sha2::preprocessing::sha256_preprocessing;
 //! use jisp_sha2::sha256::sha_256;
 //! use crypto_bigint::U256;
 //! 
-//! let input_str = "abc";
-//! 
 //! let hex = sha256_preprocessing("abc");
 //! let hash = sha_256(hex);
 //! 
 //! let expected = 
 //!     U256::from_be_hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
 //! assert_eq!(hash, expected);
+//! assert_eq!(U256::from(jisp_sha2::sha256(b"abc")), expected);
+//! # }
 //! ```
 
+pub mod preprocessing;
+#[cfg(feature = "bigint")]
+pub use preprocessing::{sha256_preprocessing, sha512_preprocessing, custom_preprocessing};
+#[cfg(feature = "bigint")]
+pub use sha256::{sha_256, sha_224};
+#[cfg(feature = "bigint")]
+pub use sha512::{sha_512, sha_384};
+pub use sha256::{sha256, sha224};
+pub use sha512::{sha512, sha384};
 
+pub mod digest;
+pub use digest::{Digest, Sha224Digest, Sha256Digest, Sha384Digest, Sha512Digest};
 
-pub mod preprocessing;
-pub use preprocessing::sha256_preprocessing;
-pub use preprocessing::sha512_preprocessing;
-pub use preprocessing::custom_preprocessing;
-pub use sha256::sha_256;
-pub use sha256::sha_224;
-pub use sha512::sha_512;
-pub use sha512::sha_384;
+pub mod error;
+pub use error::Error;
 
 pub mod conversions;
 pub mod printer;
 pub mod constants;
 pub mod sha256;
-pub mod sha512;
\ No newline at end of file
+pub mod sha512;
+pub mod hasher;
+pub mod hmac;
+pub mod kdf;
+#[cfg(feature = "bigint")]
+pub mod reduced;
+pub mod family;
+pub mod bitcoin;
+pub mod tagged;
+pub mod pow;
+pub mod merkle;
+pub mod ct;
+pub mod smt;
+pub mod mmr;
+pub mod ots;
+pub mod lms;
\ No newline at end of file
@@ -0,0 +1,817 @@
+//! Leighton-Micali hash based signatures from RFC 8554: the one-time scheme LM-OTS, the Merkle tree scheme LMS on top of it
+//! and the multi-level HSS, on SHA-256 and on the SHA-256/192 parameter sets of NIST SP 800-208, which truncate the hash to 24 bytes.
+//!
+//! Keys and signatures use the byte encodings of the RFC, so [HssPublicKey::verify] checks signatures made by other implementations.
+//! Private keys are derived from a seed as in appendix A of the RFC, and so are the randomizer of each one-time signature and the keys of the lower HSS levels.
+//!
+//! An LMS key signs once with each leaf of its tree and must never use a leaf twice. [sign](LmsPrivateKey::sign) moves on to the next leaf before it signs,
+//! so the key has to be saved after every signature and before the signature is released, with the `serde` feature for example.
+//! Restoring an older copy of a key would sign with leaves that were already used and break the scheme.
+//! A key with no leaves left returns [KeyExhausted](Error::KeyExhausted).
+//! # Examples
+//! ```
+//! use jisp_sha2::lms::{HssPrivateKey, HssPublicKey, LmotsType, LmsType};
+//!
+//! let levels = [(LmsType::Sha256M32H5, LmotsType::Sha256N32W4); 2];
+//! let mut key = HssPrivateKey::new(&levels, [7; 16], &[42; 32]).unwrap();
+//! let public = HssPublicKey::from_bytes(&key.public_key().to_bytes()).unwrap();
+//!
+//! let signature = key.sign(b"firmware image").unwrap();
+//! assert!(public.verify(b"firmware image", &signature));
+//! assert!(!public.verify(b"other image", &signature));
+//! ```
+use crate::constants::Sha256;
+use crate::error::{Error, Result};
+use crate::sha256::Sha256Hasher;
+
+const D_PBLC:u16 = 0x8080;
+const D_MESG:u16 = 0x8181;
+const D_LEAF:u16 = 0x8282;
+const D_INTR:u16 = 0x8383;
+
+/// The `j` byte of the pseudorandom key derivation in appendix A
+const PRF_J:u8 = 0xff;
+/// Chain indices that no parameter set reaches, used to derive the randomizer `C` and the keys of the next HSS level
+const C_INDEX:u16 = 0xfffd;
+const CHILD_ID:u16 = 0xfffe;
+const CHILD_SEED:u16 = 0xffff;
+
+/// The maximum number of HSS levels
+pub const MAX_LEVELS:usize = 8;
+/// The highest tree a private key builds in memory, verification takes all heights
+pub const MAX_SIGNING_HEIGHT:u32 = 15;
+
+/// SHA-256 of the parts, truncated to `n` bytes
+fn hash(n:usize, parts:&[&[u8]]) -> Vec<u8> {
+    let mut hasher = Sha256Hasher::<Sha256>::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut digest = hasher.finalize().0.to_vec();
+    digest.truncate(n);
+    digest
+}
+
+fn read_u32(bytes:&[u8], pos:usize) -> Result<u32> {
+    let word = bytes.get(pos..pos + 4).ok_or(Error::InvalidLength { expected: pos + 4, found: bytes.len() })?;
+    Ok(u32::from_be_bytes(word.try_into().unwrap()))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LmotsType {
+    Sha256N32W1,
+    Sha256N32W2,
+    Sha256N32W4,
+    Sha256N32W8,
+    Sha256N24W1,
+    Sha256N24W2,
+    Sha256N24W4,
+    Sha256N24W8
+}
+
+impl LmotsType {
+    pub const ALL:[LmotsType; 8] = [
+        LmotsType::Sha256N32W1, LmotsType::Sha256N32W2, LmotsType::Sha256N32W4, LmotsType::Sha256N32W8,
+        LmotsType::Sha256N24W1, LmotsType::Sha256N24W2, LmotsType::Sha256N24W4, LmotsType::Sha256N24W8
+    ];
+
+    /// The type code from the IANA registry, 1 to 4 for SHA-256 and 5 to 8 for SHA-256/192
+    pub fn code(&self) -> u32 {
+        Self::ALL.iter().position(|t| t == self).unwrap() as u32 + 1
+    }
+
+    pub fn from_code(code:u32) -> Result<Self> {
+        Self::ALL.into_iter().find(|t| t.code() == code).ok_or(Error::InvalidParameters(format!("unknown LM-OTS type {:#x}", code)))
+    }
+
+    /// The bytes of a hash
+    pub fn n(&self) -> usize {
+        if self.code() <= 4 { 32 } else { 24 }
+    }
+
+    /// The bits of a digit, the Winternitz parameter
+    pub fn w(&self) -> usize {
+        1 << ((self.code() - 1) % 4)
+    }
+
+    /// The number of digits of the message hash
+    fn u(&self) -> usize {
+        (8 * self.n()).div_ceil(self.w())
+    }
+
+    /// The number of digits of the checksum
+    fn v(&self) -> usize {
+        let max = ((1 << self.w()) - 1) * self.u();
+        (max.ilog2() as usize + 1).div_ceil(self.w())
+    }
+
+    /// The number of chains, one for each digit of the message hash and its checksum
+    pub fn p(&self) -> usize {
+        self.u() + self.v()
+    }
+
+    /// How far the checksum is shifted left in its 16 bits
+    pub fn ls(&self) -> u32 {
+        (16 - self.v() * self.w()) as u32
+    }
+
+    /// The length of an encoded one-time signature
+    pub fn signature_len(&self) -> usize {
+        4 + self.n() * (self.p() + 1)
+    }
+
+    /// The digits of the message hash followed by those of its checksum
+    fn digits(&self, q:&[u8]) -> Vec<usize> {
+        let w = self.w();
+        let coef = |s:&[u8], i:usize| (s[i * w / 8] as usize >> (8 - (w * (i % (8 / w)) + w))) & ((1 << w) - 1);
+        let sum:usize = (0..self.u()).map(|i| (1 << w) - 1 - coef(q, i)).sum();
+        let checksum = ((sum << self.ls()) as u16).to_be_bytes();
+        let s = [q, &checksum].concat();
+        (0..self.p()).map(|i| coef(&s, i)).collect()
+    }
+
+    /// Runs chain `i` of leaf `q` from step `from` up to `to`
+    fn chain(&self, id:&[u8;16], q:u32, i:usize, mut x:Vec<u8>, from:usize, to:usize) -> Vec<u8> {
+        for j in from..to {
+            x = hash(self.n(), &[id, &q.to_be_bytes(), &(i as u16).to_be_bytes(), &[j as u8], &x]);
+        }
+        x
+    }
+
+    fn prf(&self, id:&[u8;16], q:u32, i:u16, seed:&[u8]) -> Vec<u8> {
+        hash(self.n(), &[id, &q.to_be_bytes(), &i.to_be_bytes(), &[PRF_J], seed])
+    }
+
+    /// The one-time public key `K` of leaf `q`
+    fn public_key(&self, id:&[u8;16], q:u32, seed:&[u8]) -> Vec<u8> {
+        let top = (1 << self.w()) - 1;
+        let ends:Vec<Vec<u8>> = (0..self.p()).map(|i| self.chain(id, q, i, self.prf(id, q, i as u16, seed), 0, top)).collect();
+        self.compress(id, q, &ends)
+    }
+
+    fn compress(&self, id:&[u8;16], q:u32, ends:&[Vec<u8>]) -> Vec<u8> {
+        let (q, d) = (q.to_be_bytes(), D_PBLC.to_be_bytes());
+        let mut parts:Vec<&[u8]> = vec![id, &q, &d];
+        parts.extend(ends.iter().map(|e| e.as_slice()));
+        hash(self.n(), &parts)
+    }
+
+    fn message_hash(&self, id:&[u8;16], q:u32, c:&[u8], msg:&[u8]) -> Vec<u8> {
+        hash(self.n(), &[id, &q.to_be_bytes(), &D_MESG.to_be_bytes(), c, msg])
+    }
+
+    /// Signs with the randomizer `c`
+    fn sign(&self, id:&[u8;16], q:u32, seed:&[u8], c:&[u8], msg:&[u8]) -> Vec<u8> {
+        let digits = self.digits(&self.message_hash(id, q, c, msg));
+        let mut sig = Vec::with_capacity(self.signature_len());
+        sig.extend_from_slice(&self.code().to_be_bytes());
+        sig.extend_from_slice(c);
+        for (i, a) in digits.into_iter().enumerate() {
+            sig.extend(self.chain(id, q, i, self.prf(id, q, i as u16, seed), 0, a));
+        }
+        sig
+    }
+
+    /// The public key that a signature of exactly [signature_len](Self::signature_len) bytes leads to, algorithm 4b of the RFC
+    fn candidate(&self, id:&[u8;16], q:u32, msg:&[u8], sig:&[u8]) -> Vec<u8> {
+        let n = self.n();
+        let c = &sig[4..4 + n];
+        let digits = self.digits(&self.message_hash(id, q, c, msg));
+        let top = (1 << self.w()) - 1;
+        let ends:Vec<Vec<u8>> = digits.into_iter().enumerate()
+            .map(|(i, a)| self.chain(id, q, i, sig[4 + n * (i + 1)..4 + n * (i + 2)].to_vec(), a, top))
+            .collect();
+        self.compress(id, q, &ends)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LmsType {
+    Sha256M32H5,
+    Sha256M32H10,
+    Sha256M32H15,
+    Sha256M32H20,
+    Sha256M32H25,
+    Sha256M24H5,
+    Sha256M24H10,
+    Sha256M24H15,
+    Sha256M24H20,
+    Sha256M24H25
+}
+
+impl LmsType {
+    pub const ALL:[LmsType; 10] = [
+        LmsType::Sha256M32H5, LmsType::Sha256M32H10, LmsType::Sha256M32H15, LmsType::Sha256M32H20, LmsType::Sha256M32H25,
+        LmsType::Sha256M24H5, LmsType::Sha256M24H10, LmsType::Sha256M24H15, LmsType::Sha256M24H20, LmsType::Sha256M24H25
+    ];
+
+    /// The type code from the IANA registry, 5 to 9 for SHA-256 and 10 to 14 for SHA-256/192
+    pub fn code(&self) -> u32 {
+        Self::ALL.iter().position(|t| t == self).unwrap() as u32 + 5
+    }
+
+    pub fn from_code(code:u32) -> Result<Self> {
+        Self::ALL.into_iter().find(|t| t.code() == code).ok_or(Error::InvalidParameters(format!("unknown LMS type {:#x}", code)))
+    }
+
+    /// The bytes of a tree node
+    pub fn m(&self) -> usize {
+        if self.code() <= 9 { 32 } else { 24 }
+    }
+
+    /// The height of the tree, which has `2^h` leaves
+    pub fn h(&self) -> u32 {
+        5 * ((self.code() - 5) % 5 + 1)
+    }
+
+    pub fn leaves(&self) -> u64 {
+        1 << self.h()
+    }
+
+    fn public_key_len(&self) -> usize {
+        24 + self.m()
+    }
+}
+
+/// SP 800-208 uses the same hash length for the tree and the one-time signatures
+fn check_types(lms_type:LmsType, ots_type:LmotsType) -> Result<()> {
+    if lms_type.m() != ots_type.n() {
+        return Err(Error::InvalidParameters(format!("{:?} and {:?} have different hash lengths", lms_type, ots_type)));
+    }
+    Ok(())
+}
+
+/// The length of the LMS signature at the start of the bytes, read from the type codes in it
+fn signature_len(sig:&[u8]) -> Result<usize> {
+    let ots = LmotsType::from_code(read_u32(sig, 4)?)?;
+    let lms = LmsType::from_code(read_u32(sig, 4 + ots.signature_len())?)?;
+    Ok(8 + ots.signature_len() + lms.h() as usize * lms.m())
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LmsPublicKey {
+    pub lms_type:LmsType,
+    pub ots_type:LmotsType,
+    pub id:[u8;16],
+    /// the root of the tree, `T[1]`
+    pub root:Vec<u8>
+}
+
+impl LmsPublicKey {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.lms_type.code().to_be_bytes().as_slice(), &self.ots_type.code().to_be_bytes(), &self.id, self.root.as_slice()].concat()
+    }
+
+    pub fn from_bytes(bytes:&[u8]) -> Result<Self> {
+        let lms_type = LmsType::from_code(read_u32(bytes, 0)?)?;
+        let ots_type = LmotsType::from_code(read_u32(bytes, 4)?)?;
+        check_types(lms_type, ots_type)?;
+        if bytes.len() != lms_type.public_key_len() {
+            return Err(Error::InvalidLength { expected: lms_type.public_key_len(), found: bytes.len() });
+        }
+        Ok(Self { lms_type, ots_type, id: bytes[8..24].try_into().unwrap(), root: bytes[24..].to_vec() })
+    }
+
+    /// Checks an encoded LMS signature with the algorithm of section 5.4.2 of the RFC
+    pub fn verify(&self, msg:&[u8], sig:&[u8]) -> bool {
+        self.candidate_root(msg, sig).is_ok_and(|root| root == self.root)
+    }
+
+    fn candidate_root(&self, msg:&[u8], sig:&[u8]) -> Result<Vec<u8>> {
+        let q = read_u32(sig, 0)?;
+        let (ots, lms) = (self.ots_type, self.lms_type);
+        check_types(lms, ots)?;
+        if read_u32(sig, 4)? != ots.code() || read_u32(sig, 4 + ots.signature_len())? != lms.code() {
+            return Err(Error::InvalidParameters("the signature is for other parameters than the key".to_owned()));
+        }
+        let (h, m) = (lms.h() as usize, lms.m());
+        let path_start = 8 + ots.signature_len();
+        if sig.len() != path_start + h * m {
+            return Err(Error::InvalidLength { expected: path_start + h * m, found: sig.len() });
+        }
+        if q as u64 >= lms.leaves() {
+            return Err(Error::InvalidParameters(format!("leaf {} is not in a tree of height {}", q, h)));
+        }
+
+        let k = ots.candidate(&self.id, q, msg, &sig[4..4 + ots.signature_len()]);
+        let mut node = (1u32 << h) + q;
+        let mut tmp = hash(m, &[&self.id, &node.to_be_bytes(), &D_LEAF.to_be_bytes(), &k]);
+        for sibling in sig[path_start..].chunks(m) {
+            let parent = (node / 2).to_be_bytes();
+            tmp = if node % 2 == 1 {
+                hash(m, &[&self.id, &parent, &D_INTR.to_be_bytes(), sibling, &tmp])
+            } else {
+                hash(m, &[&self.id, &parent, &D_INTR.to_be_bytes(), &tmp, sibling])
+            };
+            node /= 2;
+        }
+        Ok(tmp)
+    }
+}
+
+/// An LMS private key: the seed its one-time keys are derived from, the next unused leaf and the whole tree, which is recomputed on loading.
+/// Making the tree computes `2^h` one-time public keys, so keys are limited to a height of [MAX_SIGNING_HEIGHT].
+/// With the `serde` feature the key is saved without the tree.
+/// Private keys are not [Clone], as a copy would sign with the same leaves again.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LmsPrivateKey {
+    lms_type:LmsType,
+    ots_type:LmotsType,
+    id:[u8;16],
+    seed:Vec<u8>,
+    q:u32,
+    /// the `m` bytes of node `T[r]` at offset `r * m`, the first node is unused
+    #[cfg_attr(feature = "serde", serde(skip))]
+    tree:Vec<u8>
+}
+
+impl LmsPrivateKey {
+    /// A key with the identifier `I` and a secret seed of `m` bytes
+    pub fn new(lms_type:LmsType, ots_type:LmotsType, id:[u8;16], seed:&[u8]) -> Result<Self> {
+        Self::with_state(lms_type, ots_type, id, seed.to_vec(), 0)
+    }
+
+    fn with_state(lms_type:LmsType, ots_type:LmotsType, id:[u8;16], seed:Vec<u8>, q:u32) -> Result<Self> {
+        check_types(lms_type, ots_type)?;
+        if lms_type.h() > MAX_SIGNING_HEIGHT {
+            return Err(Error::InvalidParameters(format!("private keys have trees of height up to {}, not {}", MAX_SIGNING_HEIGHT, lms_type.h())));
+        }
+        if seed.len() != lms_type.m() {
+            return Err(Error::InvalidLength { expected: lms_type.m(), found: seed.len() });
+        }
+        if q as u64 > lms_type.leaves() {
+            return Err(Error::MalformedState(format!("leaf {} is past the end of a tree of height {}", q, lms_type.h())));
+        }
+        let leaves = lms_type.leaves() as usize;
+        let m = lms_type.m();
+        let mut tree = vec![0; 2 * leaves * m];
+        for r in (1..2 * leaves).rev() {
+            let node = (r as u32).to_be_bytes();
+            let value = if r >= leaves {
+                let k = ots_type.public_key(&id, (r - leaves) as u32, &seed);
+                hash(m, &[&id, &node, &D_LEAF.to_be_bytes(), &k])
+            } else {
+                hash(m, &[&id, &node, &D_INTR.to_be_bytes(), &tree[2 * r * m..(2 * r + 2) * m]])
+            };
+            tree[r * m..(r + 1) * m].copy_from_slice(&value);
+        }
+        Ok(Self { lms_type, ots_type, id, seed, q, tree })
+    }
+
+    fn node(&self, r:usize) -> &[u8] {
+        let m = self.lms_type.m();
+        &self.tree[r * m..(r + 1) * m]
+    }
+
+    pub fn lms_type(&self) -> LmsType {
+        self.lms_type
+    }
+
+    pub fn ots_type(&self) -> LmotsType {
+        self.ots_type
+    }
+
+    /// The next leaf that will sign
+    pub fn next_leaf(&self) -> u32 {
+        self.q
+    }
+
+    /// The number of signatures left
+    pub fn remaining(&self) -> u64 {
+        self.lms_type.leaves() - self.q as u64
+    }
+
+    pub fn public_key(&self) -> LmsPublicKey {
+        LmsPublicKey { lms_type: self.lms_type, ots_type: self.ots_type, id: self.id, root: self.node(1).to_vec() }
+    }
+
+    /// Signs with the next leaf and moves past it, save the key before releasing the signature
+    pub fn sign(&mut self, msg:&[u8]) -> Result<Vec<u8>> {
+        if self.remaining() == 0 {
+            return Err(Error::KeyExhausted);
+        }
+        let q = self.q;
+        self.q += 1;
+        let c = self.ots_type.prf(&self.id, q, C_INDEX, &self.seed);
+        Ok(self.signature(q, &c, msg))
+    }
+
+    /// The signature with leaf `q` and the randomizer `c`, whether or not the leaf was used
+    fn signature(&self, q:u32, c:&[u8], msg:&[u8]) -> Vec<u8> {
+        let mut sig = q.to_be_bytes().to_vec();
+        sig.extend(self.ots_type.sign(&self.id, q, &self.seed, c, msg));
+        sig.extend_from_slice(&self.lms_type.code().to_be_bytes());
+        let node = (1usize << self.lms_type.h()) + q as usize;
+        for i in 0..self.lms_type.h() {
+            sig.extend_from_slice(self.node((node >> i) ^ 1));
+        }
+        sig
+    }
+
+    /// A key for the level below, derived from this key and its next leaf, and the signature of its public key with that leaf
+    fn child(&mut self, lms_type:LmsType, ots_type:LmotsType) -> Result<(LmsPrivateKey, Vec<u8>)> {
+        if self.remaining() == 0 {
+            return Err(Error::KeyExhausted);
+        }
+        let q = self.q.to_be_bytes();
+        let id = hash(16, &[&self.id, &q, &CHILD_ID.to_be_bytes(), &[PRF_J], &self.seed]);
+        let seed = hash(lms_type.m(), &[&self.id, &q, &CHILD_SEED.to_be_bytes(), &[PRF_J], &self.seed]);
+        let child = LmsPrivateKey::new(lms_type, ots_type, id.try_into().unwrap(), &seed)?;
+        let sig = self.sign(&child.public_key().to_bytes())?;
+        Ok((child, sig))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LmsPrivateKey {
+    fn deserialize<D:serde::Deserializer<'de>>(deserializer:D) -> std::result::Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "LmsPrivateKey")]
+        struct State {
+            lms_type:LmsType,
+            ots_type:LmotsType,
+            id:[u8;16],
+            seed:Vec<u8>,
+            q:u32
+        }
+
+        let s = State::deserialize(deserializer)?;
+        LmsPrivateKey::with_state(s.lms_type, s.ots_type, s.id, s.seed, s.q).map_err(|e| match e {
+            Error::MalformedState(_) => serde::de::Error::custom(e),
+            e => serde::de::Error::custom(Error::MalformedState(e.to_string())),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HssPublicKey {
+    /// the number of levels `L`
+    pub levels:u32,
+    pub top:LmsPublicKey
+}
+
+impl HssPublicKey {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.levels.to_be_bytes().as_slice(), &self.top.to_bytes()].concat()
+    }
+
+    pub fn from_bytes(bytes:&[u8]) -> Result<Self> {
+        let levels = read_u32(bytes, 0)?;
+        if levels == 0 || levels as usize > MAX_LEVELS {
+            return Err(Error::InvalidParameters(format!("HSS has 1 to {} levels, not {}", MAX_LEVELS, levels)));
+        }
+        Ok(Self { levels, top: LmsPublicKey::from_bytes(&bytes[4..])? })
+    }
+
+    /// Checks an encoded HSS signature with the algorithm of section 6.3 of the RFC
+    pub fn verify(&self, msg:&[u8], sig:&[u8]) -> bool {
+        self.check(msg, sig).is_ok()
+    }
+
+    fn check(&self, msg:&[u8], sig:&[u8]) -> Result<()> {
+        let signed_keys = read_u32(sig, 0)?;
+        if signed_keys.checked_add(1) != Some(self.levels) {
+            return Err(Error::InvalidParameters(format!("{} signed keys for {} levels", signed_keys, self.levels)));
+        }
+        let mut key = self.top.clone();
+        let mut pos = 4;
+        for _ in 0..signed_keys {
+            let sig_len = signature_len(&sig[pos..])?;
+            let key_sig = sig.get(pos..pos + sig_len).ok_or(Error::InvalidLength { expected: pos + sig_len, found: sig.len() })?;
+            pos += sig_len;
+            let key_len = LmsType::from_code(read_u32(sig, pos)?)?.public_key_len();
+            let next = sig.get(pos..pos + key_len).ok_or(Error::InvalidLength { expected: pos + key_len, found: sig.len() })?;
+            pos += key_len;
+            if !key.verify(next, key_sig) {
+                return Err(Error::InvalidParameters("a signed key does not verify".to_owned()));
+            }
+            key = LmsPublicKey::from_bytes(next)?;
+            if key.lms_type.m() != self.top.lms_type.m() {
+                return Err(Error::InvalidParameters("the levels of HSS have different hash lengths".to_owned()));
+            }
+        }
+        if !key.verify(msg, &sig[pos..]) {
+            return Err(Error::InvalidParameters("the message signature does not verify".to_owned()));
+        }
+        Ok(())
+    }
+}
+
+/// An HSS private key: an LMS key for each level, each one signed by the level above.
+/// When the bottom key runs out of leaves it is replaced with a new key signed by the next leaf of the level above, and so on up the levels.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HssPrivateKey {
+    levels:Vec<LmsPrivateKey>,
+    /// the signature of the public key of level `i + 1` by level `i`
+    signed:Vec<Vec<u8>>
+}
+
+impl HssPrivateKey {
+    /// A key with the parameters of each level from the top down, the identifier `I` of the top tree and its secret seed
+    pub fn new(levels:&[(LmsType, LmotsType)], id:[u8;16], seed:&[u8]) -> Result<Self> {
+        if levels.is_empty() || levels.len() > MAX_LEVELS {
+            return Err(Error::InvalidParameters(format!("HSS has 1 to {} levels, not {}", MAX_LEVELS, levels.len())));
+        }
+        if levels.iter().any(|(lms_type, _)| lms_type.m() != levels[0].0.m()) {
+            return Err(Error::InvalidParameters("the levels of HSS have different hash lengths".to_owned()));
+        }
+        let (lms_type, ots_type) = levels[0];
+        let mut keys = vec![LmsPrivateKey::new(lms_type, ots_type, id, seed)?];
+        let mut signed = Vec::new();
+        for (lms_type, ots_type) in &levels[1..] {
+            let (child, sig) = keys.last_mut().unwrap().child(*lms_type, *ots_type)?;
+            keys.push(child);
+            signed.push(sig);
+        }
+        Ok(Self { levels: keys, signed })
+    }
+
+    pub fn public_key(&self) -> HssPublicKey {
+        HssPublicKey { levels: self.levels.len() as u32, top: self.levels[0].public_key() }
+    }
+
+    /// The LMS keys from the top down
+    pub fn levels(&self) -> &[LmsPrivateKey] {
+        &self.levels
+    }
+
+    /// Signs with the next leaf of the bottom key, save the key before releasing the signature
+    pub fn sign(&mut self, msg:&[u8]) -> Result<Vec<u8>> {
+        let bottom = self.levels.len() - 1;
+        if self.levels[bottom].remaining() == 0 {
+            let d = (0..bottom).rev().find(|d| self.levels[*d].remaining() > 0).ok_or(Error::KeyExhausted)?;
+            for i in d + 1..=bottom {
+                let (upper, lower) = self.levels.split_at_mut(i);
+                let (child, sig) = upper[i - 1].child(lower[0].lms_type, lower[0].ots_type)?;
+                lower[0] = child;
+                self.signed[i - 1] = sig;
+            }
+        }
+        let msg_sig = self.levels[bottom].sign(msg)?;
+
+        let mut sig = (bottom as u32).to_be_bytes().to_vec();
+        for (key_sig, key) in self.signed.iter().zip(&self.levels[1..]) {
+            sig.extend_from_slice(key_sig);
+            sig.extend(key.public_key().to_bytes());
+        }
+        sig.extend(msg_sig);
+        Ok(sig)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for HssPrivateKey {
+    fn deserialize<D:serde::Deserializer<'de>>(deserializer:D) -> std::result::Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "HssPrivateKey")]
+        struct State {
+            levels:Vec<LmsPrivateKey>,
+            signed:Vec<Vec<u8>>
+        }
+
+        let state = State::deserialize(deserializer)?;
+        let invalid = |reason:String| serde::de::Error::custom(Error::MalformedState(reason));
+        if state.levels.is_empty() || state.levels.len() > MAX_LEVELS || state.signed.len() + 1 != state.levels.len() {
+            return Err(invalid(format!("{} levels with {} signed keys", state.levels.len(), state.signed.len())));
+        }
+        if state.levels.iter().any(|key| key.lms_type.m() != state.levels[0].lms_type.m()) {
+            return Err(invalid("the levels have different hash lengths".to_owned()));
+        }
+        for (i, sig) in state.signed.iter().enumerate() {
+            if !state.levels[i].public_key().verify(&state.levels[i + 1].public_key().to_bytes(), sig) {
+                return Err(invalid(format!("the key of level {} is not signed by the level above", i + 1)));
+            }
+        }
+        Ok(Self { levels: state.levels, signed: state.signed })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes:&[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    const ID:[u8;16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+    #[test]
+    fn parameter_sets() {
+        //p and ls from table 1 of RFC 8554 and table 4 of SP 800-208, and the signature lengths
+        let table:Vec<(usize, u32, usize)> = LmotsType::ALL.iter().map(|t| (t.p(), t.ls(), t.signature_len())).collect();
+        assert_eq!(table, [
+            (265, 7, 8516), (133, 6, 4292), (67, 4, 2180), (34, 0, 1124),
+            (200, 8, 4828), (101, 6, 2452), (51, 4, 1252), (26, 0, 652)
+        ]);
+        for t in LmotsType::ALL {
+            assert_eq!(LmotsType::from_code(t.code()), Ok(t));
+        }
+        assert_eq!(LmsType::Sha256M24H15.code(), 0x0c);
+        assert_eq!(LmsType::Sha256M32H25.h(), 25);
+        assert_eq!(LmsType::from_code(0x0e), Ok(LmsType::Sha256M24H25));
+        assert!(LmsType::from_code(4).is_err());
+        assert!(LmotsType::from_code(9).is_err());
+    }
+
+    fn unhex(s:&str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    //test case 2 of appendix F of RFC 8554, which gives the seeds of both levels
+    const TC2_PUBLIC:&str = "000000020000000600000003d08fabd4a2091ff0a8cb4ed834e7453432a58885cd9ba0431235466bff9651c6c92124404d45fa53cf161c28f1ad5a8e";
+    const TC2_SEED:&str = "558b8966c48ae9cb898b423c83443aae014a72f1b1ab5cc85cf1d892903b5439";
+    const TC2_ID:&str = "d08fabd4a2091ff0a8cb4ed834e74534";
+    const TC2_BOTTOM_PUBLIC:&str = "0000000500000004215f83b7ccb9acbcd08db97b0d04dc2ba1cd035833e0e90059603f26e07ad2aad152338e7a5e5984bcd5f7bb4eba40b7";
+    const TC2_BOTTOM_SEED:&str = "a1c4696e2608035a886100d05cd99945eb3370731884a8235e2fb3d4d71f2547";
+    const TC2_BOTTOM_ID:&str = "215f83b7ccb9acbcd08db97b0d04dc2b";
+    const TC2_MESSAGE:&[u8] = b"The enumeration in the Constitution, of certain rights, shall not be construed to deny or disparage others retained by the people.\n";
+
+    #[test]
+    fn rfc8554_test_case_2() {
+        let mut top = LmsPrivateKey::new(LmsType::Sha256M32H10, LmotsType::Sha256N32W4, unhex(TC2_ID).try_into().unwrap(), &unhex(TC2_SEED)).unwrap();
+        let public = HssPublicKey { levels: 2, top: top.public_key() };
+        assert_eq!(hex(&public.to_bytes()), TC2_PUBLIC);
+        let bottom = LmsPrivateKey::new(LmsType::Sha256M32H5, LmotsType::Sha256N32W8, unhex(TC2_BOTTOM_ID).try_into().unwrap(), &unhex(TC2_BOTTOM_SEED)).unwrap();
+        assert_eq!(hex(&bottom.public_key().to_bytes()), TC2_BOTTOM_PUBLIC);
+
+        //the signature of the message is the one of the RFC, with its leaf and randomizer, y[0] follows q, the type and C
+        let msg_sig = bottom.signature(4, &unhex("0eb1ed54a2460d512388cad533138d240534e97b1e82d33bd927d201dfc24ebb"), TC2_MESSAGE);
+        assert_eq!(hex(&msg_sig[40..72]), "11b3649023696f85150b189e50c00e98850ac343a77b3638319c347d7310269d");
+
+        //the randomizer of the signature of the lower key is not reproduced here, so the top key signs it with its own
+        let key_sig = top.sign(&unhex(TC2_BOTTOM_PUBLIC)).unwrap();
+        let sig = [&1u32.to_be_bytes(), key_sig.as_slice(), &unhex(TC2_BOTTOM_PUBLIC), &msg_sig].concat();
+        let public = HssPublicKey::from_bytes(&unhex(TC2_PUBLIC)).unwrap();
+        assert!(public.verify(TC2_MESSAGE, &sig));
+        assert!(!public.verify(&TC2_MESSAGE[1..], &sig));
+
+        //one flipped byte anywhere, in either level's signature or the lower public key, is rejected
+        for i in (0..sig.len()).step_by(97).chain([sig.len() - 1]) {
+            let mut flipped = sig.clone();
+            flipped[i] ^= 1;
+            assert!(!public.verify(TC2_MESSAGE, &flipped), "byte {} flipped", i);
+        }
+        let mut flipped = unhex(TC2_PUBLIC);
+        flipped[20] ^= 1;
+        assert!(!HssPublicKey::from_bytes(&flipped).unwrap().verify(TC2_MESSAGE, &sig));
+    }
+
+    //checked against an independent implementation of the RFC, with the key derivation of appendix A
+    #[test]
+    fn known_keys() {
+        let seed:Vec<u8> = (0..32).collect();
+        let mut key = LmsPrivateKey::new(LmsType::Sha256M32H5, LmotsType::Sha256N32W4, ID, &seed).unwrap();
+        assert_eq!(hex(&key.public_key().root), "a0e11306c37bc1bbcccb535e4af6b0cd6211b96525158bddc4b2e6dc71ece9f8");
+        assert_eq!(hex(&crate::sha256(&key.sign(b"abc").unwrap()).0), "391cfa9fd56fba0d28fd367e5ca61d0dbc66f2c461009e0e31cc7809371701e3");
+
+        let mut key = LmsPrivateKey::new(LmsType::Sha256M24H5, LmotsType::Sha256N24W8, ID, &seed[..24]).unwrap();
+        assert_eq!(hex(&key.public_key().root), "e63256cc1945d13f1bc16fecc8b6174585b5bb0e397a4973");
+        assert_eq!(hex(&crate::sha256(&key.sign(b"abc").unwrap()).0), "137b91e838149440bde08f54708af42f390fc1e55c59ab8ba6fef7db2c4108f9");
+    }
+
+    #[test]
+    fn lms_round_trip() {
+        for ots_type in [LmotsType::Sha256N32W1, LmotsType::Sha256N32W2, LmotsType::Sha256N24W4, LmotsType::Sha256N24W8] {
+            let lms_type = if ots_type.n() == 32 { LmsType::Sha256M32H5 } else { LmsType::Sha256M24H5 };
+            let mut key = LmsPrivateKey::new(lms_type, ots_type, ID, &vec![9; lms_type.m()]).unwrap();
+            let public = LmsPublicKey::from_bytes(&key.public_key().to_bytes()).unwrap();
+            assert_eq!(public, key.public_key());
+
+            let sig = key.sign(b"abc").unwrap();
+            assert_eq!(sig.len(), 8 + ots_type.signature_len() + 5 * lms_type.m());
+            assert_eq!(signature_len(&sig), Ok(sig.len()));
+            assert!(public.verify(b"abc", &sig));
+            assert!(!public.verify(b"abd", &sig));
+            let sig = key.sign(b"abc").unwrap();
+            assert_eq!(read_u32(&sig, 0), Ok(1));
+            assert!(public.verify(b"abc", &sig));
+        }
+    }
+
+    #[test]
+    fn lms_exhaustion() {
+        let mut key = LmsPrivateKey::new(LmsType::Sha256M32H5, LmotsType::Sha256N32W2, ID, &[1; 32]).unwrap();
+        let public = key.public_key();
+        for q in 0..32 {
+            assert_eq!(key.next_leaf(), q);
+            let sig = key.sign(&q.to_be_bytes()).unwrap();
+            assert!(public.verify(&q.to_be_bytes(), &sig));
+        }
+        assert_eq!(key.remaining(), 0);
+        assert_eq!(key.sign(b"one more"), Err(Error::KeyExhausted));
+    }
+
+    #[test]
+    fn malformed_signatures() {
+        let mut key = LmsPrivateKey::new(LmsType::Sha256M32H5, LmotsType::Sha256N32W4, ID, &[1; 32]).unwrap();
+        let public = key.public_key();
+        let sig = key.sign(b"abc").unwrap();
+        assert!(!public.verify(b"abc", &sig[..sig.len() - 1]));
+        assert!(!public.verify(b"abc", &[sig.as_slice(), &[0]].concat()));
+        assert!(!public.verify(b"abc", &[]));
+        for pos in [3, 7, 40, sig.len() - 1] {
+            let mut flipped = sig.clone();
+            flipped[pos] ^= 1;
+            assert!(!public.verify(b"abc", &flipped));
+        }
+        //a leaf past the end of the tree
+        let mut past = sig.clone();
+        past[..4].copy_from_slice(&32u32.to_be_bytes());
+        assert!(!public.verify(b"abc", &past));
+
+        assert!(LmsPublicKey::from_bytes(&public.to_bytes()[1..]).is_err());
+        assert!(LmsPrivateKey::new(LmsType::Sha256M32H5, LmotsType::Sha256N32W4, ID, &[1; 24]).is_err());
+    }
+
+    #[test]
+    fn unsupported_keys() {
+        let too_high = LmsPrivateKey::new(LmsType::Sha256M32H20, LmotsType::Sha256N32W8, ID, &[1; 32]);
+        assert!(matches!(too_high, Err(Error::InvalidParameters(_))));
+        let mixed = LmsPrivateKey::new(LmsType::Sha256M32H5, LmotsType::Sha256N24W4, ID, &[1; 32]);
+        assert!(matches!(mixed, Err(Error::InvalidParameters(_))));
+        let levels = [(LmsType::Sha256M32H5, LmotsType::Sha256N32W4), (LmsType::Sha256M24H5, LmotsType::Sha256N24W4)];
+        assert!(matches!(HssPrivateKey::new(&levels, ID, &[1; 32]), Err(Error::InvalidParameters(_))));
+
+        let mut public = [&LmsType::Sha256M32H5.code().to_be_bytes()[..], &LmotsType::Sha256N24W4.code().to_be_bytes(), &ID].concat();
+        public.extend([0; 32]);
+        assert!(matches!(LmsPublicKey::from_bytes(&public), Err(Error::InvalidParameters(_))));
+        //verification still takes the heights that private keys do not
+        public[7] = LmotsType::Sha256N32W4.code() as u8;
+        public[3] = LmsType::Sha256M32H25.code() as u8;
+        assert!(LmsPublicKey::from_bytes(&public).is_ok());
+    }
+
+    #[test]
+    fn hss_across_trees() {
+        let levels = [(LmsType::Sha256M32H5, LmotsType::Sha256N32W4), (LmsType::Sha256M32H5, LmotsType::Sha256N32W2)];
+        let mut key = HssPrivateKey::new(&levels, ID, &[3; 32]).unwrap();
+        let public = key.public_key();
+        assert_eq!(HssPublicKey::from_bytes(&public.to_bytes()), Ok(public.clone()));
+
+        //the bottom tree has 32 leaves, so the 33rd signature comes from a second bottom key
+        let mut bottom_keys = Vec::new();
+        for i in 0..40u32 {
+            let sig = key.sign(&i.to_be_bytes()).unwrap();
+            assert!(public.verify(&i.to_be_bytes(), &sig));
+            assert!(!public.verify(&(i + 1).to_be_bytes(), &sig));
+            bottom_keys.push(key.levels()[1].public_key());
+        }
+        assert_eq!(key.levels()[0].next_leaf(), 2);
+        assert_ne!(bottom_keys[31], bottom_keys[32]);
+        assert_eq!(bottom_keys[32], bottom_keys[39]);
+
+        let single = HssPrivateKey::new(&levels[..1], ID, &[3; 32]).unwrap();
+        assert_eq!(single.public_key().top, public.top);
+        assert!(HssPrivateKey::new(&[], ID, &[3; 32]).is_err());
+        assert!(HssPublicKey::from_bytes(&[&[0, 0, 0, 9], &public.to_bytes()[4..]].concat()).is_err());
+    }
+
+    #[test]
+    fn hss_rejects_mixed_signatures() {
+        let levels = [(LmsType::Sha256M32H5, LmotsType::Sha256N32W4); 2];
+        let mut key = HssPrivateKey::new(&levels, ID, &[3; 32]).unwrap();
+        let public = key.public_key();
+        let sig = key.sign(b"abc").unwrap();
+        //the signature alone of the bottom level is not an HSS signature with two levels
+        let bottom_start = 4 + signature_len(&sig[4..]).unwrap() + LmsType::Sha256M32H5.public_key_len();
+        let bottom_only = [&0u32.to_be_bytes(), &sig[bottom_start..]].concat();
+        assert!(!public.verify(b"abc", &bottom_only));
+        let mut flipped = sig.clone();
+        flipped[100] ^= 1;
+        assert!(!public.verify(b"abc", &flipped));
+        //a count of signed keys that overflows
+        assert!(!public.verify(b"abc", &[0xff; 4]));
+        assert!(!public.verify(b"abc", &[&[0xff; 4], &sig[4..]].concat()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn saved_state_continues() {
+        let levels = [(LmsType::Sha256M32H5, LmotsType::Sha256N32W4); 2];
+        let mut key = HssPrivateKey::new(&levels, ID, &[3; 32]).unwrap();
+        let public = key.public_key();
+        key.sign(b"first").unwrap();
+        let saved = serde_json::to_string(&key).unwrap();
+
+        let mut loaded:HssPrivateKey = serde_json::from_str(&saved).unwrap();
+        assert_eq!(loaded.public_key(), public);
+        assert_eq!(loaded.levels()[1].next_leaf(), 1);
+        let sig = loaded.sign(b"second").unwrap();
+        assert!(public.verify(b"second", &sig));
+        assert_eq!(sig, key.sign(b"second").unwrap());
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&loaded, &mut cbor).unwrap();
+        assert_eq!(ciborium::from_reader::<HssPrivateKey, _>(&cbor[..]).unwrap().levels()[1].next_leaf(), 2);
+
+        let mut state:serde_json::Value = serde_json::from_str(&saved).unwrap();
+        state["levels"][1]["q"] = serde_json::json!(33);
+        assert!(serde_json::from_value::<HssPrivateKey>(state.clone()).is_err());
+        state["levels"][1]["q"] = serde_json::json!(1);
+        state["levels"][1]["seed"][0] = serde_json::json!(0);
+        assert!(serde_json::from_value::<HssPrivateKey>(state).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+//! Binary Merkle trees over any of the SHA-2 [hashers](crate::hasher), with inclusion proofs.
+//!
+//! How a tree is hashed is set by a [Config]: what happens to the last node of a level with an odd number of nodes,
+//! the prefixes that separate leaf hashes from node hashes, whether the leaves still need to be hashed and whether every hash is applied twice.
+//! [Config::bitcoin] builds the transaction trees of Bitcoin blocks and [Config::default] a domain separated tree like RFC 6962.
+//! # Examples
+//! ```
+//! use jisp_sha2::merkle::{Config, MerkleTree};
+//! use jisp_sha2::sha256::Sha256Hasher;
+//! use jisp_sha2::constants::Sha256;
+//!
+//! type Tree = MerkleTree<Sha256Hasher<Sha256>>;
+//!
+//! let leaves = ["a", "b", "c", "d", "e"];
+//! let tree = Tree::new(Config::default(), &leaves).unwrap();
+//! let proof = tree.proof(2).unwrap();
+//! assert!(Tree::verify(&Config::default(), tree.root(), b"c", &proof));
+//! assert!(!Tree::verify(&Config::default(), tree.root(), b"x", &proof));
+//! ```
+use std::marker::PhantomData;
+
+use crate::error::{Error, Result};
+use crate::hasher::Sha2Hasher;
+
+/// What to do with the last node of a level that has no sibling
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OddNode {
+    /// hash the node with a copy of itself, as Bitcoin does
+    Duplicate,
+    /// move the node up to the next level unchanged, as RFC 6962 does
+    Promote
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    pub odd:OddNode,
+    /// hashed in front of every leaf, only used when `hash_leaves` is set
+    pub leaf_prefix:Vec<u8>,
+    /// hashed in front of every pair of children
+    pub node_prefix:Vec<u8>,
+    /// whether the leaves are data to hash, or already digests that are used as they are
+    pub hash_leaves:bool,
+    /// whether every hash is applied twice, like the double SHA-256 of Bitcoin
+    pub double:bool
+}
+
+impl Config {
+    /// Bitcoin's transaction tree: the leaves are txids in internal byte order, nodes are `sha256d(left || right)` and odd nodes are duplicated.
+    /// Duplicating makes the lists `[a, b, c]` and `[a, b, c, c]` share a root, so a tree alone does not fix the number of leaves.
+    pub fn bitcoin() -> Self {
+        Self { odd: OddNode::Duplicate, leaf_prefix: Vec::new(), node_prefix: Vec::new(), hash_leaves: false, double: true }
+    }
+}
+
+impl Default for Config {
+    /// Leaves hashed as `H(0x00 || leaf)`, nodes as `H(0x01 || left || right)` and odd nodes promoted, which gives the roots of RFC 6962
+    fn default() -> Self {
+        Self { odd: OddNode::Promote, leaf_prefix: vec![0x00], node_prefix: vec![0x01], hash_leaves: true, double: false }
+    }
+}
+
+/// The siblings on the path from a leaf to the root, lowest first.
+/// A node without a sibling adds nothing to the path, whatever the [OddNode] policy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Proof {
+    pub index:usize,
+    pub leaves:usize,
+    pub siblings:Vec<Vec<u8>>
+}
+
+/// A tree with every level kept, so proofs are read off without hashing
+#[derive(Clone)]
+pub struct MerkleTree<H:Sha2Hasher> {
+    config:Config,
+    /// the leaf hashes first and the root last
+    levels:Vec<Vec<Vec<u8>>>,
+    hasher:PhantomData<H>
+}
+
+impl<H:Sha2Hasher> MerkleTree<H> {
+    /// Builds the tree over the leaves, which have to be digests of the right length if the config does not hash them.
+    /// Returns [InvalidParameters](Error::InvalidParameters) without any leaves.
+    pub fn new(config:Config, leaves:&[impl AsRef<[u8]>]) -> Result<Self> {
+        if leaves.is_empty() {
+            return Err(Error::InvalidParameters("a merkle tree needs at least one leaf".to_owned()));
+        }
+        let hashes = leaves.iter().map(|leaf| leaf_hash::<H>(&config, leaf.as_ref())).collect::<Result<Vec<_>>>()?;
+        let mut levels = vec![hashes];
+        while let Some(level) = levels.last().filter(|l| l.len() > 1) {
+            let next = level.chunks(2).map(|pair| match pair {
+                [left, right] => node_hash::<H>(&config, left, right),
+                [single] => match config.odd {
+                    OddNode::Duplicate => node_hash::<H>(&config, single, single),
+                    OddNode::Promote => single.clone(),
+                },
+                _ => unreachable!(),
+            }).collect();
+            levels.push(next);
+        }
+        Ok(Self { config, levels, hasher: PhantomData })
+    }
+
+    pub fn root(&self) -> &[u8] {
+        &self.levels[self.levels.len() - 1][0]
+    }
+
+    /// The number of leaves
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Always false, a tree has at least one leaf
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The hash of the leaf at `index` as it enters the tree
+    pub fn leaf_hash(&self, index:usize) -> Option<&[u8]> {
+        self.levels[0].get(index).map(|h| h.as_slice())
+    }
+
+    /// The inclusion proof of the leaf at `index`, or [InvalidParameters](Error::InvalidParameters) if there is no such leaf
+    pub fn proof(&self, index:usize) -> Result<Proof> {
+        if index >= self.len() {
+            return Err(Error::InvalidParameters(format!("leaf {} is out of range for a tree of {} leaves", index, self.len())));
+        }
+        let mut siblings = Vec::new();
+        let mut i = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(i ^ 1) {
+                siblings.push(sibling.clone());
+            }
+            i /= 2;
+        }
+        Ok(Proof { index, leaves: self.len(), siblings })
+    }
+
+    /// The root that a proof leads to from a leaf, an error if the proof does not fit its number of leaves
+    pub fn proof_root(config:&Config, leaf:&[u8], proof:&Proof) -> Result<Vec<u8>> {
+        if proof.index >= proof.leaves {
+            return Err(Error::InvalidParameters(format!("leaf {} is out of range for a tree of {} leaves", proof.index, proof.leaves)));
+        }
+        let mut node = leaf_hash::<H>(config, leaf)?;
+        let mut used = 0;
+        let (mut i, mut n) = (proof.index, proof.leaves);
+        while n > 1 {
+            if i ^ 1 < n {
+                let sibling = proof.siblings.get(used).ok_or(Error::InvalidLength { expected: used + 1, found: proof.siblings.len() })?;
+                check_length::<H>(sibling)?;
+                used += 1;
+                node = if i % 2 == 0 { node_hash::<H>(config, &node, sibling) } else { node_hash::<H>(config, sibling, &node) };
+            } else if config.odd == OddNode::Duplicate {
+                node = node_hash::<H>(config, &node, &node);
+            }
+            i /= 2;
+            n = n.div_ceil(2);
+        }
+        if used != proof.siblings.len() {
+            return Err(Error::InvalidLength { expected: used, found: proof.siblings.len() });
+        }
+        Ok(node)
+    }
+
+    /// Whether the proof shows that `leaf` is in the tree with this root
+    pub fn verify(config:&Config, root:&[u8], leaf:&[u8], proof:&Proof) -> bool {
+        Self::proof_root(config, leaf, proof).is_ok_and(|r| r == root)
+    }
+}
+
+fn hash<H:Sha2Hasher>(config:&Config, parts:&[&[u8]]) -> Vec<u8> {
+    let mut hasher = H::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    let digest = hasher.finalize_bytes();
+    if config.double { H::digest(&digest) } else { digest }
+}
+
+fn check_length<H:Sha2Hasher>(digest:&[u8]) -> Result<()> {
+    if digest.len() != H::DIGEST_BYTES {
+        return Err(Error::InvalidLength { expected: H::DIGEST_BYTES, found: digest.len() });
+    }
+    Ok(())
+}
+
+fn leaf_hash<H:Sha2Hasher>(config:&Config, leaf:&[u8]) -> Result<Vec<u8>> {
+    if config.hash_leaves {
+        Ok(hash::<H>(config, &[&config.leaf_prefix, leaf]))
+    } else {
+        check_length::<H>(leaf)?;
+        Ok(leaf.to_vec())
+    }
+}
+
+fn node_hash<H:Sha2Hasher>(config:&Config, left:&[u8], right:&[u8]) -> Vec<u8> {
+    hash::<H>(config, &[&config.node_prefix, left, right])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::{from_display_hex, to_display_hex};
+    use crate::constants::{Sha256, Sha512};
+    use crate::digest::Sha256Digest;
+    use crate::sha256::Sha256Hasher;
+    use crate::sha512::Sha512Hasher;
+
+    type Tree256 = MerkleTree<Sha256Hasher<Sha256>>;
+
+    fn root_hex(tree:&Tree256) -> String {
+        to_display_hex(&Sha256Digest::from(<[u8;32]>::try_from(tree.root()).unwrap()))
+    }
+
+    //the four transactions of block 100000
+    const TXIDS:[&str; 4] = [
+        "8c14f0db3df150123e6f3dbbf30f8b955a8249b62ac1d1ff16284aefa3d06d87",
+        "fff2525b8931402dd09222c50775608f75787bd2b87e56995a7bdd30f79702c4",
+        "6359f0868171b1d194cbee1af2f16ea598ae8fad666d9b012c8ed2b79a236ec4",
+        "e9a66845e05d5abc0ad04ec80f774a7e585c6e8db975962d069a522137b80c1d"
+    ];
+
+    #[test]
+    fn bitcoin_block_root() {
+        let txids:Vec<Sha256Digest> = TXIDS.iter().map(|t| from_display_hex(t).unwrap()).collect();
+        let tree = Tree256::new(Config::bitcoin(), &txids).unwrap();
+        assert_eq!(root_hex(&tree), "f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766");
+
+        //an odd number of transactions duplicates the last one
+        let odd = Tree256::new(Config::bitcoin(), &txids[..3]).unwrap();
+        assert_eq!(root_hex(&odd), "fa435470825de273081dcc706b25514c936fa6dc80ab965ce6970d68ddd0b553");
+        let duplicated = Tree256::new(Config::bitcoin(), &[txids[0], txids[1], txids[2], txids[2]]).unwrap();
+        assert_eq!(odd.root(), duplicated.root());
+
+        let proof = odd.proof(2).unwrap();
+        assert_eq!(proof.siblings.len(), 1);
+        assert!(Tree256::verify(&Config::bitcoin(), odd.root(), &txids[2].0, &proof));
+        assert!(Tree256::new(Config::bitcoin(), &[[0u8; 31]]).is_err());
+    }
+
+    #[test]
+    fn promoted_sha512_root() {
+        let leaves:Vec<[u8;1]> = (0..5).map(|i| [i]).collect();
+        let tree = MerkleTree::<Sha512Hasher<Sha512>>::new(Config::default(), &leaves).unwrap();
+        let hex:String = tree.root().iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, concat!(
+            "1a46ea87d7bac814bb71ef63753ee0a09bab85eae852585131a54e05100c9716",
+            "a2d11934256268bb7f9589d9b0cbc9e29175058f47892f85eac1c53bb2f79c34"
+        ));
+    }
+
+    #[test]
+    fn every_proof_verifies() {
+        for odd in [OddNode::Duplicate, OddNode::Promote] {
+            let config = Config { odd, ..Config::default() };
+            for n in 1..=17u8 {
+                let leaves:Vec<[u8;1]> = (0..n).map(|i| [i]).collect();
+                let tree = Tree256::new(config.clone(), &leaves).unwrap();
+                for (i, leaf) in leaves.iter().enumerate() {
+                    let proof = tree.proof(i).unwrap();
+                    assert!(Tree256::verify(&config, tree.root(), leaf, &proof));
+                    assert!(!Tree256::verify(&config, tree.root(), &[n], &proof));
+                    //the proof is bound to the position of the leaf
+                    if n > 1 {
+                        let moved = Proof { index: (i + 1) % n as usize, ..proof.clone() };
+                        assert!(!Tree256::verify(&config, tree.root(), leaf, &moved));
+                    }
+                }
+                assert!(tree.proof(n as usize).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn domain_separation() {
+        let leaves = [b"a", b"b"];
+        let tree = Tree256::new(Config::default(), &leaves).unwrap();
+        //the root is not the hash of a leaf that is the concatenation of the two leaf hashes
+        let forged = [tree.leaf_hash(0).unwrap(), tree.leaf_hash(1).unwrap()].concat();
+        let single = Tree256::new(Config::default(), &[forged]).unwrap();
+        assert_ne!(single.root(), tree.root());
+
+        let plain = Config { leaf_prefix: Vec::new(), node_prefix: Vec::new(), ..Config::default() };
+        assert_ne!(Tree256::new(plain, &leaves).unwrap().root(), tree.root());
+    }
+
+    #[test]
+    fn malformed_proofs() {
+        let leaves = [b"a", b"b", b"c"];
+        let tree = Tree256::new(Config::default(), &leaves).unwrap();
+        let proof = tree.proof(0).unwrap();
+
+        let mut long = proof.clone();
+        long.siblings.push(vec![0; 32]);
+        assert!(Tree256::proof_root(&Config::default(), b"a", &long).is_err());
+        let mut short = proof.clone();
+        short.siblings.pop();
+        assert!(Tree256::proof_root(&Config::default(), b"a", &short).is_err());
+        let mut truncated = proof.clone();
+        truncated.siblings[0].pop();
+        assert!(Tree256::proof_root(&Config::default(), b"a", &truncated).is_err());
+        assert!(!Tree256::verify(&Config::default(), tree.root(), b"a", &Proof { index: 3, ..proof }));
+    }
+}
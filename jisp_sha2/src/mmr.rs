@@ -0,0 +1,407 @@
+//! A Merkle Mountain Range, an append-only accumulator over any of the SHA-2 [hashers](crate::hasher).
+//!
+//! The nodes are numbered in the order they are added, starting at 0. Appending a leaf merges it with the trees of the same height before it,
+//! so the range is a row of perfect binary trees, the peaks, of decreasing height. The root bags the peaks from right to left.
+//! Leaves are hashed as `H(0x00 || data)` and nodes as `H(0x01 || left || right)`, also when bagging.
+//!
+//! Every node is kept, so a [Proof] can be made for any leaf against the current root or against the root of any earlier size.
+//! With the `serde` feature the whole range can be saved and loaded again, the nodes are checked against each other on loading.
+//! # Examples
+//! ```
+//! use jisp_sha2::mmr::Mmr;
+//! use jisp_sha2::sha512::Sha512Hasher;
+//! use jisp_sha2::constants::Sha512;
+//!
+//! let mut mmr = Mmr::<Sha512Hasher<Sha512>>::new();
+//! let first = mmr.append(b"created");
+//! mmr.append(b"renamed");
+//! mmr.append(b"deleted");
+//! assert_eq!(mmr.len(), 4);
+//!
+//! let proof = mmr.proof(first).unwrap();
+//! assert!(Mmr::<Sha512Hasher<Sha512>>::verify(&mmr.root(), b"created", &proof));
+//! ```
+use std::marker::PhantomData;
+
+use crate::error::{Error, Result};
+use crate::hasher::Sha2Hasher;
+
+const LEAF_PREFIX:u8 = 0x00;
+const NODE_PREFIX:u8 = 0x01;
+
+/// The largest range a proof can be for, so that the positions of the nodes above a leaf fit into a `u64`
+pub const MAX_PROOF_SIZE:u64 = u64::MAX >> 1;
+
+/// The height of the node at a position, 0 for leaves
+pub fn height(pos:u64) -> u32 {
+    //in 1 based positions the leftmost node of every height is all ones
+    let mut pos = pos + 1;
+    while !(pos + 1).is_power_of_two() {
+        pos -= (1 << (63 - pos.leading_zeros())) - 1;
+    }
+    63 - pos.leading_zeros()
+}
+
+/// The position of a leaf from the number of leaves before it
+pub fn leaf_position(index:u64) -> u64 {
+    2 * index - index.count_ones() as u64
+}
+
+/// The number of nodes in a range of `leaves` leaves
+pub fn size_for_leaves(leaves:u64) -> u64 {
+    2 * leaves - leaves.count_ones() as u64
+}
+
+/// The positions of the peaks of a range with `size` nodes, from left to right.
+/// Returns `None` if a range cannot have this many nodes.
+pub fn peaks(size:u64) -> Option<Vec<u64>> {
+    let mut peaks = Vec::new();
+    let mut left = size;
+    let mut start = 0;
+    let mut peak_size = u64::MAX.checked_shr(size.leading_zeros()).unwrap_or(0);
+    while peak_size != 0 {
+        if left >= peak_size {
+            peaks.push(start + peak_size - 1);
+            start += peak_size;
+            left -= peak_size;
+        }
+        peak_size >>= 1;
+    }
+    (left == 0).then_some(peaks)
+}
+
+/// The root from the peak hashes, `H(0x01 || p1 || H(0x01 || p2 || ... pn))`. The root of no peaks is the hash of the empty string.
+pub fn bag_peaks<H:Sha2Hasher>(peaks:&[Vec<u8>]) -> Vec<u8> {
+    let mut peaks = peaks.iter().rev();
+    let Some(last) = peaks.next() else { return H::digest(&[]) };
+    peaks.fold(last.clone(), |bag, peak| node_hash::<H>(peak, &bag))
+}
+
+fn leaf_hash<H:Sha2Hasher>(data:&[u8]) -> Vec<u8> {
+    let mut hasher = H::new();
+    hasher.update(&[LEAF_PREFIX]);
+    hasher.update(data);
+    hasher.finalize_bytes()
+}
+
+fn node_hash<H:Sha2Hasher>(left:&[u8], right:&[u8]) -> Vec<u8> {
+    let mut hasher = H::new();
+    hasher.update(&[NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize_bytes()
+}
+
+/// The parent of a node and the position of its sibling
+fn parent(pos:u64) -> (u64, u64) {
+    let h = height(pos);
+    if height(pos + 1) > h {
+        (pos + 1, pos + 1 - (2 << h))
+    } else {
+        let sibling = pos + (2 << h) - 1;
+        (sibling + 1, sibling)
+    }
+}
+
+/// The path from a leaf to its peak and the other peaks of the range
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Proof {
+    pub position:u64,
+    /// the number of nodes in the range the proof was made for
+    pub size:u64,
+    pub siblings:Vec<Vec<u8>>,
+    /// every peak but the one above the leaf, from left to right
+    pub peaks:Vec<Vec<u8>>
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
+pub struct Mmr<H:Sha2Hasher> {
+    nodes:Vec<Vec<u8>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hasher:PhantomData<H>
+}
+
+impl<H:Sha2Hasher> Default for Mmr<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H:Sha2Hasher> Mmr<H> {
+    pub fn new() -> Self {
+        Self { nodes: Vec::new(), hasher: PhantomData }
+    }
+
+    /// The number of nodes, leaves and parents together
+    pub fn len(&self) -> u64 {
+        self.nodes.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn leaf_count(&self) -> u64 {
+        //a peak of height h has 2^h leaves below it
+        self.peak_positions(self.len()).unwrap_or_default().iter().map(|p| 1 << height(*p)).sum()
+    }
+
+    /// The hash of the node at a position
+    pub fn node(&self, pos:u64) -> Option<&[u8]> {
+        self.nodes.get(usize::try_from(pos).ok()?).map(|n| n.as_slice())
+    }
+
+    /// Adds a leaf and the parents it completes, and returns the position of the leaf
+    pub fn append(&mut self, data:&[u8]) -> u64 {
+        let pos = self.len();
+        self.nodes.push(leaf_hash::<H>(data));
+        let mut h = 0;
+        while height(self.len()) > h {
+            let right = &self.nodes[self.nodes.len() - 1];
+            let left = &self.nodes[self.nodes.len() - (2 << h)];
+            let node = node_hash::<H>(left, right);
+            self.nodes.push(node);
+            h += 1;
+        }
+        pos
+    }
+
+    /// The hashes of the current peaks from left to right
+    pub fn peaks(&self) -> Vec<Vec<u8>> {
+        self.peaks_at(self.len()).unwrap_or_default()
+    }
+
+    pub fn root(&self) -> Vec<u8> {
+        bag_peaks::<H>(&self.peaks())
+    }
+
+    /// The root the range had when it had `size` nodes
+    pub fn root_at(&self, size:u64) -> Result<Vec<u8>> {
+        Ok(bag_peaks::<H>(&self.peaks_at(size)?))
+    }
+
+    /// The inclusion proof of the leaf at `pos` against the current root
+    pub fn proof(&self, pos:u64) -> Result<Proof> {
+        self.proof_at(pos, self.len())
+    }
+
+    /// The inclusion proof of the leaf at `pos` against the root the range had with `size` nodes
+    pub fn proof_at(&self, pos:u64, size:u64) -> Result<Proof> {
+        let peak_positions = self.peak_positions(size)?;
+        if pos >= size || height(pos) != 0 {
+            return Err(Error::InvalidParameters(format!("there is no leaf at {} in a range of {} nodes", pos, size)));
+        }
+        let mut siblings = Vec::new();
+        let mut node = pos;
+        while !peak_positions.contains(&node) {
+            let (up, sibling) = parent(node);
+            siblings.push(self.nodes[sibling as usize].clone());
+            node = up;
+        }
+        let peaks = peak_positions.iter().filter(|p| **p != node).map(|p| self.nodes[*p as usize].clone()).collect();
+        Ok(Proof { position: pos, size, siblings, peaks })
+    }
+
+    /// The root a proof leads to from the leaf data
+    pub fn proof_root(data:&[u8], proof:&Proof) -> Result<Vec<u8>> {
+        let malformed = |reason:String| Err(Error::InvalidParameters(reason));
+        if proof.size > MAX_PROOF_SIZE {
+            return malformed(format!("a proof is for at most {} nodes, not {}", MAX_PROOF_SIZE, proof.size));
+        }
+        let Some(peak_positions) = peaks(proof.size) else {
+            return malformed(format!("a range cannot have {} nodes", proof.size));
+        };
+        if proof.position >= proof.size || height(proof.position) != 0 {
+            return malformed(format!("there is no leaf at {} in a range of {} nodes", proof.position, proof.size));
+        }
+        let mut node = leaf_hash::<H>(data);
+        let mut pos = proof.position;
+        let mut siblings = proof.siblings.iter();
+        while !peak_positions.contains(&pos) {
+            let (up, sibling_pos) = parent(pos);
+            let sibling = siblings.next().ok_or(Error::InvalidLength { expected: proof.siblings.len() + 1, found: proof.siblings.len() })?;
+            node = if sibling_pos < pos { node_hash::<H>(sibling, &node) } else { node_hash::<H>(&node, sibling) };
+            pos = up;
+        }
+        if siblings.next().is_some() || proof.peaks.len() + 1 != peak_positions.len() {
+            return malformed("the proof does not fit the size of the range".to_owned());
+        }
+        let index = peak_positions.iter().position(|p| *p == pos).unwrap_or(0);
+        let mut all = proof.peaks.clone();
+        all.insert(index, node);
+        Ok(bag_peaks::<H>(&all))
+    }
+
+    /// Whether the proof shows that the leaf data is in the range with this root
+    pub fn verify(root:&[u8], data:&[u8], proof:&Proof) -> bool {
+        Self::proof_root(data, proof).is_ok_and(|r| r == root)
+    }
+
+    fn peak_positions(&self, size:u64) -> Result<Vec<u64>> {
+        if size > self.len() {
+            return Err(Error::InvalidParameters(format!("the range has {} nodes, not {}", self.len(), size)));
+        }
+        peaks(size).ok_or(Error::InvalidParameters(format!("a range cannot have {} nodes", size)))
+    }
+
+    fn peaks_at(&self, size:u64) -> Result<Vec<Vec<u8>>> {
+        Ok(self.peak_positions(size)?.into_iter().map(|p| self.nodes[p as usize].clone()).collect())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, H:Sha2Hasher> serde::Deserialize<'de> for Mmr<H> {
+    fn deserialize<D:serde::Deserializer<'de>>(deserializer:D) -> std::result::Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "Mmr")]
+        struct State {
+            nodes:Vec<Vec<u8>>
+        }
+
+        let state = State::deserialize(deserializer)?;
+        let invalid = |reason:String| serde::de::Error::custom(Error::MalformedState(reason));
+        if peaks(state.nodes.len() as u64).is_none() {
+            return Err(invalid(format!("a range cannot have {} nodes", state.nodes.len())));
+        }
+        for (pos, node) in state.nodes.iter().enumerate() {
+            if node.len() != H::DIGEST_BYTES {
+                return Err(invalid(format!("node {} has {} bytes instead of {}", pos, node.len(), H::DIGEST_BYTES)));
+            }
+            //every parent has to be the hash of its two children
+            let h = height(pos as u64);
+            if h > 0 && *node != node_hash::<H>(&state.nodes[pos - (2 << (h - 1))], &state.nodes[pos - 1]) {
+                return Err(invalid(format!("node {} is not the hash of its children", pos)));
+            }
+        }
+        Ok(Self { nodes: state.nodes, hasher: PhantomData })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{Sha256, Sha512};
+    use crate::sha256::Sha256Hasher;
+    use crate::sha512::Sha512Hasher;
+
+    type Mmr256 = Mmr<Sha256Hasher<Sha256>>;
+
+    fn hex(bytes:&[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn range(leaves:u8) -> Mmr256 {
+        let mut mmr = Mmr256::new();
+        for i in 0..leaves {
+            mmr.append(&[i]);
+        }
+        mmr
+    }
+
+    #[test]
+    fn positions() {
+        //the heights of the first 19 nodes, the range of 11 leaves has peaks at 14, 17 and 18
+        let heights:Vec<u32> = (0..19).map(height).collect();
+        assert_eq!(heights, [0, 0, 1, 0, 0, 1, 2, 0, 0, 1, 0, 0, 1, 2, 3, 0, 0, 1, 0]);
+        assert_eq!(peaks(19), Some(vec![14, 17, 18]));
+        assert_eq!(peaks(0), Some(vec![]));
+        assert_eq!(peaks(2), None);
+        assert_eq!((0..8).map(leaf_position).collect::<Vec<_>>(), [0, 1, 3, 4, 7, 8, 10, 11]);
+
+        let mut mmr = Mmr256::new();
+        for i in 0..11u64 {
+            assert_eq!(mmr.append(&[i as u8]), leaf_position(i));
+            assert_eq!(mmr.leaf_count(), i + 1);
+        }
+        assert_eq!(mmr.len(), 19);
+        assert_eq!(size_for_leaves(11), 19);
+    }
+
+    #[test]
+    fn roots() {
+        let mmr = range(7);
+        assert_eq!(hex(&mmr.root()), "3560191803028444b232018ac047fdb561c09c23a7a6876c85e08b5e4d48e9f3");
+        assert_eq!(mmr.peaks().len(), 3);
+        assert_eq!(hex(&Mmr256::new().root()), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+
+        //a range that grows keeps its old roots
+        let mut grown = mmr.clone();
+        grown.append(&[7]);
+        assert_eq!(grown.peaks().len(), 1);
+        assert_eq!(grown.root_at(mmr.len()).unwrap(), mmr.root());
+        assert!(grown.root_at(2).is_err());
+    }
+
+    #[test]
+    fn every_proof_verifies() {
+        let mmr = range(11);
+        let root = mmr.root();
+        for i in 0..11u8 {
+            let pos = leaf_position(i as u64);
+            let proof = mmr.proof(pos).unwrap();
+            assert!(Mmr256::verify(&root, &[i], &proof));
+            assert!(!Mmr256::verify(&root, &[i + 1], &proof));
+            //and against every earlier root that already had the leaf
+            for leaves in i as u64 + 1..=11 {
+                let size = size_for_leaves(leaves);
+                let past = mmr.proof_at(pos, size).unwrap();
+                assert!(Mmr256::verify(&mmr.root_at(size).unwrap(), &[i], &past));
+            }
+        }
+        assert!(mmr.proof(2).is_err());
+        assert!(mmr.proof(mmr.len()).is_err());
+
+        let mut proof = mmr.proof(0).unwrap();
+        proof.peaks.pop();
+        assert!(Mmr256::proof_root(&[0], &proof).is_err());
+
+        //positions whose parents do not fit into a u64
+        let huge = Proof { position: u64::MAX - 1, size: u64::MAX, siblings: Vec::new(), peaks: Vec::new() };
+        assert!(!Mmr256::verify(&root, &[0], &huge));
+        let huge = Proof { position: MAX_PROOF_SIZE - 1, size: MAX_PROOF_SIZE + 1, ..huge };
+        assert!(Mmr256::proof_root(&[0], &huge).is_err());
+        for position in [0, leaf_position((1 << 62) - 1)] {
+            let largest = Proof { position, size: MAX_PROOF_SIZE, siblings: vec![vec![0; 32]; 62], peaks: Vec::new() };
+            assert!(!Mmr256::verify(&root, &[0], &largest));
+        }
+    }
+
+    #[test]
+    fn sha512_range() {
+        let mut mmr = Mmr::<Sha512Hasher<Sha512>>::new();
+        for i in 0..5u8 {
+            mmr.append(&[i]);
+        }
+        assert_eq!(mmr.root().len(), 64);
+        let proof = mmr.proof(leaf_position(4)).unwrap();
+        assert!(proof.siblings.is_empty());
+        assert!(Mmr::<Sha512Hasher<Sha512>>::verify(&mmr.root(), &[4], &proof));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mmr = range(6);
+        let json = serde_json::to_string(&mmr).unwrap();
+        let mut loaded:Mmr256 = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.root(), mmr.root());
+        loaded.append(&[6]);
+        assert_eq!(loaded.root(), range(7).root());
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&mmr, &mut cbor).unwrap();
+        assert_eq!(ciborium::from_reader::<Mmr256, _>(&cbor[..]).unwrap().root(), mmr.root());
+
+        let mut nodes:Vec<Vec<u8>> = serde_json::from_value(serde_json::from_str::<serde_json::Value>(&json).unwrap()["nodes"].clone()).unwrap();
+        nodes[2][0] ^= 1;
+        let tampered = serde_json::json!({ "nodes": nodes }).to_string();
+        assert!(serde_json::from_str::<Mmr256>(&tampered).is_err());
+        nodes.pop();
+        nodes.pop();
+        let truncated = serde_json::json!({ "nodes": nodes }).to_string();
+        assert!(serde_json::from_str::<Mmr256>(&truncated).is_err());
+    }
+}
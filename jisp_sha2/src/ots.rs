@@ -0,0 +1,376 @@
+//! Hash based one-time signatures: Lamport and Winternitz (WOTS and WOTS+), on any of the SHA-2 [hashers](crate::hasher).
+//! These are meant for teaching and experiments, not for protecting anything real.
+//!
+//! The secret values are derived from a seed with [HMAC](crate::hmac), so the seed has to be random and kept secret.
+//! A one-time key is broken once it signs two different messages, so a secret key remembers that it was used
+//! and [sign](LamportSecretKey::sign) returns [KeyExhausted](Error::KeyExhausted) the second time.
+//! Secret keys are not [Clone] for the same reason, but deriving a key from the same seed twice gets around all of this.
+//!
+//! The message is hashed first and the bits of its digest are signed.
+//! Lamport reveals one of two secrets per bit. Winternitz splits the digest into base `w` digits and walks a hash chain as far as each digit,
+//! with a checksum so that no digit can be increased without decreasing another one.
+//! WOTS+ puts a public bitmask and key into every step of the chains, which keeps it secure with weaker assumptions on the hash.
+//! # Examples
+//! ```
+//! use jisp_sha2::ots::{WotsParams, WotsSecretKey, WotsVariant};
+//! use jisp_sha2::sha256::Sha256Hasher;
+//! use jisp_sha2::constants::Sha256;
+//! use jisp_sha2::Error;
+//!
+//! let params = WotsParams::new(16, WotsVariant::Plus).unwrap();
+//! let mut key = WotsSecretKey::<Sha256Hasher<Sha256>>::from_seed(params, b"a random secret seed");
+//! let public = key.public_key();
+//!
+//! let signature = key.sign(b"hello").unwrap();
+//! assert!(public.verify(b"hello", &signature));
+//! assert!(!public.verify(b"goodbye", &signature));
+//! assert_eq!(key.sign(b"goodbye").unwrap_err(), Error::KeyExhausted);
+//! ```
+use std::marker::PhantomData;
+
+use crate::error::{Error, Result};
+use crate::hasher::Sha2Hasher;
+use crate::hmac::hmac;
+
+/// The secret value number `i` of a key, derived from the seed
+fn secret<H:Sha2Hasher>(seed:&[u8], label:&[u8], i:usize) -> Vec<u8> {
+    hmac::<H>(seed, &[label, &(i as u64).to_be_bytes()].concat())
+}
+
+/// Bit `i` of the digest counted from the most significant bit of the first byte
+fn bit(digest:&[u8], i:usize) -> usize {
+    (digest[i / 8] >> (7 - i % 8) & 1) as usize
+}
+
+/// Secret and public values in pairs, the first for a 0 bit and the second for a 1 bit
+pub struct LamportSecretKey<H:Sha2Hasher> {
+    secrets:Vec<Vec<u8>>,
+    used:bool,
+    hasher:PhantomData<H>
+}
+
+#[derive(Clone)]
+pub struct LamportPublicKey<H:Sha2Hasher> {
+    hashes:Vec<Vec<u8>>,
+    hasher:PhantomData<H>
+}
+
+/// The revealed secret for every bit of the message digest
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LamportSignature {
+    pub revealed:Vec<Vec<u8>>
+}
+
+impl<H:Sha2Hasher> LamportSecretKey<H> {
+    /// A key of two secrets for each bit of the digest
+    pub fn from_seed(seed:&[u8]) -> Self {
+        let secrets = (0..16 * H::DIGEST_BYTES).map(|i| secret::<H>(seed, b"lamport", i)).collect();
+        Self { secrets, used: false, hasher: PhantomData }
+    }
+
+    pub fn public_key(&self) -> LamportPublicKey<H> {
+        LamportPublicKey { hashes: self.secrets.iter().map(|s| H::digest(s)).collect(), hasher: PhantomData }
+    }
+
+    pub fn is_used(&self) -> bool {
+        self.used
+    }
+
+    /// Signs once, every later call returns [KeyExhausted](Error::KeyExhausted)
+    pub fn sign(&mut self, msg:&[u8]) -> Result<LamportSignature> {
+        if self.used {
+            return Err(Error::KeyExhausted);
+        }
+        self.used = true;
+        let digest = H::digest(msg);
+        let revealed = (0..8 * H::DIGEST_BYTES).map(|i| self.secrets[2 * i + bit(&digest, i)].clone()).collect();
+        Ok(LamportSignature { revealed })
+    }
+}
+
+impl<H:Sha2Hasher> LamportPublicKey<H> {
+    /// The public values in pairs, as they would be published
+    pub fn hashes(&self) -> &[Vec<u8>] {
+        &self.hashes
+    }
+
+    pub fn verify(&self, msg:&[u8], signature:&LamportSignature) -> bool {
+        let digest = H::digest(msg);
+        signature.revealed.len() == 8 * H::DIGEST_BYTES && signature.revealed.iter().enumerate()
+            .all(|(i, s)| H::digest(s) == self.hashes[2 * i + bit(&digest, i)])
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WotsVariant {
+    /// chains of plain hashes, `H(H(...H(x)))`
+    Plain,
+    /// every step masks the value with a public bitmask and hashes it with a public key, `H(k || (x ^ r_j))`
+    Plus
+}
+
+/// The Winternitz parameter `w`, the base of the digits, and the variant of the chains
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WotsParams {
+    w:u32,
+    variant:WotsVariant
+}
+
+impl WotsParams {
+    /// Returns [InvalidParameters](Error::InvalidParameters) unless `w` is 2, 4, 16 or 256, so that digits never cross a byte
+    pub fn new(w:u32, variant:WotsVariant) -> Result<Self> {
+        if ![2, 4, 16, 256].contains(&w) {
+            return Err(Error::InvalidParameters(format!("the Winternitz parameter has to be 2, 4, 16 or 256, not {}", w)));
+        }
+        Ok(Self { w, variant })
+    }
+
+    pub fn w(&self) -> u32 {
+        self.w
+    }
+
+    pub fn variant(&self) -> WotsVariant {
+        self.variant
+    }
+
+    /// The bits in a digit
+    pub fn log_w(&self) -> u32 {
+        self.w.trailing_zeros()
+    }
+
+    /// The number of digits of a digest of `n` bytes
+    pub fn len1(&self, n:usize) -> usize {
+        (8 * n).div_ceil(self.log_w() as usize)
+    }
+
+    /// The number of digits of the checksum, enough for its largest value `len1 * (w - 1)`
+    pub fn len2(&self, n:usize) -> usize {
+        let max = self.len1(n) * (self.w as usize - 1);
+        max.ilog2() as usize / self.log_w() as usize + 1
+    }
+
+    /// The number of chains, and of values in a key or signature
+    pub fn len(&self, n:usize) -> usize {
+        self.len1(n) + self.len2(n)
+    }
+
+    /// The sum of how far each digit is from `w - 1`
+    pub fn checksum(&self, digits:&[u32]) -> u32 {
+        digits.iter().map(|d| self.w - 1 - d).sum()
+    }
+
+    /// The base `w` digits of a digest followed by the digits of its checksum, most significant first
+    pub fn digits(&self, digest:&[u8]) -> Vec<u32> {
+        let log_w = self.log_w() as usize;
+        let mut digits:Vec<u32> = (0..self.len1(digest.len()))
+            .map(|i| (0..log_w).fold(0, |d, b| d << 1 | bit(digest, i * log_w + b) as u32))
+            .collect();
+        let checksum = self.checksum(&digits);
+        let len2 = self.len2(digest.len());
+        digits.extend((0..len2).rev().map(|i| checksum >> (i * log_w) & (self.w - 1)));
+        digits
+    }
+}
+
+/// The public bitmasks and key of WOTS+, empty for plain WOTS
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Masks {
+    pub key:Vec<u8>,
+    /// one bitmask for each step of a chain
+    pub bitmasks:Vec<Vec<u8>>
+}
+
+impl Masks {
+    fn chain<H:Sha2Hasher>(&self, params:&WotsParams, mut x:Vec<u8>, start:u32, steps:u32) -> Vec<u8> {
+        for j in start..start + steps {
+            x = match params.variant {
+                WotsVariant::Plain => H::digest(&x),
+                WotsVariant::Plus => {
+                    let masked:Vec<u8> = x.iter().zip(&self.bitmasks[j as usize]).map(|(a, b)| a ^ b).collect();
+                    H::digest(&[self.key.as_slice(), &masked].concat())
+                },
+            };
+        }
+        x
+    }
+}
+
+pub struct WotsSecretKey<H:Sha2Hasher> {
+    params:WotsParams,
+    secrets:Vec<Vec<u8>>,
+    masks:Masks,
+    used:bool,
+    hasher:PhantomData<H>
+}
+
+#[derive(Clone)]
+pub struct WotsPublicKey<H:Sha2Hasher> {
+    params:WotsParams,
+    /// the ends of the chains
+    ends:Vec<Vec<u8>>,
+    masks:Masks,
+    hasher:PhantomData<H>
+}
+
+/// One value from the middle of each chain
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WotsSignature {
+    pub values:Vec<Vec<u8>>
+}
+
+impl<H:Sha2Hasher> WotsSecretKey<H> {
+    pub fn from_seed(params:WotsParams, seed:&[u8]) -> Self {
+        let secrets = (0..params.len(H::DIGEST_BYTES)).map(|i| secret::<H>(seed, b"wots", i)).collect();
+        let masks = match params.variant {
+            WotsVariant::Plain => Masks { key: Vec::new(), bitmasks: Vec::new() },
+            WotsVariant::Plus => Masks {
+                key: secret::<H>(seed, b"wots+ key", 0),
+                bitmasks: (0..params.w as usize - 1).map(|j| secret::<H>(seed, b"wots+ mask", j)).collect()
+            },
+        };
+        Self { params, secrets, masks, used: false, hasher: PhantomData }
+    }
+
+    pub fn params(&self) -> WotsParams {
+        self.params
+    }
+
+    /// Walks every chain to its end, `w - 1` steps from the secret
+    pub fn public_key(&self) -> WotsPublicKey<H> {
+        let ends = self.secrets.iter().map(|s| self.masks.chain::<H>(&self.params, s.clone(), 0, self.params.w - 1)).collect();
+        WotsPublicKey { params: self.params, ends, masks: self.masks.clone(), hasher: PhantomData }
+    }
+
+    pub fn is_used(&self) -> bool {
+        self.used
+    }
+
+    /// Signs once, every later call returns [KeyExhausted](Error::KeyExhausted)
+    pub fn sign(&mut self, msg:&[u8]) -> Result<WotsSignature> {
+        if self.used {
+            return Err(Error::KeyExhausted);
+        }
+        self.used = true;
+        let digits = self.params.digits(&H::digest(msg));
+        let values = self.secrets.iter().zip(digits).map(|(s, d)| self.masks.chain::<H>(&self.params, s.clone(), 0, d)).collect();
+        Ok(WotsSignature { values })
+    }
+}
+
+impl<H:Sha2Hasher> WotsPublicKey<H> {
+    pub fn params(&self) -> WotsParams {
+        self.params
+    }
+
+    pub fn ends(&self) -> &[Vec<u8>] {
+        &self.ends
+    }
+
+    pub fn masks(&self) -> &Masks {
+        &self.masks
+    }
+
+    /// Walks each chain on from the signature, the rest of the way to its end
+    pub fn verify(&self, msg:&[u8], signature:&WotsSignature) -> bool {
+        let digits = self.params.digits(&H::digest(msg));
+        signature.values.len() == digits.len() && signature.values.iter().all(|v| v.len() == H::DIGEST_BYTES)
+            && signature.values.iter().zip(digits).zip(&self.ends)
+                .all(|((v, d), end)| self.masks.chain::<H>(&self.params, v.clone(), d, self.params.w - 1 - d) == *end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{Sha256, Sha512};
+    use crate::sha256::Sha256Hasher;
+    use crate::sha512::Sha512Hasher;
+
+    type H256 = Sha256Hasher<Sha256>;
+    type H512 = Sha512Hasher<Sha512>;
+
+    #[test]
+    fn lamport() {
+        let mut key = LamportSecretKey::<H256>::from_seed(b"seed");
+        let public = key.public_key();
+        assert_eq!(public.hashes().len(), 512);
+
+        let signature = key.sign(b"abc").unwrap();
+        assert!(key.is_used());
+        assert!(public.verify(b"abc", &signature));
+        assert!(!public.verify(b"abd", &signature));
+        assert_eq!(key.sign(b"abc"), Err(Error::KeyExhausted));
+
+        let mut short = signature.clone();
+        short.revealed.pop();
+        assert!(!public.verify(b"abc", &short));
+
+        let mut key = LamportSecretKey::<H512>::from_seed(b"seed");
+        let public = key.public_key();
+        assert!(public.verify(b"abc", &key.sign(b"abc").unwrap()));
+    }
+
+    #[test]
+    fn lengths_and_checksum() {
+        let params = |w| WotsParams::new(w, WotsVariant::Plain).unwrap();
+        //the lengths of WOTS+ in RFC 8391 for n = 32 and w = 16
+        assert_eq!((params(16).len1(32), params(16).len2(32)), (64, 3));
+        assert_eq!((params(4).len1(32), params(4).len2(32)), (128, 5));
+        assert_eq!((params(256).len1(32), params(256).len2(32)), (32, 2));
+        assert_eq!((params(2).len1(64), params(2).len2(64)), (512, 10));
+        assert!(WotsParams::new(8, WotsVariant::Plain).is_err());
+
+        let digits = params(16).digits(&[0x12, 0x34]);
+        //digits 1, 2, 3, 4 and a checksum of 4 * 15 - 10 = 50 = 0x32 in two digits
+        assert_eq!(digits, [1, 2, 3, 4, 3, 2]);
+        let zeros = params(16).digits(&[0; 32]);
+        assert_eq!(params(16).checksum(&zeros[..64]), 960);
+        assert_eq!(zeros[64..], [3, 12, 0]);
+    }
+
+    #[test]
+    fn wots_round_trip() {
+        for variant in [WotsVariant::Plain, WotsVariant::Plus] {
+            for w in [2, 4, 16, 256] {
+                let params = WotsParams::new(w, variant).unwrap();
+                let mut key = WotsSecretKey::<H256>::from_seed(params, b"seed");
+                let public = key.public_key();
+                let signature = key.sign(b"message").unwrap();
+                assert_eq!(signature.values.len(), params.len(32));
+                assert!(public.verify(b"message", &signature));
+                assert!(!public.verify(b"massage", &signature));
+                assert_eq!(key.sign(b"message").unwrap_err(), Error::KeyExhausted);
+            }
+        }
+
+        let params = WotsParams::new(16, WotsVariant::Plus).unwrap();
+        let mut key = WotsSecretKey::<H512>::from_seed(params, b"seed");
+        let public = key.public_key();
+        assert!(public.verify(b"message", &key.sign(b"message").unwrap()));
+    }
+
+    #[test]
+    fn wots_forgery_needs_the_checksum() {
+        let params = WotsParams::new(16, WotsVariant::Plus).unwrap();
+        let mut key = WotsSecretKey::<H256>::from_seed(params, b"seed");
+        let public = key.public_key();
+        let msg = b"message";
+        let signature = key.sign(msg).unwrap();
+
+        //moving a chain one step further matches a digest with a larger digit,
+        //but the checksum digits of that digest are smaller and cannot be walked back
+        let digits = params.digits(&H256::digest(msg));
+        let i = digits.iter().position(|d| *d < 15).unwrap();
+        let mut forged = signature.clone();
+        forged.values[i] = public.masks().chain::<H256>(&params, forged.values[i].clone(), digits[i], 1);
+        assert!(!public.verify(msg, &forged));
+
+        let plain = WotsSecretKey::<H256>::from_seed(WotsParams::new(16, WotsVariant::Plain).unwrap(), b"seed").public_key();
+        assert_ne!(plain.ends(), public.ends());
+    }
+}
@@ -0,0 +1,229 @@
+//! A toy proof of work: find a nonce such that `sha256d(header || nonce)` starts with a number of zero bits.
+//! The nonce is appended as 8 little endian bytes and the zero bits are counted from the first byte of the digest.
+//!
+//! The header is the same for every nonce, so its complete blocks are compressed once and each attempt starts from that midstate.
+//! Only the last block, with the end of the header, the nonce and the padding, and the second hash are computed per nonce.
+//! # Examples
+//! ```
+//! use std::sync::atomic::AtomicBool;
+//! use jisp_sha2::pow::{leading_zero_bits, pow_hash, search};
+//!
+//! let stop = AtomicBool::new(false);
+//! let solution = search(b"workshop block", 8, 2, &stop, |_| ()).unwrap().unwrap();
+//! assert!(leading_zero_bits(&solution.hash) >= 8);
+//! assert_eq!(pow_hash(b"workshop block", solution.nonce), solution.hash);
+//! ```
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::bitcoin::sha256d;
+use crate::constants::{Constants, Sha256};
+use crate::conversions::u32_words_to_be_bytes;
+use crate::digest::Sha256Digest;
+use crate::error::{Error, Result};
+use crate::preprocessing::final_blocks;
+use crate::sha256::{block_words, sha256_block, Sha256Hasher};
+
+/// How often the progress callback of [search] is called
+pub const PROGRESS_INTERVAL:Duration = Duration::from_millis(100);
+
+/// hashes a worker does before adding them to the shared count
+const BATCH:u64 = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Solution {
+    pub nonce:u64,
+    pub hash:Sha256Digest
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progress {
+    /// hashes tried by all threads together
+    pub hashes:u64,
+    pub elapsed:Duration
+}
+
+impl Progress {
+    /// Hashes per second
+    pub fn hashrate(&self) -> f64 {
+        self.hashes as f64 / self.elapsed.as_secs_f64().max(1e-9)
+    }
+}
+
+/// The number of zero bits at the start of the digest
+pub fn leading_zero_bits(digest:&Sha256Digest) -> u32 {
+    let mut bits = 0;
+    for b in digest.as_bytes() {
+        bits += b.leading_zeros();
+        if *b != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+/// The chaining value after the complete blocks of the header,
+/// and the padded last one or two blocks with room for the nonce
+struct Midstate {
+    hash:[u32;8],
+    last:[u8;128],
+    /// where the nonce goes in `last`
+    nonce_at:usize,
+    /// the bytes of `last` that are used, 64 or 128
+    len:usize,
+    k:[u32;64]
+}
+
+impl Midstate {
+    fn new(header:&[u8]) -> Self {
+        let mut prefix = Sha256Hasher::<Sha256>::new();
+        prefix.update(header);
+        let tail = &header[header.len() - header.len() % 64..];
+        let bits = (header.len() as u128 + 8) * 8;
+        let padded = final_blocks(&[tail, &[0; 8]].concat(), 64, bits);
+        let mut last = [0u8; 128];
+        last[..padded.len()].copy_from_slice(&padded);
+        Self { hash: prefix.midstate(), last, nonce_at: tail.len(), len: padded.len(), k: Sha256.constant_words() }
+    }
+
+    /// `sha256d(header || nonce)` on copies of the midstate and the last blocks, so nothing is allocated per nonce
+    fn pow_hash(&self, nonce:u64) -> Sha256Digest {
+        let mut last = self.last;
+        last[self.nonce_at..self.nonce_at + 8].copy_from_slice(&nonce.to_le_bytes());
+        let mut hash = self.hash;
+        for chunk in last[..self.len].chunks_exact(64) {
+            sha256_block(&mut hash, &block_words(chunk), &self.k);
+        }
+
+        //the second hash is a single block: the 8 words of the first, the 1 bit and a length of 256 bits
+        let mut block = [0u32; 16];
+        block[..8].copy_from_slice(&hash);
+        block[8] = 0x8000_0000;
+        block[15] = 256;
+        let mut second = Sha256.initial_hash();
+        sha256_block(&mut second, &block, &self.k);
+        Sha256Digest::from(u32_words_to_be_bytes(&second))
+    }
+}
+
+/// `sha256d(header || nonce)` computed without a midstate, to check a [Solution]
+pub fn pow_hash(header:&[u8], nonce:u64) -> Sha256Digest {
+    sha256d(&[header, &nonce.to_le_bytes()].concat())
+}
+
+/// Searches for a nonce that gives at least `difficulty` leading zero bits on `threads` threads,
+/// thread `i` tries the nonces `i`, `i + threads`, `i + 2·threads` and so on.
+///
+/// `progress` is called on the calling thread every [PROGRESS_INTERVAL] and once at the end.
+/// Setting `stop` ends the search with `None`, and the search sets it itself once a solution is found so that every thread stops.
+/// Returns [InvalidParameters](Error::InvalidParameters) for a difficulty above 256 bits.
+pub fn search(header:&[u8], difficulty:u32, threads:usize, stop:&AtomicBool, mut progress:impl FnMut(Progress)) -> Result<Option<Solution>> {
+    if difficulty > 256 {
+        return Err(Error::InvalidParameters(format!("a SHA-256 digest has 256 bits, {} leading zeros are impossible", difficulty)));
+    }
+    let threads = threads.max(1);
+    let midstate = Midstate::new(header);
+
+    let hashes = AtomicU64::new(0);
+    let started = Instant::now();
+    let report = |hashes:&AtomicU64| Progress { hashes: hashes.load(Ordering::Relaxed), elapsed: started.elapsed() };
+
+    let solution = thread::scope(|s| {
+        let (tx, rx) = mpsc::channel();
+        for i in 0..threads {
+            let tx = tx.clone();
+            let (midstate, hashes) = (&midstate, &hashes);
+            s.spawn(move|| {
+                let mut count = 0;
+                let mut nonce = i as u64;
+                while !stop.load(Ordering::Relaxed) {
+                    let hash = midstate.pow_hash(nonce);
+                    count += 1;
+                    if leading_zero_bits(&hash) >= difficulty {
+                        let _ = tx.send(Solution { nonce, hash });
+                        break;
+                    }
+                    if count == BATCH {
+                        hashes.fetch_add(count, Ordering::Relaxed);
+                        count = 0;
+                    }
+                    nonce = match nonce.checked_add(threads as u64) {
+                        Some(n) => n,
+                        None => break,
+                    };
+                }
+                hashes.fetch_add(count, Ordering::Relaxed);
+            });
+        }
+        drop(tx);
+
+        loop {
+            match rx.recv_timeout(PROGRESS_INTERVAL) {
+                Ok(solution) => {
+                    stop.store(true, Ordering::Relaxed);
+                    return Some(solution);
+                },
+                Err(mpsc::RecvTimeoutError::Timeout) => progress(report(&hashes)),
+                //every thread stopped or ran out of nonces
+                Err(mpsc::RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    });
+
+    progress(report(&hashes));
+    Ok(solution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn midstate_matches_full_hash() {
+        //headers that end before, at and after a block boundary
+        for len in [0, 55, 56, 63, 64, 100] {
+            let header = vec![0x42u8; len];
+            let stop = AtomicBool::new(false);
+            let solution = search(&header, 4, 3, &stop, |_| ()).unwrap().unwrap();
+            assert_eq!(pow_hash(&header, solution.nonce), solution.hash);
+            assert!(leading_zero_bits(&solution.hash) >= 4);
+            assert!(stop.load(Ordering::Relaxed));
+        }
+    }
+
+    #[test]
+    fn midstate_hashes() {
+        for len in [0, 47, 48, 55, 56, 63, 64, 119, 120, 200] {
+            let header:Vec<u8> = (0..len as u8).collect();
+            let midstate = Midstate::new(&header);
+            for nonce in [0, 1, 0x0123_4567_89ab_cdef, u64::MAX] {
+                assert_eq!(midstate.pow_hash(nonce), pow_hash(&header, nonce));
+            }
+        }
+    }
+
+    #[test]
+    fn progress_and_stop() {
+        let stop = AtomicBool::new(false);
+        let mut last = None;
+        search(b"abc", 12, 4, &stop, |p| last = Some(p)).unwrap().unwrap();
+        //the final report counts every hash, at least the one of the solution
+        assert!(last.unwrap().hashes >= 1);
+
+        let stopped = AtomicBool::new(true);
+        assert_eq!(search(b"abc", 64, 2, &stopped, |_| ()).unwrap(), None);
+        assert!(search(b"abc", 257, 2, &stopped, |_| ()).is_err());
+    }
+
+    #[test]
+    fn zero_bits() {
+        let mut bytes = [0xffu8; 32];
+        assert_eq!(leading_zero_bits(&Sha256Digest::from(bytes)), 0);
+        bytes[0] = 0;
+        bytes[1] = 0x1f;
+        assert_eq!(leading_zero_bits(&Sha256Digest::from(bytes)), 11);
+        assert_eq!(leading_zero_bits(&Sha256Digest::from([0; 32])), 256);
+    }
+}
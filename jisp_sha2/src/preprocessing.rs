@@ -1,35 +1,104 @@
-//! Functions for performing the standard preprocessing steps before applying the SHA-2 algorithm
+//! Functions for performing the standard preprocessing steps before applying the SHA-2 algorithm.
+//! The functions that return message blocks as [Uint](crypto_bigint::Uint)s need the `bigint` feature,
+//! the [hashers](crate::hasher) pad with [final_blocks] instead.
 
+#[cfg(feature = "bigint")]
 use crypto_bigint::{Uint, U1024, U512};
+#[cfg(feature = "bigint")]
 use crate::conversions;
+#[cfg(feature = "bigint")]
+use crate::error::{Error, Result};
 
 pub fn string_to_encoding(s: &str) -> Vec<u8> {
     s.as_bytes().into()
 }
 
+#[cfg(feature = "bigint")]
 pub fn sha256_preprocessing(s: &str) -> Vec<U512> {
     standard_preprocessing::<8, 1>(s)
 }
 
+#[cfg(feature = "bigint")]
 pub fn sha512_preprocessing(s: &str) -> Vec<U1024> {
     standard_preprocessing::<16, 2>(s)
 }
 
+#[cfg(feature = "bigint")]
 pub fn standard_preprocessing<const BLOCK: usize, const SUFFIX: usize>(s: &str) -> Vec<Uint<BLOCK>> {
     let bytes = string_to_encoding(s);
+    byte_preprocessing::<BLOCK, SUFFIX>(&bytes)
+}
+
+/// Same as [sha256_preprocessing] but for raw bytes, so no text encoding is applied to the message
+/// # Examples
+/// ```
+/// use jisp_sha2::preprocessing::{sha256_byte_preprocessing, sha256_preprocessing};
+///
+/// let blocks = sha256_byte_preprocessing(&[0x61, 0x62, 0x63]);
+///
+/// assert_eq!(blocks, sha256_preprocessing("abc"));
+/// ```
+#[cfg(feature = "bigint")]
+pub fn sha256_byte_preprocessing(bytes: &[u8]) -> Vec<U512> {
+    byte_preprocessing::<8, 1>(bytes)
+}
+
+/// Same as [sha512_preprocessing] but for raw bytes, so no text encoding is applied to the message
+#[cfg(feature = "bigint")]
+pub fn sha512_byte_preprocessing(bytes: &[u8]) -> Vec<U1024> {
+    byte_preprocessing::<16, 2>(bytes)
+}
+
+/// Pads the bytes and appends the length of the message in bits as the suffix
+#[cfg(feature = "bigint")]
+pub fn byte_preprocessing<const BLOCK: usize, const SUFFIX: usize>(bytes: &[u8]) -> Vec<Uint<BLOCK>> {
     let l = 8 * bytes.len();
-    let mut l_vec = [0 as u64; SUFFIX];
+    let mut l_vec = [0u64; SUFFIX];
     l_vec[SUFFIX - 1] = l as u64;
 
-    let words = byte_padding(&bytes);
+    let words = pad_bytes(bytes);
     word_padding(&words, l_vec)
 }
 
+/// Same as [byte_preprocessing] but returns an [Error] instead of panicking or silently wrapping the length.
+/// The length is written into the last word of the suffix, so a message can be at most `2^64 - 1` bits long.
+/// # Examples
+/// ```
+/// use jisp_sha2::Error;
+/// use jisp_sha2::preprocessing::{try_byte_preprocessing, sha256_preprocessing};
+///
+/// assert_eq!(try_byte_preprocessing::<8, 1>(b"abc"), Ok(sha256_preprocessing("abc")));
+/// assert!(matches!(try_byte_preprocessing::<8, 0>(b"abc"), Err(Error::InvalidParameters(_))));
+/// ```
+#[cfg(feature = "bigint")]
+pub fn try_byte_preprocessing<const BLOCK: usize, const SUFFIX: usize>(bytes: &[u8]) -> Result<Vec<Uint<BLOCK>>> {
+    if SUFFIX == 0 {
+        return Err(Error::InvalidParameters("the suffix needs at least one word for the message length".to_owned()));
+    }
+    let l = (bytes.len() as u128) * 8;
+    if l > u64::MAX as u128 {
+        return Err(Error::MessageTooLong { max_bits: u64::MAX as u128 });
+    }
+    let mut l_vec = [0u64; SUFFIX];
+    l_vec[SUFFIX - 1] = l as u64;
+
+    let words = pad_bytes(bytes);
+    try_word_padding(&words, l_vec)
+}
+
+#[cfg(feature = "bigint")]
 pub fn custom_preprocessing<const BLOCK: usize, const SUFFIX: usize>(bytes: Vec<u8>, suffix:[u64;SUFFIX]) -> Vec<Uint<BLOCK>> {
     let words = byte_padding(&bytes);
     word_padding(&words, suffix)
 }
 
+/// Same as [custom_preprocessing] but returns [InvalidParameters](Error::InvalidParameters) if the suffix does not fit into a block
+#[cfg(feature = "bigint")]
+pub fn try_custom_preprocessing<const BLOCK: usize, const SUFFIX: usize>(bytes: Vec<u8>, suffix:[u64;SUFFIX]) -> Result<Vec<Uint<BLOCK>>> {
+    let words = byte_padding(&bytes);
+    try_word_padding(&words, suffix)
+}
+
 /// Packs 64 bit words into 512 bit blocks by padding the message with 0s and adding the suffix l (usually the length of the message) at the end of the final block.
 /// # Examples
 /// The word padding function completely trusts you that the length you give was the original length of the message
@@ -45,19 +114,33 @@ pub fn custom_preprocessing<const BLOCK: usize, const SUFFIX: usize>(bytes: Vec<
 ///
 /// assert_eq!(res, vec![expected])
 /// ```
+#[cfg(feature = "bigint")]
+#[allow(clippy::ptr_arg)]
 pub fn word_padding<const BSIZE: usize, const SUFFIX: usize>(
     v: &Vec<u64>,
     l: [u64; SUFFIX],
 ) -> Vec<Uint<BSIZE>> {
+    match try_word_padding(v, l) {
+        Ok(blocks) => blocks,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+/// Same as [word_padding] but returns [InvalidParameters](Error::InvalidParameters) instead of panicking when `SUFFIX` is larger than `BSIZE`
+#[cfg(feature = "bigint")]
+pub fn try_word_padding<const BSIZE: usize, const SUFFIX: usize>(
+    v: &[u64],
+    l: [u64; SUFFIX],
+) -> Result<Vec<Uint<BSIZE>>> {
     let mut blocks = Vec::new();
     let mut block = [0u64; BSIZE];
     let mut block_index = 0;
 
     if SUFFIX > BSIZE {
-        panic!(
+        return Err(Error::InvalidParameters(format!(
             "Size SUFFIX:{} is larger than block size BSIZE: {}",
             SUFFIX, BSIZE
-        );
+        )));
     }
 
     for word in v.iter() {
@@ -65,25 +148,48 @@ pub fn word_padding<const BSIZE: usize, const SUFFIX: usize>(
         block_index += 1;
         if block_index == BSIZE {
             block_index = 0;
-            blocks.push(conversions::from_u64_words(&mut block));
+            blocks.push(conversions::from_u64_array(&std::mem::replace(&mut block, [0; BSIZE])));
         }
     }
 
-    let mut i = BSIZE - SUFFIX;
+    let i = BSIZE - SUFFIX;
     //check if length fits in remaining block space
     if i < block_index {
-        blocks.push(conversions::from_u64_words(&mut block));
+        blocks.push(conversions::from_u64_array(&std::mem::replace(&mut block, [0; BSIZE])));
     }
 
     //append l to the end of the final block and push
-    for l_elem in l {
-        block[i] = l_elem;
-        i += 1;
-    }
-    blocks.push(conversions::from_u64_words(&mut block));
+    block[i..].copy_from_slice(&l);
+    blocks.push(conversions::from_u64_array(&std::mem::replace(&mut block, [0; BSIZE])));
+
+    Ok(blocks)
+}
 
-    return blocks;
+/// Pads the last, incomplete block of a message: a 1 bit, 0s and the message length in `bits`,
+/// which takes the last 8 bytes of a 64 byte block or the last 16 bytes of a 128 byte block.
+/// Returns one or two complete blocks.
+/// # Examples
+/// ```
+/// use jisp_sha2::preprocessing::final_blocks;
+///
+/// let blocks = final_blocks(b"abc", 64, 24);
+/// assert_eq!(blocks.len(), 64);
+/// assert_eq!(blocks[..4], [0x61, 0x62, 0x63, 0x80]);
+/// assert_eq!(blocks[63], 24);
+///
+/// assert_eq!(final_blocks(&[0; 56], 64, 448).len(), 128);
+/// ```
+pub fn final_blocks(rest:&[u8], block_bytes:usize, bits:u128) -> Vec<u8> {
+    let length_bytes = block_bytes / 8;
+    let mut blocks = rest.to_vec();
+    blocks.push(0x80);
+    while !(blocks.len() + length_bytes).is_multiple_of(block_bytes) {
+        blocks.push(0);
+    }
+    blocks.extend_from_slice(&bits.to_be_bytes()[16 - length_bytes..]);
+    blocks
 }
+
 /// Merges groups of 8 bytes into 64 bit words
 /// We extend the length of the list of bytes by adding a 1 bit at the end and enough 0s such that we have a multiple of 64 bits
 ///  # Example
@@ -110,7 +216,13 @@ pub fn word_padding<const BSIZE: usize, const SUFFIX: usize>(
 ///
 /// assert_eq!(res,vec![5, 1 << 63])
 /// ```
+#[allow(clippy::ptr_arg)]
 pub fn byte_padding(v: &Vec<u8>) -> Vec<u64> {
+    pad_bytes(v)
+}
+
+/// [byte_padding] on a slice
+fn pad_bytes(v: &[u8]) -> Vec<u64> {
     let mut words = Vec::new();
     let mut word = [0; 8];
     let mut byte_i = 0;
@@ -128,13 +240,13 @@ pub fn byte_padding(v: &Vec<u8>) -> Vec<u64> {
     word[byte_i] = 0x80u8;
     words.push(bytes_to_u64(word));
 
-    return words;
+    words
 }
 
 pub fn bytes_to_u64(v: [u8; 8]) -> u64 {
     let mut res: u64 = 0;
     for i in v.iter() {
-        res = res << 8;
+        res <<= 8;
         res += *i as u64;
     }
     res
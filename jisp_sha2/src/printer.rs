@@ -1,8 +1,11 @@
 //! simple functions for printing lists of bytes with spaces inbetween every 4 or 8 bytes
+#[cfg(feature = "bigint")]
 use crypto_bigint::Uint;
+#[cfg(feature = "bigint")]
 use crate::conversions::to_u64_words;
 
 ///Splits either every 4 bytes or every 8 bytes based on the `split4` boolean
+#[cfg(feature = "bigint")]
 pub fn print_blocks<const BLOCK:usize>(v: &Vec<Uint<BLOCK>>, split4:bool) -> String {
     let mut res = "".to_owned();
     for n in v {
@@ -13,6 +16,7 @@ pub fn print_blocks<const BLOCK:usize>(v: &Vec<Uint<BLOCK>>, split4:bool) -> Str
 }
 
 ///Splits either every 4 bytes or every 8 bytes based on the `split4` boolean
+#[allow(clippy::ptr_arg)]
 pub fn print_word_string(v:&Vec<u64>, split4:bool) -> String {
     let mut res = "".to_owned();
     let m : u64 = 1 << 32;  
@@ -28,6 +32,7 @@ pub fn print_word_string(v:&Vec<u64>, split4:bool) -> String {
 }
 
 //splits after every u32 word. Which is once every 4 bytes.
+#[allow(clippy::ptr_arg)]
 pub fn print_u32_word_string(v:&Vec<u32>) -> String {
     let mut res = "".to_owned();
     for i in v.iter() {
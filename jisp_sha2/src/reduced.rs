@@ -0,0 +1,144 @@
+//! SHA-256 and SHA-512 with fewer rounds, for cryptanalysis exercises.
+//! **These are not secure hash functions**, the standard algorithms are in [sha256](crate::sha256) and [sha512](crate::sha512).
+//!
+//! A [RoundConfig] sets the number of rounds of every compression and whether the chaining value is added back in after it (the feed-forward).
+//! Instead of a single hash, every chaining value is returned: the initial hash value followed by the state after each message block.
+//! # Examples
+//! ```
+//! use jisp_sha2::reduced::{RoundConfig, sha256_reduced};
+//! use jisp_sha2::preprocessing::sha256_preprocessing;
+//! use jisp_sha2::constants::Sha256;
+//!
+//! let chaining = sha256_reduced(sha256_preprocessing("abc"), &Sha256, RoundConfig::new(64)).unwrap();
+//! assert_eq!(chaining.len(), 2);
+//! assert_eq!(chaining[1][0], 0xba7816bf);
+//!
+//! let reduced = sha256_reduced(sha256_preprocessing("abc"), &Sha256, RoundConfig::new(16).without_feed_forward()).unwrap();
+//! assert_ne!(reduced[1], chaining[1]);
+//! ```
+use crypto_bigint::{U1024, U512};
+
+use crate::constants::Constants;
+use crate::conversions::{to_u32_array, to_u64_words};
+use crate::error::{Error, Result};
+use crate::sha256::sha256_rounds;
+use crate::sha512::sha512_rounds;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoundConfig {
+    /// rounds per compression, at most 64 for SHA-256 and 80 for SHA-512
+    pub rounds:usize,
+    /// whether the chaining value is added to the registers after the rounds, as the standard does
+    pub feed_forward:bool
+}
+
+impl RoundConfig {
+    /// `rounds` rounds with the feed-forward
+    pub fn new(rounds:usize) -> Self {
+        Self { rounds, feed_forward: true }
+    }
+
+    pub fn without_feed_forward(self) -> Self {
+        Self { feed_forward: false, ..self }
+    }
+}
+
+/// One compression of SHA-256 with a configurable number of rounds, starting from any chaining value.
+/// Returns [InvalidParameters](Error::InvalidParameters) if more than 64 rounds are requested
+pub fn sha256_reduced_compression<C:Constants<64,u32>>(chaining:&[u32;8], block:U512, constants:&C, config:RoundConfig) -> Result<[u32;8]> {
+    check_rounds("SHA-256", config, 64)?;
+    let registers = sha256_rounds(chaining, &to_u32_array(block), &constants.constant_words(), config.rounds, |_| ());
+    Ok(feed_forward(chaining, registers, config, u32::wrapping_add))
+}
+
+/// One compression of SHA-512 with a configurable number of rounds, starting from any chaining value.
+/// Returns [InvalidParameters](Error::InvalidParameters) if more than 80 rounds are requested
+pub fn sha512_reduced_compression<C:Constants<80,u64>>(chaining:&[u64;8], block:U1024, constants:&C, config:RoundConfig) -> Result<[u64;8]> {
+    check_rounds("SHA-512", config, 80)?;
+    let registers = sha512_rounds(chaining, &to_u64_words(block), &constants.constant_words(), config.rounds, |_| ());
+    Ok(feed_forward(chaining, registers, config, u64::wrapping_add))
+}
+
+/// Reduced SHA-256 over [preprocessed](crate::preprocessing::sha256_preprocessing) message blocks.
+/// Returns the initial hash value followed by the chaining value after every block, the last one takes the place of the hash.
+/// Returns [InvalidParameters](Error::InvalidParameters) if more than 64 rounds are requested
+pub fn sha256_reduced<C:Constants<64,u32>>(msg:Vec<U512>, constants:&C, config:RoundConfig) -> Result<Vec<[u32;8]>> {
+    check_rounds("SHA-256", config, 64)?;
+    let mut chaining = vec![constants.initial_hash()];
+    for block in msg {
+        let last = chaining[chaining.len() - 1];
+        chaining.push(sha256_reduced_compression(&last, block, constants, config)?);
+    }
+    Ok(chaining)
+}
+
+/// Reduced SHA-512 over [preprocessed](crate::preprocessing::sha512_preprocessing) message blocks.
+/// Returns the initial hash value followed by the chaining value after every block, the last one takes the place of the hash.
+/// Returns [InvalidParameters](Error::InvalidParameters) if more than 80 rounds are requested
+pub fn sha512_reduced<C:Constants<80,u64>>(msg:Vec<U1024>, constants:&C, config:RoundConfig) -> Result<Vec<[u64;8]>> {
+    check_rounds("SHA-512", config, 80)?;
+    let mut chaining = vec![constants.initial_hash()];
+    for block in msg {
+        let last = chaining[chaining.len() - 1];
+        chaining.push(sha512_reduced_compression(&last, block, constants, config)?);
+    }
+    Ok(chaining)
+}
+
+fn check_rounds(name:&str, config:RoundConfig, max:usize) -> Result<()> {
+    if config.rounds > max {
+        return Err(Error::InvalidParameters(format!("{} has at most {} rounds, not {}", name, max, config.rounds)));
+    }
+    Ok(())
+}
+
+fn feed_forward<T:Copy>(chaining:&[T;8], registers:[T;8], config:RoundConfig, add:impl Fn(T, T) -> T) -> [T;8] {
+    if !config.feed_forward {
+        return registers;
+    }
+    std::array::from_fn(|i| add(chaining[i], registers[i]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{Sha256, Sha512};
+    use crate::conversions::{to_u32_array, to_u64_words};
+    use crate::preprocessing::{sha256_preprocessing, sha512_preprocessing};
+
+    #[test]
+    fn full_rounds_match_standard() {
+        let msg = "a message that is long enough to need two blocks of sha-256, unlike abc";
+        let chaining = sha256_reduced(sha256_preprocessing(msg), &Sha256, RoundConfig::new(64)).unwrap();
+        assert_eq!(chaining.len(), 3);
+        assert_eq!(chaining[2], to_u32_array(crate::sha_256(sha256_preprocessing(msg))));
+
+        let chaining = sha512_reduced(sha512_preprocessing(msg), &Sha512, RoundConfig::new(80)).unwrap();
+        assert_eq!(chaining[1].to_vec(), to_u64_words(crate::sha_512(sha512_preprocessing(msg))));
+    }
+
+    #[test]
+    fn feed_forward_is_optional() {
+        let block = sha256_preprocessing("abc")[0];
+        let iv = Sha256.initial_hash();
+        let with = sha256_reduced_compression(&iv, block, &Sha256, RoundConfig::new(20)).unwrap();
+        let without = sha256_reduced_compression(&iv, block, &Sha256, RoundConfig::new(20).without_feed_forward()).unwrap();
+        for i in 0..8 {
+            assert_eq!(with[i], without[i].wrapping_add(iv[i]));
+        }
+
+        //no rounds at all leave the registers untouched
+        assert_eq!(sha256_reduced_compression(&iv, block, &Sha256, RoundConfig::new(0).without_feed_forward()), Ok(iv));
+    }
+
+    #[test]
+    fn too_many_rounds() {
+        let res = sha512_reduced(sha512_preprocessing("abc"), &Sha512, RoundConfig::new(81));
+        assert!(matches!(res, Err(Error::InvalidParameters(_))));
+        let block = sha256_preprocessing("abc")[0];
+        let res = sha256_reduced_compression(&Sha256.initial_hash(), block, &Sha256, RoundConfig::new(65));
+        assert!(matches!(res, Err(Error::InvalidParameters(_))));
+        //an empty message is checked too
+        assert!(sha256_reduced(Vec::new(), &Sha256, RoundConfig::new(65)).is_err());
+    }
+}
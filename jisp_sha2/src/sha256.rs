@@ -1,171 +1,352 @@
 //! This is the SHA-256 Algorithm. 
-//! You can use the standard implementations of [sha_256] and [sha_224] or you can use custom constants and initial hash values with [sha256_internal]
+//! You can hash bytes with [sha256] and [sha224], or incrementally with a [Sha256Hasher] that also takes custom constants and initial hash values.
+//! With the `bigint` feature there are the implementations on preprocessed blocks, [sha_256], [sha_224] and [sha256_internal].
 
 
-use std::ops::{BitXor, BitAnd};
-
+#[cfg(feature = "bigint")]
 use crypto_bigint::{U512,U256};
-use crate::conversions;
+use crate::{conversions, preprocessing};
+use crate::digest::{Sha224Digest, Sha256Digest};
+use crate::family::{self, Sha256Words};
+use crate::error::{Error, Result};
 use crate::constants::{Constants, Sha256, Sha224};
 
+/// The SHA-256 hash of a message of bytes
+/// # Examples
+/// ```
+/// use jisp_sha2::sha256::sha256;
+///
+/// assert_eq!(sha256(b"abc").to_hex(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+/// ```
+pub fn sha256(msg:&[u8]) -> Sha256Digest {
+    let mut hasher = Sha256Hasher::<Sha256>::new();
+    hasher.update(msg);
+    hasher.finalize()
+}
+
+/// The SHA-224 hash of a message of bytes, the first 28 bytes of SHA-256 with the SHA-224 initial hash value
+pub fn sha224(msg:&[u8]) -> Sha224Digest {
+    let mut hasher = Sha256Hasher::<Sha224>::new();
+    hasher.update(msg);
+    let mut truncated = [0u8; 28];
+    truncated.copy_from_slice(&hasher.finalize().0[..28]);
+    Sha224Digest::from(truncated)
+}
 
 /// The SHA-256 Algorithm, only use this after having [preprocessed](crate::preprocessing::sha256_preprocessing) your data into message blocks
+#[cfg(feature = "bigint")]
 pub fn sha_256(m:Vec<U512>) -> U256 {
-    sha256_internal::<Sha256>(m)
+    sha256_internal(m, &Sha256)
 }
 
 /// The SHA-224 Algorithm, only use this after having [preprocessed](crate::preprocessing::sha256_preprocessing) your data into message blocks
 /// Note that this is essentially the [SHA-256](sha_256) algorithm, except with different constants and a truncated result.
+#[cfg(feature = "bigint")]
 pub fn sha_224(m:Vec<U512>) -> [u32;7] {
-    let hash = sha256_internal::<Sha224>(m);
-    let words = conversions::to_u32_words(hash);
+    let hash = sha256_internal(m, &Sha224);
+    let words:[u32;8] = conversions::to_u32_array(hash);
     let mut truncated_words = [0u32; 7];
-    for i in 0..7 {
-        truncated_words[i] = words[i];
-    } 
-    return truncated_words;
+    truncated_words.copy_from_slice(&words[..7]);
+    truncated_words
 }
 
 
 /// The internal loop of the SHA-256 algorithm, 
 /// you can use different initial hash and constant values by implementing the [Constants](crate::constants::Constants) trait on a new object
-pub fn sha256_internal<C:Constants<64,u32>>(msg:Vec<U512>) -> U256 {
-    let mut hash = C::initial_hash();
+/// or with [CustomConstants](crate::constants::CustomConstants) if they are only known at runtime
+#[cfg(feature = "bigint")]
+pub fn sha256_internal<C:Constants<64,u32>>(msg:Vec<U512>, constants:&C) -> U256 {
+    let mut hash = constants.initial_hash();
+    let k = constants.constant_words();
     for block in msg {
-        let registers = sha256_compression::<C>(&hash, block);
-        for i in 0..8 {
-            hash[i] = hash[i].wrapping_add(registers[i]);
-        }
+        sha256_block(&mut hash, &conversions::to_u32_array(block), &k);
     }
-    conversions::from_u32_words(&hash.to_vec())
+    conversions::from_u32_array(&hash)
     
 }
 
-fn sha256_compression<C:Constants<64,u32>>(intermediate_hash:&[u32;8], msg:U512) -> [u32;8] {
-    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = intermediate_hash.clone();
-    let constants_k = C::constant_words();
-    let expanded_blocks = sha256_message_schedule(msg);
-
-    for j in 0..64 {
-        let T1 = wrapping_sum(vec![
-            h, sigma_l1(e), ch(e,f,g), constants_k[j], expanded_blocks[j] 
-        ]);
-        let T2 = sigma_l0(a).wrapping_add(maj(a,b,c));
+/// An incremental version of [sha256] for messages that arrive in pieces, such as a large file being read from disk.
+/// The message is fed in with [update](Sha256Hasher::update) and no preprocessing is needed, since the padding is added by [finalize](Sha256Hasher::finalize).
+/// # Examples
+/// ```
+/// use jisp_sha2::sha256::{Sha256Hasher, sha256};
+/// use jisp_sha2::constants::Sha256;
+///
+/// let mut hasher = Sha256Hasher::<Sha256>::new();
+/// hasher.update(b"a");
+/// hasher.update(b"bc");
+///
+/// assert_eq!(hasher.finalize(), sha256(b"abc"));
+/// ```
+///
+/// With the `serde` feature the state of a hasher, the chaining value, the buffered bytes, the length and the constants,
+/// can be saved part way through a message and the hashing resumed later.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Sha256Hasher<C:Constants<64,u32>> {
+    hash:[u32;8],
+    buffer:Vec<u8>,
+    length:u64,
+    constants:C,
+}
 
-        h = g;
-        g = f;
-        f = e;
-        e = d.wrapping_add(T1);
-        d = c;
-        c = b;
-        b = a;
-        a = T1.wrapping_add(T2);
+impl<C:Constants<64,u32> + Default> Sha256Hasher<C> {
+    pub fn new() -> Self {
+        Self::with_constants(C::default())
     }
-
-    [a, b, c, d, e, f, g, h]
 }
 
-// ------ Six Logical Functions -------
+impl<C:Constants<64,u32>> Sha256Hasher<C> {
+    /// A hasher for constants that are only known at runtime, such as [CustomConstants](crate::constants::CustomConstants)
+    pub fn with_constants(constants:C) -> Self {
+        Self { hash: constants.initial_hash(), buffer: Vec::with_capacity(64), length: 0, constants }
+    }
 
-fn ch(x:u32, y:u32, z:u32) -> u32 {
-    let x_and_y = x.bitand(y);
-    let x_and_z = inv(x).bitand(z);
-    return x_and_y.bitxor(x_and_z);
-}
+    /// Continues from a midstate, the chaining value after the first `length` bytes of a message.
+    /// Returns [InvalidParameters](crate::Error::InvalidParameters) if `length` is not a whole number of 64 byte blocks.
+    pub fn from_midstate(constants:C, midstate:[u32;8], length:u64) -> Result<Self> {
+        if !length.is_multiple_of(64) {
+            return Err(Error::InvalidParameters(format!("a midstate is only defined after whole blocks, not after {} bytes", length)));
+        }
+        Ok(Self { hash: midstate, buffer: Vec::with_capacity(64), length, constants })
+    }
 
-fn maj(x:u32, y:u32, z:u32) -> u32 {
-    let x_and_y = x.bitand(y);
-    let y_and_z = y.bitand(z);
-    let x_and_z = x.bitand(z);
-    return x_and_y.bitxor(y_and_z).bitxor(x_and_z);
-}
+    /// Appends bytes to the message, every completed block of 64 bytes is compressed right away
+    pub fn update(&mut self, bytes:&[u8]) {
+        self.length = self.length.wrapping_add(bytes.len() as u64);
+        self.buffer.extend_from_slice(bytes);
+        let full = self.buffer.len() - self.buffer.len() % 64;
+        let k = self.constants.constant_words();
+        for chunk in self.buffer[..full].chunks_exact(64) {
+            sha256_block(&mut self.hash, &block_words(chunk), &k);
+        }
+        self.buffer.drain(..full);
+    }
 
-fn sigma_l0(x:u32) -> u32 {
-    let s2 = x.rotate_right(2);
-    let s13 = x.rotate_right(13);
-    let s22 = x.rotate_right(22);
-    return s2.bitxor(s13).bitxor(s22);
-}
+    /// Same as [update](Sha256Hasher::update) but returns [MessageTooLong](crate::Error::MessageTooLong)
+    /// instead of wrapping the length once the message no longer fits into the 64-bit length field
+    pub fn try_update(&mut self, bytes:&[u8]) -> Result<()> {
+        let max_bits = u64::MAX as u128;
+        match self.length.checked_add(bytes.len() as u64) {
+            Some(length) if (length as u128) <= max_bits / 8 => {
+                self.update(bytes);
+                Ok(())
+            },
+            _ => Err(Error::MessageTooLong { max_bits }),
+        }
+    }
 
-fn sigma_l1(x:u32) -> u32 {
-    let s6 = x.rotate_right(6);
-    let s11 = x.rotate_right(11);
-    let s25 = x.rotate_right(25);
-    return s6.bitxor(s11).bitxor(s25);
+    /// The number of message bytes given to the hasher so far
+    pub fn length(&self) -> u64 {
+        self.length
+    }
 
-}
+    /// The chaining value after the last complete block, bytes that do not fill a block yet are not part of it
+    pub fn midstate(&self) -> [u32;8] {
+        self.hash
+    }
 
-fn sigma_s0(x:u32) -> u32 {
-    let s7 = x.rotate_right(7);
-    let s18 = x.rotate_right(18);
-    let r3 = x >> 3;
-    return s7.bitxor(s18).bitxor(r3);
+    /// Pads the remaining bytes and returns the final hash, all 8 words of it even for SHA-224 constants
+    pub fn finalize(mut self) -> Sha256Digest {
+        let bits = self.length.wrapping_mul(8);
+        let k = self.constants.constant_words();
+        for chunk in preprocessing::final_blocks(&self.buffer, 64, bits as u128).chunks_exact(64) {
+            sha256_block(&mut self.hash, &block_words(chunk), &k);
+        }
+        Sha256Digest::from(conversions::u32_words_to_be_bytes(&self.hash))
+    }
 }
 
-fn sigma_s1(x:u32) -> u32 {
-    let s17 = x.rotate_right(17);
-    let s19 = x.rotate_right(19);
-    let r10 = x >> 10;
-    return s17.bitxor(s19).bitxor(r10);
+impl<C:Constants<64,u32> + Default> Default for Sha256Hasher<C> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-// --------- addendum -------------
-fn inv(x:u32) -> u32 {
-    x.bitxor(u32::MAX)
-}
+/// Rejects state where the buffered bytes do not match the length, since such a hasher would silently compute a wrong hash
+#[cfg(feature = "serde")]
+impl<'de, C:Constants<64,u32> + serde::Deserialize<'de>> serde::Deserialize<'de> for Sha256Hasher<C> {
+    fn deserialize<D:serde::Deserializer<'de>>(deserializer:D) -> std::result::Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "Sha256Hasher")]
+        struct State<C> {
+            hash:[u32;8],
+            buffer:Vec<u8>,
+            length:u64,
+            constants:C,
+        }
 
-fn xor_sum(v:Vec<u32>) -> u32 {
-    let mut res = 0u32;
-    for u in v {
-        res = res.bitxor(u);
+        let state = State::<C>::deserialize(deserializer)?;
+        if state.buffer.len() as u64 != state.length % 64 {
+            let reason = format!("{} buffered bytes for a message of {} bytes", state.buffer.len(), state.length);
+            return Err(serde::de::Error::custom(Error::MalformedState(reason)));
+        }
+        Ok(Self { hash: state.hash, buffer: state.buffer, length: state.length, constants: state.constants })
     }
-    return res;
 }
 
-fn wrapping_sum(v:Vec<u32>) -> u32 {
-    let mut res = 0u32;
-    for u in v {
-        res = res.wrapping_add(u);
+/// The registers `a` to `h` after each of the 64 rounds, for every message block.
+/// This is meant for studying how a change in the message spreads through the state, the hash itself is not part of the trace.
+#[cfg(feature = "bigint")]
+pub fn sha256_round_trace<C:Constants<64,u32>>(msg:Vec<U512>, constants:&C) -> Vec<[[u32;8];64]> {
+    let mut hash = constants.initial_hash();
+    let k = constants.constant_words();
+    let mut trace = Vec::new();
+    for block in msg {
+        let mut rounds = [[0;8];64];
+        let mut j = 0;
+        let registers = sha256_rounds(&hash, &conversions::to_u32_array(block), &k, 64, |r| {
+            rounds[j] = r;
+            j += 1;
+        });
+        for i in 0..8 {
+            hash[i] = hash[i].wrapping_add(registers[i]);
+        }
+        trace.push(rounds);
     }
-    return res;
+    trace
 }
 
+/// The 16 big endian words of a 64 byte chunk
+pub(crate) fn block_words(chunk:&[u8]) -> [u32;16] {
+    let mut bytes = [0u8; 64];
+    bytes.copy_from_slice(chunk);
+    conversions::u32_words_from_be_bytes(&bytes)
+}
 
-// ------------------ Message Schedule ----------------
-
-fn sha256_message_schedule(m:U512) -> [u32;64] {
-    //word vector of length 16
-    let m = conversions::to_u32_words(m); 
-    let mut w = [0u32; 64];
-    for i in 0..16 {
-        w[i] = m[i];
+pub(crate) fn sha256_block(hash:&mut [u32;8], block:&[u32;16], k:&[u32;64]) {
+    let registers = sha256_compression(hash, block, k);
+    for i in 0..8 {
+        hash[i] = hash[i].wrapping_add(registers[i]);
     }
+}
 
-    for i in 16..64 {
-        let mut w_i = sigma_s1(w[i - 2]);
-        w_i = w_i.wrapping_add(w[i - 7]);
-        w_i = w_i.wrapping_add(sigma_s0(w[i - 15]));
-        w_i = w_i.wrapping_add(w[i - 16]);
-        w[i] = w_i;
-    }
+fn sha256_compression(intermediate_hash:&[u32;8], msg:&[u32;16], k:&[u32;64]) -> [u32;8] {
+    sha256_rounds(intermediate_hash, msg, k, 64, |_| ())
+}
 
-    return w;
+/// The compression function with the round constants `constants_k`, stopping after `rounds` rounds.
+/// `record` is called with the registers after every round.
+pub(crate) fn sha256_rounds(intermediate_hash:&[u32;8], msg:&[u32;16], constants_k:&[u32;64], rounds:usize, record:impl FnMut([u32;8])) -> [u32;8] {
+    family::compress::<Sha256Words>(intermediate_hash, msg, &constants_k[..rounds], record)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::family::{small_sigma0, small_sigma1};
     #[test]
     fn simple_sigma_s0() {
-        let y = sigma_s0(1);
+        let y = small_sigma0::<Sha256Words>(1);
         let expected = (1 << 25) + (1 << 14);
         assert_eq!(y, expected);
     }
 
     #[test]
     fn simple_sigma_s1() {
-        let y = sigma_s1(1 << 31);
+        let y = small_sigma1::<Sha256Words>(1 << 31);
         let expected = 0b00000000_00100000_01010000_00000000;
         assert_eq!(y,expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn byte_hashes() {
+        assert_eq!(sha224(b"abc").to_hex(), "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7");
+        assert_eq!(sha256(b"").to_hex(), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn resume_from_saved_state() {
+        let msg = [0xa5u8; 300];
+        let mut hasher = Sha256Hasher::<Sha256>::new();
+        hasher.update(&msg[..101]);
+
+        let json = serde_json::to_string(&hasher).unwrap();
+        let mut resumed:Sha256Hasher<Sha256> = serde_json::from_str(&json).unwrap();
+        resumed.update(&msg[101..]);
+        assert_eq!(resumed.finalize(), sha256(&msg));
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&hasher, &mut cbor).unwrap();
+        let mut resumed:Sha256Hasher<Sha256> = ciborium::from_reader(&cbor[..]).unwrap();
+        resumed.update(&msg[101..]);
+        assert_eq!(resumed.finalize(), sha256(&msg));
+
+        //custom constants are saved with the state
+        let mut constants = crate::constants::CustomConstants::from_constants(&Sha256);
+        constants.iv[0] ^= 1;
+        let mut custom = Sha256Hasher::with_constants(constants);
+        custom.update(b"abc");
+        let json = serde_json::to_string(&custom).unwrap();
+        let resumed:Sha256Hasher<crate::constants::CustomConstants<64, u32>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(resumed.finalize(), custom.finalize());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn corrupted_state_is_rejected() {
+        let mut hasher = Sha256Hasher::<Sha256>::new();
+        hasher.update(b"abc");
+        let json = serde_json::to_string(&hasher).unwrap();
+
+        let corrupted = json.replace("\"length\":3", "\"length\":4");
+        let err = serde_json::from_str::<Sha256Hasher<Sha256>>(&corrupted).map(|_| ()).unwrap_err();
+        assert!(err.to_string().contains("malformed state"));
+
+        let truncated = json.replacen("[", "[1,", 1);
+        assert!(serde_json::from_str::<Sha256Hasher<Sha256>>(&truncated).is_err());
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn hasher_matches_preprocessed() {
+        let msg = [0x5au8; 300];
+        for len in [0, 55, 56, 63, 64, 300] {
+            let mut hasher = Sha256Hasher::<Sha256>::new();
+            for chunk in msg[..len].chunks(7) {
+                hasher.update(chunk);
+            }
+            let expected = sha_256(crate::preprocessing::sha256_byte_preprocessing(&msg[..len]));
+            assert_eq!(U256::from(hasher.finalize()), expected);
+        }
+    }
+
+    #[test]
+    fn length_field_overflow() {
+        let mut hasher = Sha256Hasher::<Sha256>::new();
+        assert_eq!(hasher.try_update(b"abc"), Ok(()));
+        hasher.length = u64::MAX / 8 - 1;
+        assert_eq!(hasher.try_update(b"a"), Ok(()));
+        assert_eq!(hasher.try_update(b"a"), Err(Error::MessageTooLong { max_bits: u64::MAX as u128 }));
+        assert_eq!(hasher.length(), u64::MAX / 8);
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn round_trace_ends_in_hash() {
+        let trace = sha256_round_trace(crate::preprocessing::sha256_preprocessing("abc"), &Sha256);
+        let mut hash = Sha256.initial_hash();
+        for i in 0..8 {
+            hash[i] = hash[i].wrapping_add(trace[0][63][i]);
+        }
+        assert_eq!(trace.len(), 1);
+        assert_eq!(hash, conversions::to_u32_array(sha_256(crate::preprocessing::sha256_preprocessing("abc"))));
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn custom_constants() {
+        use crate::constants::CustomConstants;
+        let msg = crate::preprocessing::sha256_preprocessing("abc");
+        let mut constants = CustomConstants::from_constants(&Sha256);
+        assert_eq!(sha256_internal(msg.clone(), &constants), sha_256(msg.clone()));
+
+        constants.k[63] ^= 1;
+        let mut hasher = Sha256Hasher::with_constants(constants);
+        hasher.update(b"abc");
+        let hash = U256::from(hasher.finalize());
+        assert_ne!(hash, sha_256(msg.clone()));
+        assert_eq!(hash, sha256_internal(msg, &constants));
+    }
+}
@@ -1,163 +1,326 @@
 //! This is the SHA-512 Algorithm. 
-//! You can use the standard implementations of [sha_512] and [sha_384] or you can use custom constants and initial hash values with [sha512_internal]
-
-use std::ops::{BitXor, BitAnd};
+//! You can hash bytes with [sha512] and [sha384], or incrementally with a [Sha512Hasher] that also takes custom constants and initial hash values.
+//! With the `bigint` feature there are the implementations on preprocessed blocks, [sha_512], [sha_384] and [sha512_internal].
 
+#[cfg(feature = "bigint")]
 use crypto_bigint::{U384, U512,U1024};
-use crate::conversions::{self, to_u64_words};
+use crate::preprocessing;
+use crate::conversions;
+use crate::digest::{Sha384Digest, Sha512Digest};
+use crate::family::{self, Sha512Words};
+use crate::error::{Error, Result};
 use crate::constants::{Constants, Sha512, Sha384};
 
+/// The SHA-512 hash of a message of bytes
+/// # Examples
+/// ```
+/// use jisp_sha2::sha512::sha512;
+///
+/// assert_eq!(sha512(b"abc").as_bytes()[..4], [0xdd, 0xaf, 0x35, 0xa1]);
+/// ```
+pub fn sha512(msg:&[u8]) -> Sha512Digest {
+    let mut hasher = Sha512Hasher::<Sha512>::new();
+    hasher.update(msg);
+    hasher.finalize()
+}
+
+/// The SHA-384 hash of a message of bytes, the first 48 bytes of SHA-512 with the SHA-384 initial hash value
+pub fn sha384(msg:&[u8]) -> Sha384Digest {
+    let mut hasher = Sha512Hasher::<Sha384>::new();
+    hasher.update(msg);
+    let mut truncated = [0u8; 48];
+    truncated.copy_from_slice(&hasher.finalize().0[..48]);
+    Sha384Digest::from(truncated)
+}
+
 /// The SHA-512 Algorithm, only use this after having [preprocessed](crate::preprocessing::sha512_preprocessing) your data into message blocks
+#[cfg(feature = "bigint")]
 pub fn sha_512(m:Vec<U1024>) -> U512 {
-    sha512_internal::<Sha512>(m)
+    sha512_internal(m, &Sha512)
 }
 
 /// The SHA-384 Algorithm, only use this after having [preprocessed](crate::preprocessing::sha512_preprocessing) your data into message blocks
 /// Note that this is essentially the [SHA-512](sha_512) algorithm, except with different constants and a truncated result.
+#[cfg(feature = "bigint")]
 pub fn sha_384(m:Vec<U1024>) -> U384 {
-    let res = sha512_internal::<Sha384>(m);
-    let words = to_u64_words(res);
+    let res = sha512_internal(m, &Sha384);
+    let words = conversions::to_u64_words(res);
     let mut truncated_words = [0u64; 6];
-    for i in 0..6 {
-        truncated_words[i] = words[i];
-    } 
-    return conversions::from_u64_words(&mut truncated_words);
+    truncated_words.copy_from_slice(&words[..6]);
+    conversions::from_u64_array(&truncated_words)
 }
 
 /// The internal loop of the SHA-512 algorithm, 
 /// you can use different initial hash and constant values by implementing the [Constants](crate::constants::Constants) trait on a new object
-pub fn sha512_internal<C:Constants<80,u64>>(msg:Vec<U1024>) -> U512 {
-    let mut hash = C::initial_hash();
+/// or with [CustomConstants](crate::constants::CustomConstants) if they are only known at runtime
+#[cfg(feature = "bigint")]
+pub fn sha512_internal<C:Constants<80,u64>>(msg:Vec<U1024>, constants:&C) -> U512 {
+    let mut hash = constants.initial_hash();
+    let k = constants.constant_words();
     for block in msg {
-        let registers = sha512_compression::<C>(&hash, block);
-        for i in 0..8 {
-            hash[i] = hash[i].wrapping_add(registers[i]);
-        }
+        sha512_block(&mut hash, &conversions::to_u64_words(block), &k);
     }
-    conversions::from_u64_words(&mut hash)
+    conversions::from_u64_array(&hash)
 }
 
-fn sha512_compression<C:Constants<80,u64>>(intermediate_hash:&[u64;8], msg:U1024) -> [u64;8] {
-    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = intermediate_hash.clone();
-    let constants_k = C::constant_words();
-    let expanded_blocks = sha512_message_schedule(msg);
-
-    for j in 0..80 {
-        let T1 = wrap_sum(vec![
-            h, sigma_l1(e), ch(e,f,g), constants_k[j], expanded_blocks[j] 
-        ]);
-        let T2 = sigma_l0(a).wrapping_add(maj(a,b,c));
+/// An incremental version of [sha512] for messages that arrive in pieces, such as a large file being read from disk.
+/// The message is fed in with [update](Sha512Hasher::update) and no preprocessing is needed, since the padding is added by [finalize](Sha512Hasher::finalize).
+/// # Examples
+/// ```
+/// use jisp_sha2::sha512::{Sha512Hasher, sha512};
+/// use jisp_sha2::constants::Sha512;
+///
+/// let mut hasher = Sha512Hasher::<Sha512>::new();
+/// hasher.update(b"a");
+/// hasher.update(b"bc");
+///
+/// assert_eq!(hasher.finalize(), sha512(b"abc"));
+/// ```
+///
+/// With the `serde` feature the state of a hasher, the chaining value, the buffered bytes, the length and the constants,
+/// can be saved part way through a message and the hashing resumed later.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Sha512Hasher<C:Constants<80,u64>> {
+    hash:[u64;8],
+    buffer:Vec<u8>,
+    length:u128,
+    constants:C,
+}
 
-        h = g;
-        g = f;
-        f = e;
-        e = d.wrapping_add(T1);
-        d = c;
-        c = b;
-        b = a;
-        a = T1.wrapping_add(T2);
+impl<C:Constants<80,u64> + Default> Sha512Hasher<C> {
+    pub fn new() -> Self {
+        Self::with_constants(C::default())
     }
-
-    [a, b, c, d, e, f, g, h]
 }
 
-// ------ Six Logical Functions -------
-
-fn ch(x:u64, y:u64, z:u64) -> u64 {
-    let x_and_y = x.bitand(y);
-    let x_and_z = inv(x).bitand(z);
-    return x_and_y.bitxor(x_and_z);
-}
+impl<C:Constants<80,u64>> Sha512Hasher<C> {
+    /// A hasher for constants that are only known at runtime, such as [CustomConstants](crate::constants::CustomConstants)
+    pub fn with_constants(constants:C) -> Self {
+        Self { hash: constants.initial_hash(), buffer: Vec::with_capacity(128), length: 0, constants }
+    }
 
-fn maj(x:u64, y:u64, z:u64) -> u64 {
-    let x_and_y = x.bitand(y);
-    let y_and_z = y.bitand(z);
-    let x_and_z = x.bitand(z);
-    return xor_sum(vec![x_and_y, y_and_z, x_and_z]);
-}
+    /// Appends bytes to the message, every completed block of 128 bytes is compressed right away
+    pub fn update(&mut self, bytes:&[u8]) {
+        self.length = self.length.wrapping_add(bytes.len() as u128);
+        self.buffer.extend_from_slice(bytes);
+        let full = self.buffer.len() - self.buffer.len() % 128;
+        let k = self.constants.constant_words();
+        for chunk in self.buffer[..full].chunks_exact(128) {
+            sha512_block(&mut self.hash, &block_words(chunk), &k);
+        }
+        self.buffer.drain(..full);
+    }
 
-fn sigma_l0(x:u64) -> u64 {
-    return xor_sum(vec![s(x,28), s(x,34), s(x,39)]);
-}
+    /// Same as [update](Sha512Hasher::update) but returns [MessageTooLong](crate::Error::MessageTooLong)
+    /// instead of wrapping the length once the message no longer fits into the 128-bit length field
+    pub fn try_update(&mut self, bytes:&[u8]) -> Result<()> {
+        let max_bits = u128::MAX;
+        match self.length.checked_add(bytes.len() as u128) {
+            Some(length) if length <= max_bits / 8 => {
+                self.update(bytes);
+                Ok(())
+            },
+            _ => Err(Error::MessageTooLong { max_bits }),
+        }
+    }
 
-fn sigma_l1(x:u64) -> u64 {
-    return xor_sum(vec![s(x,14), s(x,18), s(x,41)]);
-}
+    /// The number of message bytes given to the hasher so far
+    pub fn length(&self) -> u128 {
+        self.length
+    }
 
-fn sigma_s0(x:u64) -> u64 {
-    return xor_sum(vec![s(x,1), s(x,8), r(x,7)]);
+    /// Pads the remaining bytes and returns the final hash, all 8 words of it even for SHA-384 constants
+    pub fn finalize(mut self) -> Sha512Digest {
+        let bits = self.length.wrapping_mul(8);
+        let k = self.constants.constant_words();
+        for chunk in preprocessing::final_blocks(&self.buffer, 128, bits).chunks_exact(128) {
+            sha512_block(&mut self.hash, &block_words(chunk), &k);
+        }
+        Sha512Digest::from(conversions::u64_words_to_be_bytes(&self.hash))
+    }
 }
 
-fn sigma_s1(x:u64) -> u64 {
-    return xor_sum(vec![s(x,19), s(x, 61), r(x,6)])
+impl<C:Constants<80,u64> + Default> Default for Sha512Hasher<C> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-// --------- addendum -------------
-fn inv(x:u64) -> u64 {
-    x.bitxor(u64::MAX)
-}
+/// Rejects state where the buffered bytes do not match the length, since such a hasher would silently compute a wrong hash
+#[cfg(feature = "serde")]
+impl<'de, C:Constants<80,u64> + serde::Deserialize<'de>> serde::Deserialize<'de> for Sha512Hasher<C> {
+    fn deserialize<D:serde::Deserializer<'de>>(deserializer:D) -> std::result::Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "Sha512Hasher")]
+        struct State<C> {
+            hash:[u64;8],
+            buffer:Vec<u8>,
+            length:u128,
+            constants:C,
+        }
 
-fn xor_sum(v:Vec<u64>) -> u64 {
-    let mut res = 0u64;
-    for u in v {
-        res = res.bitxor(u);
+        let state = State::<C>::deserialize(deserializer)?;
+        if state.buffer.len() as u128 != state.length % 128 {
+            let reason = format!("{} buffered bytes for a message of {} bytes", state.buffer.len(), state.length);
+            return Err(serde::de::Error::custom(Error::MalformedState(reason)));
+        }
+        Ok(Self { hash: state.hash, buffer: state.buffer, length: state.length, constants: state.constants })
     }
-    return res;
 }
 
-fn wrap_sum(v:Vec<u64>) -> u64 {
-    let mut res = 0u64;
-    for u in v {
-        res = res.wrapping_add(u);
+/// The registers `a` to `h` after each of the 80 rounds, for every message block.
+/// This is meant for studying how a change in the message spreads through the state, the hash itself is not part of the trace.
+#[cfg(feature = "bigint")]
+pub fn sha512_round_trace<C:Constants<80,u64>>(msg:Vec<U1024>, constants:&C) -> Vec<[[u64;8];80]> {
+    let mut hash = constants.initial_hash();
+    let k = constants.constant_words();
+    let mut trace = Vec::new();
+    for block in msg {
+        let mut rounds = [[0;8];80];
+        let mut j = 0;
+        let registers = sha512_rounds(&hash, &conversions::to_u64_words(block), &k, 80, |r| {
+            rounds[j] = r;
+            j += 1;
+        });
+        for i in 0..8 {
+            hash[i] = hash[i].wrapping_add(registers[i]);
+        }
+        trace.push(rounds);
     }
-    return res;
+    trace
 }
 
-//Renamed functions to fit with the SHA-2 design document
-fn s(uint:u64, rot:u8) -> u64 {
-    uint.rotate_right(rot as u32)
-} 
-fn r(uint:u64, shift:u8) -> u64 {
-    uint >> shift
+/// The 16 big endian words of a 128 byte chunk
+fn block_words(chunk:&[u8]) -> [u64;16] {
+    let mut bytes = [0u8; 128];
+    bytes.copy_from_slice(chunk);
+    conversions::u64_words_from_be_bytes(&bytes)
 }
 
-
-// ------------------ Message Schedule ----------------
-
-fn sha512_message_schedule(m:U1024) -> [u64;80] {
-    //word vector of length 16
-    let m = conversions::to_u64_words(m); 
-    let mut w = [0u64; 80];
-    for i in 0..16 {
-        w[i] = m[i];
+fn sha512_block(hash:&mut [u64;8], block:&[u64;16], k:&[u64;80]) {
+    let registers = sha512_compression(hash, block, k);
+    for i in 0..8 {
+        hash[i] = hash[i].wrapping_add(registers[i]);
     }
+}
 
-    for i in 16..80 {
-        w[i] = wrap_sum(vec![
-            sigma_s1(w[i - 2]),
-            w[i - 7],
-            sigma_s0(w[i - 15]),
-            w[i - 16]
-        ]);
-    }
+fn sha512_compression(intermediate_hash:&[u64;8], msg:&[u64;16], k:&[u64;80]) -> [u64;8] {
+    sha512_rounds(intermediate_hash, msg, k, 80, |_| ())
+}
 
-    return w;
+/// The compression function with the round constants `constants_k`, stopping after `rounds` rounds.
+/// `record` is called with the registers after every round.
+pub(crate) fn sha512_rounds(intermediate_hash:&[u64;8], msg:&[u64;16], constants_k:&[u64;80], rounds:usize, record:impl FnMut([u64;8])) -> [u64;8] {
+    family::compress::<Sha512Words>(intermediate_hash, msg, &constants_k[..rounds], record)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::family::{big_sigma0, small_sigma0};
     #[test]
     fn simple_sigma_l0() {
-        let y = sigma_l0(1);
+        let y = big_sigma0::<Sha512Words>(1);
         let expected = (1 << 36) + (1 << 30) + (1 << 25);
         assert_eq!(y, expected);
     }
 
     #[test]
     fn simple_sigma_s0() {
-        let y = sigma_s0(1 << 10);
+        let y = small_sigma0::<Sha512Words>(1 << 10);
         let expected = (1 << 9) + (1 << 2) + (1 << 3);
         assert_eq!(y,expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn byte_hashes() {
+        assert_eq!(&sha384(b"abc").to_hex()[..16], "cb00753f45a35e8b");
+        assert_eq!(&sha512(b"").to_hex()[..16], "cf83e1357eefb8bd");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn resume_from_saved_state() {
+        let msg = [0xa5u8; 300];
+        let mut hasher = Sha512Hasher::<Sha512>::new();
+        hasher.update(&msg[..165]);
+
+        let json = serde_json::to_string(&hasher).unwrap();
+        let mut resumed:Sha512Hasher<Sha512> = serde_json::from_str(&json).unwrap();
+        resumed.update(&msg[165..]);
+        assert_eq!(resumed.finalize(), sha512(&msg));
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&hasher, &mut cbor).unwrap();
+        let mut resumed:Sha512Hasher<Sha512> = ciborium::from_reader(&cbor[..]).unwrap();
+        resumed.update(&msg[165..]);
+        assert_eq!(resumed.finalize(), sha512(&msg));
+
+        //custom constants are saved with the state
+        let mut constants = crate::constants::CustomConstants::from_constants(&Sha512);
+        constants.iv[0] ^= 1;
+        let mut custom = Sha512Hasher::with_constants(constants);
+        custom.update(b"abc");
+        let json = serde_json::to_string(&custom).unwrap();
+        let resumed:Sha512Hasher<crate::constants::CustomConstants<80, u64>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(resumed.finalize(), custom.finalize());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn corrupted_state_is_rejected() {
+        let mut hasher = Sha512Hasher::<Sha512>::new();
+        hasher.update(b"abc");
+        let json = serde_json::to_string(&hasher).unwrap();
+
+        let corrupted = json.replace("\"length\":3", "\"length\":4");
+        let err = serde_json::from_str::<Sha512Hasher<Sha512>>(&corrupted).map(|_| ()).unwrap_err();
+        assert!(err.to_string().contains("malformed state"));
+
+        let truncated = json.replacen("[", "[1,", 1);
+        assert!(serde_json::from_str::<Sha512Hasher<Sha512>>(&truncated).is_err());
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn hasher_matches_preprocessed() {
+        let msg = [0x5au8; 300];
+        for len in [0, 119, 120, 127, 128, 300] {
+            let mut hasher = Sha512Hasher::<Sha512>::new();
+            for chunk in msg[..len].chunks(7) {
+                hasher.update(chunk);
+            }
+            let expected = sha_512(crate::preprocessing::sha512_byte_preprocessing(&msg[..len]));
+            assert_eq!(U512::from(hasher.finalize()), expected);
+        }
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn round_trace_ends_in_hash() {
+        let trace = sha512_round_trace(crate::preprocessing::sha512_preprocessing("abc"), &Sha512);
+        let mut hash = Sha512.initial_hash();
+        for i in 0..8 {
+            hash[i] = hash[i].wrapping_add(trace[0][79][i]);
+        }
+        assert_eq!(trace.len(), 1);
+        assert_eq!(hash.to_vec(), conversions::to_u64_words(sha_512(crate::preprocessing::sha512_preprocessing("abc"))).to_vec());
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn custom_constants() {
+        use crate::constants::CustomConstants;
+        let msg = crate::preprocessing::sha512_preprocessing("abc");
+        let mut constants = CustomConstants::from_constants(&Sha512);
+        assert_eq!(sha512_internal(msg.clone(), &constants), sha_512(msg.clone()));
+
+        constants.k[79] ^= 1;
+        let mut hasher = Sha512Hasher::with_constants(constants);
+        hasher.update(b"abc");
+        let hash = U512::from(hasher.finalize());
+        assert_ne!(hash, sha_512(msg.clone()));
+        assert_eq!(hash, sha512_internal(msg, &constants));
+    }
+}
@@ -0,0 +1,347 @@
+//! A sparse Merkle tree with a leaf for each of the 2^256 possible SHA-256 digests, most of them empty.
+//!
+//! The hashes of empty subtrees are the same everywhere, so they are [computed once](empty_hashes) for each level and never stored.
+//! A key is the path from the root: its first bit picks the left or right child of the root, its last bit the leaf.
+//! Leaves are hashed as `SHA-256(0x00 || key || value)` and nodes as `SHA-256(0x01 || left || right)`, an empty leaf is 32 zero bytes.
+//!
+//! The nodes are kept in a [Backend], [MemoryBackend] keeps them in a map. Only nodes that differ from the empty hash of their level are stored,
+//! so deleting every key leaves the backend empty again. A [Proof] lists only the siblings that are not empty, with a bitmap to place them.
+//! # Examples
+//! ```
+//! use jisp_sha2::sha256;
+//! use jisp_sha2::smt::{verify, SparseMerkleTree};
+//!
+//! let mut tree = SparseMerkleTree::new();
+//! let (alice, bob) = (sha256(b"alice"), sha256(b"bob"));
+//! tree.insert(alice, b"10 coins".to_vec());
+//!
+//! let proof = tree.proof(&alice);
+//! assert!(verify(&tree.root(), &alice, Some(b"10 coins"), &proof));
+//! //bob is not in the tree
+//! assert!(verify(&tree.root(), &bob, None, &tree.proof(&bob)));
+//! ```
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::constants::Sha256;
+use crate::digest::Sha256Digest;
+use crate::sha256::Sha256Hasher;
+
+/// The number of levels below the root, one for each bit of a key
+pub const DEPTH:usize = 256;
+
+const LEAF_PREFIX:u8 = 0x00;
+const NODE_PREFIX:u8 = 0x01;
+
+/// The hash of an empty subtree for every depth, from the root of the empty tree at 0 to an empty leaf at [DEPTH]
+pub fn empty_hashes() -> &'static [Sha256Digest; DEPTH + 1] {
+    static EMPTY:OnceLock<[Sha256Digest; DEPTH + 1]> = OnceLock::new();
+    EMPTY.get_or_init(|| {
+        let mut empty = [Sha256Digest::from([0; 32]); DEPTH + 1];
+        for depth in (0..DEPTH).rev() {
+            empty[depth] = node_hash(&empty[depth + 1], &empty[depth + 1]);
+        }
+        empty
+    })
+}
+
+pub fn leaf_hash(key:&Sha256Digest, value:&[u8]) -> Sha256Digest {
+    let mut hasher = Sha256Hasher::<Sha256>::new();
+    hasher.update(&[LEAF_PREFIX]);
+    hasher.update(key.as_bytes());
+    hasher.update(value);
+    hasher.finalize()
+}
+
+pub fn node_hash(left:&Sha256Digest, right:&Sha256Digest) -> Sha256Digest {
+    let mut hasher = Sha256Hasher::<Sha256>::new();
+    hasher.update(&[NODE_PREFIX]);
+    hasher.update(left.as_bytes());
+    hasher.update(right.as_bytes());
+    hasher.finalize()
+}
+
+/// Bit `i` of the key counted from the most significant bit of the first byte
+fn bit(key:&Sha256Digest, i:usize) -> bool {
+    key.0[i / 8] >> (7 - i % 8) & 1 == 1
+}
+
+/// The first `depth` bits of the key with the rest set to 0, which names the node at that depth on the path of the key
+fn prefix(key:&Sha256Digest, depth:usize) -> Sha256Digest {
+    let mut bytes = key.0;
+    for (i, b) in bytes.iter_mut().enumerate() {
+        let keep = depth.saturating_sub(8 * i).min(8);
+        *b &= !(0xffu16 >> keep) as u8;
+    }
+    Sha256Digest::from(bytes)
+}
+
+/// The node next to the node at `depth` on the path of the key
+fn sibling_prefix(key:&Sha256Digest, depth:usize) -> Sha256Digest {
+    let mut p = prefix(key, depth);
+    p.0[(depth - 1) / 8] ^= 0x80 >> ((depth - 1) % 8);
+    p
+}
+
+/// Where the nodes and values of a tree are kept. A node is named by its depth and the key bits above it, with the rest of the 256 bits set to 0.
+pub trait Backend {
+    fn node(&self, depth:usize, prefix:&Sha256Digest) -> Option<Sha256Digest>;
+    /// Stores a node, or removes it for `None`
+    fn set_node(&mut self, depth:usize, prefix:Sha256Digest, hash:Option<Sha256Digest>);
+    fn value(&self, key:&Sha256Digest) -> Option<Vec<u8>>;
+    /// Stores a value, or removes it for `None`
+    fn set_value(&mut self, key:Sha256Digest, value:Option<Vec<u8>>);
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MemoryBackend {
+    nodes:HashMap<(usize, Sha256Digest), Sha256Digest>,
+    values:HashMap<Sha256Digest, Vec<u8>>
+}
+
+impl MemoryBackend {
+    /// The number of stored nodes that are not empty
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn node(&self, depth:usize, prefix:&Sha256Digest) -> Option<Sha256Digest> {
+        self.nodes.get(&(depth, *prefix)).copied()
+    }
+
+    fn set_node(&mut self, depth:usize, prefix:Sha256Digest, hash:Option<Sha256Digest>) {
+        match hash {
+            Some(hash) => self.nodes.insert((depth, prefix), hash),
+            None => self.nodes.remove(&(depth, prefix)),
+        };
+    }
+
+    fn value(&self, key:&Sha256Digest) -> Option<Vec<u8>> {
+        self.values.get(key).cloned()
+    }
+
+    fn set_value(&mut self, key:Sha256Digest, value:Option<Vec<u8>>) {
+        match value {
+            Some(value) => self.values.insert(key, value),
+            None => self.values.remove(&key),
+        };
+    }
+}
+
+/// The siblings along the path of a key that are not empty, from the top of the tree down.
+/// Bit `d` of the bitmap, counted like the bits of a key, is set if the sibling at depth `d + 1` is in the list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Proof {
+    pub bitmap:[u8;32],
+    pub siblings:Vec<Sha256Digest>
+}
+
+impl Proof {
+    /// The root this proof leads to from the key with the value, or from an empty leaf for `None`.
+    /// Returns `None` if the number of siblings does not match the bitmap.
+    pub fn root(&self, key:&Sha256Digest, value:Option<&[u8]>) -> Option<Sha256Digest> {
+        let bitmap = Sha256Digest::from(self.bitmap);
+        let empty = empty_hashes();
+        let mut node = value.map_or(empty[DEPTH], |v| leaf_hash(key, v));
+        let mut siblings = self.siblings.iter().rev();
+        for depth in (1..=DEPTH).rev() {
+            let sibling = if bit(&bitmap, depth - 1) { *siblings.next()? } else { empty[depth] };
+            node = if bit(key, depth - 1) { node_hash(&sibling, &node) } else { node_hash(&node, &sibling) };
+        }
+        siblings.next().is_none().then_some(node)
+    }
+}
+
+/// Checks a membership proof for `Some(value)` or a non-membership proof for `None`
+pub fn verify(root:&Sha256Digest, key:&Sha256Digest, value:Option<&[u8]>, proof:&Proof) -> bool {
+    proof.root(key, value) == Some(*root)
+}
+
+pub struct SparseMerkleTree<B:Backend = MemoryBackend> {
+    backend:B
+}
+
+impl SparseMerkleTree<MemoryBackend> {
+    /// An empty tree in memory
+    pub fn new() -> Self {
+        Self::with_backend(MemoryBackend::default())
+    }
+}
+
+impl Default for SparseMerkleTree<MemoryBackend> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B:Backend> SparseMerkleTree<B> {
+    /// A tree over the nodes already in the backend
+    pub fn with_backend(backend:B) -> Self {
+        Self { backend }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn into_backend(self) -> B {
+        self.backend
+    }
+
+    pub fn root(&self) -> Sha256Digest {
+        self.node(0, &Sha256Digest::from([0; 32]))
+    }
+
+    pub fn get(&self, key:&Sha256Digest) -> Option<Vec<u8>> {
+        self.backend.value(key)
+    }
+
+    /// Sets the value of a key and returns the value it had before
+    pub fn insert(&mut self, key:Sha256Digest, value:Vec<u8>) -> Option<Vec<u8>> {
+        self.update(key, Some(value))
+    }
+
+    /// Empties the leaf of a key and returns the value it had
+    pub fn remove(&mut self, key:&Sha256Digest) -> Option<Vec<u8>> {
+        self.update(*key, None)
+    }
+
+    /// Sets or empties the leaf of a key and rehashes the path up to the root
+    pub fn update(&mut self, key:Sha256Digest, value:Option<Vec<u8>>) -> Option<Vec<u8>> {
+        let old = self.backend.value(&key);
+        let mut node = value.as_ref().map_or(empty_hashes()[DEPTH], |v| leaf_hash(&key, v));
+        self.backend.set_value(key, value);
+        self.store(DEPTH, key, node);
+        for depth in (1..=DEPTH).rev() {
+            let sibling = self.node(depth, &sibling_prefix(&key, depth));
+            node = if bit(&key, depth - 1) { node_hash(&sibling, &node) } else { node_hash(&node, &sibling) };
+            self.store(depth - 1, prefix(&key, depth - 1), node);
+        }
+        old
+    }
+
+    /// A proof for the current value of the key, or that it has none
+    pub fn proof(&self, key:&Sha256Digest) -> Proof {
+        let empty = empty_hashes();
+        let mut bitmap = [0u8; 32];
+        let mut siblings = Vec::new();
+        for depth in 1..=DEPTH {
+            let sibling = self.node(depth, &sibling_prefix(key, depth));
+            if sibling != empty[depth] {
+                bitmap[(depth - 1) / 8] |= 0x80 >> ((depth - 1) % 8);
+                siblings.push(sibling);
+            }
+        }
+        Proof { bitmap, siblings }
+    }
+
+    fn node(&self, depth:usize, prefix:&Sha256Digest) -> Sha256Digest {
+        self.backend.node(depth, prefix).unwrap_or(empty_hashes()[depth])
+    }
+
+    fn store(&mut self, depth:usize, prefix:Sha256Digest, hash:Sha256Digest) {
+        let hash = Some(hash).filter(|h| *h != empty_hashes()[depth]);
+        self.backend.set_node(depth, prefix, hash);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256;
+
+    fn key(i:u32) -> Sha256Digest {
+        sha256(&i.to_be_bytes())
+    }
+
+    #[test]
+    fn empty_tree() {
+        let tree = SparseMerkleTree::new();
+        let empty = empty_hashes();
+        assert_eq!(tree.root(), empty[0]);
+        assert_eq!(empty[DEPTH - 1], node_hash(&Sha256Digest::from([0; 32]), &Sha256Digest::from([0; 32])));
+        assert_eq!(empty[0].to_hex(), "6155289130893872355eac98042d22aefa2c2e708bea169402760e3b55f9a2dc");
+
+        let proof = tree.proof(&key(0));
+        assert!(proof.siblings.is_empty());
+        assert!(verify(&tree.root(), &key(0), None, &proof));
+    }
+
+    #[test]
+    fn single_leaf_root() {
+        let mut tree = SparseMerkleTree::new();
+        tree.insert(Sha256Digest::from([0xff; 32]), b"value".to_vec());
+        assert_eq!(tree.root().to_hex(), "68398f4980ace3a42baa060b8f9505d9b5122768b0a3dba4e7e4c7b355fe1793");
+    }
+
+    #[test]
+    fn insert_update_delete() {
+        let mut tree = SparseMerkleTree::new();
+        let mut reversed = SparseMerkleTree::new();
+        for i in 0..20 {
+            assert_eq!(tree.insert(key(i), i.to_le_bytes().to_vec()), None);
+        }
+        for i in (0..20).rev() {
+            reversed.insert(key(i), i.to_le_bytes().to_vec());
+        }
+        //the root depends on the contents and not the order of insertion
+        assert_eq!(tree.root(), reversed.root());
+
+        let root = tree.root();
+        assert_eq!(tree.insert(key(3), b"new".to_vec()), Some(3u32.to_le_bytes().to_vec()));
+        assert_ne!(tree.root(), root);
+        assert_eq!(tree.get(&key(3)), Some(b"new".to_vec()));
+        tree.insert(key(3), 3u32.to_le_bytes().to_vec());
+        assert_eq!(tree.root(), root);
+
+        for i in 0..20 {
+            assert!(tree.remove(&key(i)).is_some());
+        }
+        assert_eq!(tree.root(), empty_hashes()[0]);
+        assert_eq!(tree.backend().node_count(), 0);
+        assert_eq!(tree.remove(&key(0)), None);
+    }
+
+    #[test]
+    fn membership_proofs() {
+        let mut tree = SparseMerkleTree::new();
+        for i in 0..20 {
+            tree.insert(key(i), i.to_le_bytes().to_vec());
+        }
+        let root = tree.root();
+        for i in 0..20 {
+            let proof = tree.proof(&key(i));
+            //only the top levels are shared by other keys
+            assert!(proof.siblings.len() < 16);
+            assert!(verify(&root, &key(i), Some(&i.to_le_bytes()), &proof));
+            assert!(!verify(&root, &key(i), Some(b"other"), &proof));
+            assert!(!verify(&root, &key(i), None, &proof));
+            assert!(!verify(&root, &key(i + 1), Some(&i.to_le_bytes()), &proof));
+        }
+
+        let absent = key(100);
+        let proof = tree.proof(&absent);
+        assert!(verify(&root, &absent, None, &proof));
+        assert!(!verify(&root, &absent, Some(b""), &proof));
+
+        let mut short = proof.clone();
+        short.siblings.pop();
+        assert_eq!(short.root(&absent, None), None);
+        let mut flipped = proof.clone();
+        flipped.bitmap[31] ^= 1;
+        assert!(!verify(&root, &absent, None, &flipped));
+    }
+
+    #[test]
+    fn prefixes() {
+        let key = Sha256Digest::from([0xff; 32]);
+        assert_eq!(prefix(&key, 0), Sha256Digest::from([0; 32]));
+        assert_eq!(prefix(&key, 256), key);
+        assert_eq!(prefix(&key, 9).0[..2], [0xff, 0x80]);
+        assert_eq!(sibling_prefix(&key, 9).0[..2], [0xff, 0x00]);
+        assert_eq!(sibling_prefix(&key, 1).0[0], 0x00);
+    }
+}
@@ -0,0 +1,83 @@
+//! Tagged hashes from BIP-340, `SHA-256(SHA-256(tag) || SHA-256(tag) || msg)`.
+//! The tag makes hashes for different purposes independent of each other, even for the same message.
+//!
+//! The 64 byte prefix is exactly one SHA-256 block, so a [TaggedHash] compresses it once and starts every message from the midstate after it.
+//! # Examples
+//! ```
+//! use jisp_sha2::tagged::TaggedHash;
+//! use jisp_sha2::sha256::sha256;
+//!
+//! let challenge = TaggedHash::new("BIP0340/challenge");
+//!
+//! let tag = sha256(b"BIP0340/challenge");
+//! let prefixed = [tag.as_bytes().as_slice(), tag.as_bytes(), b"abc"].concat();
+//! assert_eq!(challenge.hash(b"abc"), sha256(&prefixed));
+//! ```
+use crate::constants::Sha256;
+use crate::digest::Sha256Digest;
+use crate::sha256::{sha256, Sha256Hasher};
+
+/// A tag with its precomputed midstate, cheap to clone and to keep around for every message hashed with the tag
+#[derive(Clone)]
+pub struct TaggedHash {
+    prefix:Sha256Hasher<Sha256>
+}
+
+impl TaggedHash {
+    pub fn new(tag:impl AsRef<[u8]>) -> Self {
+        let tag_hash = sha256(tag.as_ref());
+        let mut prefix = Sha256Hasher::new();
+        prefix.update(tag_hash.as_bytes());
+        prefix.update(tag_hash.as_bytes());
+        Self { prefix }
+    }
+
+    /// The chaining value after the tag prefix
+    pub fn midstate(&self) -> [u32;8] {
+        self.prefix.midstate()
+    }
+
+    /// A hasher that already contains the tag prefix, for messages that arrive in pieces
+    pub fn hasher(&self) -> Sha256Hasher<Sha256> {
+        self.prefix.clone()
+    }
+
+    /// The tagged hash of a complete message
+    pub fn hash(&self, msg:&[u8]) -> Sha256Digest {
+        let mut hasher = self.hasher();
+        hasher.update(msg);
+        hasher.finalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bip340_midstates() {
+        //the midstates that libsecp256k1 hardcodes for the three BIP-340 tags
+        assert_eq!(TaggedHash::new("BIP0340/challenge").midstate(), [
+            0x9cecba11, 0x23925381, 0x11679112, 0xd1627e0f, 0x97c87550, 0x003cc765, 0x90f61164, 0x33e9b66a
+        ]);
+        assert_eq!(TaggedHash::new("BIP0340/aux").midstate(), [
+            0x24dd3219, 0x4eba7e70, 0xca0fabb9, 0x0fa3166d, 0x3afbe4b1, 0x4c44df97, 0x4aac2739, 0x249e850a
+        ]);
+        assert_eq!(TaggedHash::new("BIP0340/nonce").midstate(), [
+            0x46615b35, 0xf4bfbff7, 0x9f8dc671, 0x83627ab3, 0x60217180, 0x57358661, 0x21a29e54, 0x68b07b4c
+        ]);
+    }
+
+    #[test]
+    fn midstate_matches_full_hash() {
+        let challenge = TaggedHash::new(b"BIP0340/challenge");
+        assert_eq!(challenge.hash(b"abc").to_hex(), "770a5b7e7c304bbcc3ea107343ff951dd404312ef418db0c3b94e2ebfbb50087");
+
+        let mut resumed = Sha256Hasher::from_midstate(Sha256, challenge.midstate(), 64).unwrap();
+        resumed.update(b"abc");
+        assert_eq!(resumed.finalize(), challenge.hash(b"abc"));
+        assert!(Sha256Hasher::from_midstate(Sha256, challenge.midstate(), 63).is_err());
+
+        assert_ne!(TaggedHash::new("TapLeaf").hash(b"abc"), challenge.hash(b"abc"));
+    }
+}
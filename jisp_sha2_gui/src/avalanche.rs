@@ -0,0 +1,192 @@
+//! A panel that hashes two inputs to show the avalanche effect, a small change in the message changes about half of the bits in the hash
+use eframe::egui::{self, Color32, Rect, Sense, Stroke, Ui, pos2, vec2};
+use jisp_sha2 as sha;
+use sha::constants;
+use sha::conversions::to_u64_words;
+use sha::preprocessing::{sha256_byte_preprocessing, sha512_byte_preprocessing};
+use sha::printer::{print_blocks, print_u32_word_string};
+use sha::sha256::sha256_round_trace;
+use sha::sha512::sha512_round_trace;
+
+use crate::{Algorithm, algorithm_combo};
+
+const CELL:f32 = 10.;
+const COLUMNS:usize = 32;
+
+pub struct AvalanchePanel {
+    input_a:String,
+    input_b:String,
+    show_rounds:bool,
+    computed_for:Option<(Algorithm, String, String)>,
+    result:Option<Comparison>
+}
+
+struct Comparison {
+    text_a:String,
+    text_b:String,
+    digest_a:Vec<u8>,
+    digest_b:Vec<u8>,
+    distance:u32,
+    /// differing register bits after every round, over all blocks the two messages have in common
+    rounds:Vec<u32>,
+    register_bits:u32
+}
+
+impl AvalanchePanel {
+    pub fn new() -> Self {
+        Self {
+            input_a: "abc".to_owned(),
+            input_b: "abd".to_owned(),
+            show_rounds: false,
+            computed_for: None,
+            result: None,
+        }
+    }
+
+    pub fn show(&mut self, ui:&mut Ui, alg:&mut Algorithm) {
+        ui.horizontal(|ui| {
+            algorithm_combo(ui, "avalanche_algorithm", alg);
+            ui.checkbox(&mut self.show_rounds, "Show round divergence");
+        });
+
+        egui::Grid::new("avalanche_inputs").show(ui, |ui| {
+            ui.label("[A]:");
+            ui.add_sized((520., 20.), egui::TextEdit::singleline(&mut self.input_a).hint_text("First Input..."));
+            ui.end_row();
+            ui.label("[B]:");
+            ui.add_sized((520., 20.), egui::TextEdit::singleline(&mut self.input_b).hint_text("Second Input..."));
+            ui.end_row();
+        });
+
+        //hashing two short texts is quick enough to redo whenever something changes
+        let key = (*alg, self.input_a.clone(), self.input_b.clone());
+        if self.computed_for.as_ref() != Some(&key) {
+            self.result = Some(compare(*alg, self.input_a.as_bytes(), self.input_b.as_bytes()));
+            self.computed_for = Some(key);
+        }
+        let Some(result) = &self.result else { return };
+
+        ui.separator();
+        let total = 8 * result.digest_a.len() as u32;
+        ui.label(format!(
+            "Hamming distance: {} of {} bits ({:.1}%)",
+            result.distance, total, 100. * result.distance as f32 / total as f32
+        ));
+
+        egui::Grid::new("avalanche_digests").show(ui, |ui| {
+            ui.label("[A]:");
+            ui.vertical(|ui| {
+                ui.label(egui::RichText::new(&result.text_a).monospace());
+                bit_grid(ui, &result.digest_a, &result.digest_b);
+            });
+            ui.end_row();
+            ui.label("[B]:");
+            ui.vertical(|ui| {
+                ui.label(egui::RichText::new(&result.text_b).monospace());
+                bit_grid(ui, &result.digest_b, &result.digest_a);
+            });
+            ui.end_row();
+        });
+
+        if self.show_rounds {
+            ui.separator();
+            ui.label(format!("Differing register bits after each round (out of {}):", result.register_bits));
+            round_plot(ui, &result.rounds, result.register_bits);
+        }
+    }
+}
+
+fn compare(alg:Algorithm, a:&[u8], b:&[u8]) -> Comparison {
+    let (text_a, digest_a) = digest(alg, a);
+    let (text_b, digest_b) = digest(alg, b);
+    let distance = digest_a.iter().zip(&digest_b).map(|(x, y)| (x ^ y).count_ones()).sum();
+    let (rounds, register_bits) = match alg {
+        Algorithm::Sha256 => (divergence(
+            &sha256_round_trace(sha256_byte_preprocessing(a), &constants::Sha256),
+            &sha256_round_trace(sha256_byte_preprocessing(b), &constants::Sha256),
+            |x, y| (x ^ y).count_ones()), 256),
+        Algorithm::Sha224 => (divergence(
+            &sha256_round_trace(sha256_byte_preprocessing(a), &constants::Sha224),
+            &sha256_round_trace(sha256_byte_preprocessing(b), &constants::Sha224),
+            |x, y| (x ^ y).count_ones()), 256),
+        Algorithm::Sha512 => (divergence(
+            &sha512_round_trace(sha512_byte_preprocessing(a), &constants::Sha512),
+            &sha512_round_trace(sha512_byte_preprocessing(b), &constants::Sha512),
+            |x, y| (x ^ y).count_ones()), 512),
+        Algorithm::Sha384 => (divergence(
+            &sha512_round_trace(sha512_byte_preprocessing(a), &constants::Sha384),
+            &sha512_round_trace(sha512_byte_preprocessing(b), &constants::Sha384),
+            |x, y| (x ^ y).count_ones()), 512),
+    };
+    Comparison { text_a, text_b, digest_a, digest_b, distance, rounds, register_bits }
+}
+
+/// The printed hash and its bytes
+fn digest(alg:Algorithm, bytes:&[u8]) -> (String, Vec<u8>) {
+    match alg {
+        Algorithm::Sha256 => {
+            let hash = sha::sha_256(sha256_byte_preprocessing(bytes));
+            (print_blocks(&vec![hash], true), to_u64_words(hash).iter().flat_map(|w| w.to_be_bytes()).collect())
+        },
+        Algorithm::Sha224 => {
+            let hash = sha::sha_224(sha256_byte_preprocessing(bytes));
+            (print_u32_word_string(&hash.to_vec()), hash.iter().flat_map(|w| w.to_be_bytes()).collect())
+        },
+        Algorithm::Sha512 => {
+            let hash = sha::sha_512(sha512_byte_preprocessing(bytes));
+            (print_blocks(&vec![hash], true), to_u64_words(hash).iter().flat_map(|w| w.to_be_bytes()).collect())
+        },
+        Algorithm::Sha384 => {
+            let hash = sha::sha_384(sha512_byte_preprocessing(bytes));
+            (print_blocks(&vec![hash], true), to_u64_words(hash).iter().flat_map(|w| w.to_be_bytes()).collect())
+        },
+    }
+}
+
+fn divergence<T:Copy, const ROUNDS:usize>(a:&[[[T;8];ROUNDS]], b:&[[[T;8];ROUNDS]], diff:impl Fn(T, T) -> u32) -> Vec<u32> {
+    a.iter().zip(b)
+        .flat_map(|(x, y)| x.iter().zip(y))
+        .map(|(rx, ry)| rx.iter().zip(ry).map(|(u, v)| diff(*u, *v)).sum())
+        .collect()
+}
+
+/// Draws every bit of the digest as a square, bits that differ from `other` are highlighted
+fn bit_grid(ui:&mut Ui, digest:&[u8], other:&[u8]) {
+    let bits = 8 * digest.len();
+    let rows = bits.div_ceil(COLUMNS);
+    let (rect, _) = ui.allocate_exact_size(vec2(COLUMNS as f32 * CELL, rows as f32 * CELL), Sense::hover());
+    let painter = ui.painter_at(rect);
+    for i in 0..bits {
+        let bit = (digest[i / 8] >> (7 - i % 8)) & 1;
+        let other_bit = (other[i / 8] >> (7 - i % 8)) & 1;
+        let color = if bit != other_bit {
+            Color32::from_rgb(230, 80, 60)
+        } else if bit == 1 {
+            Color32::LIGHT_GRAY
+        } else {
+            Color32::from_gray(60)
+        };
+        let min = rect.min + vec2((i % COLUMNS) as f32 * CELL, (i / COLUMNS) as f32 * CELL);
+        painter.rect_filled(Rect::from_min_size(min, vec2(CELL, CELL)).shrink(1.), 0., color);
+    }
+}
+
+/// A bar for every round, the line marks half of the register bits which is what a random state would give
+fn round_plot(ui:&mut Ui, rounds:&[u32], register_bits:u32) {
+    let size = vec2(560., 100.);
+    let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_stroke(rect, 0., Stroke::new(1., Color32::GRAY));
+    if rounds.is_empty() {
+        return;
+    }
+
+    let width = size.x / rounds.len() as f32;
+    for (j, d) in rounds.iter().enumerate() {
+        let height = size.y * *d as f32 / register_bits as f32;
+        let min = pos2(rect.min.x + j as f32 * width, rect.max.y - height);
+        painter.rect_filled(Rect::from_min_size(min, vec2(width.max(1.), height)), 0., Color32::from_rgb(90, 140, 230));
+    }
+    let half = rect.max.y - size.y / 2.;
+    painter.hline(rect.x_range(), half, Stroke::new(1., Color32::YELLOW));
+}
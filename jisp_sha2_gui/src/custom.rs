@@ -0,0 +1,299 @@
+//! An editor for the initial hash value and round constants, to see what SHA-2 does with parameters of your own
+use eframe::egui::{self, Color32, Ui};
+use serde::{Deserialize, Serialize};
+
+use jisp_sha2 as sha;
+use sha::constants::{self, Constants, CustomConstants};
+use sha::conversions::{to_u32_array, to_u64_words};
+use sha::preprocessing::{sha256_byte_preprocessing, sha512_byte_preprocessing};
+use sha::sha256::sha256_internal;
+use sha::sha512::sha512_internal;
+
+use crate::{Algorithm, algorithm_combo};
+use crate::input::to_hex;
+use crate::manifest::algorithm_named;
+
+/// The exchange format for constants, the words are hex strings
+#[derive(Serialize, Deserialize)]
+struct ConstantsFile {
+    algorithm:String,
+    iv:Vec<String>,
+    k:Vec<String>
+}
+
+pub struct CustomPanel {
+    /// the standard algorithm the words started from, the hash is compared with it.
+    /// The panel keeps its own so that choosing an algorithm on another tab does not throw away edited words
+    base:Algorithm,
+    iv:Vec<String>,
+    k:Vec<String>,
+    message:String,
+    /// text box for importing and exporting
+    exchange:String,
+    status:Result<String, String>,
+    /// the custom and the standard digest, `None` when a word is invalid
+    result:Option<(Vec<u8>, Vec<u8>)>,
+    dirty:bool
+}
+
+impl CustomPanel {
+    pub fn new() -> Self {
+        let mut panel = Self {
+            base: Algorithm::Sha256,
+            iv: Vec::new(),
+            k: Vec::new(),
+            message: "abc".to_owned(),
+            exchange: "".to_owned(),
+            status: Ok("".to_owned()),
+            result: None,
+            dirty: true
+        };
+        panel.reset();
+        panel
+    }
+
+    /// Sets every word back to the value of the standard algorithm
+    fn reset(&mut self) {
+        let (iv, k) = standard_words(self.base);
+        let width = word_bits(self.base) / 4;
+        self.iv = iv.iter().map(|w| format!("{:0width$x}", w)).collect();
+        self.k = k.iter().map(|w| format!("{:0width$x}", w)).collect();
+        self.dirty = true;
+    }
+
+    /// Switching to another algorithm starts again from its standard words
+    pub fn show(&mut self, ui:&mut Ui) {
+        ui.horizontal(|ui| {
+            let mut alg = self.base;
+            algorithm_combo(ui, "custom_algorithm", &mut alg);
+            if alg != self.base {
+                self.base = alg;
+                self.reset();
+            }
+            if ui.button("Reset to standard").clicked() {
+                self.reset();
+            }
+            let (iv, k) = standard_words(self.base);
+            let changed = self.iv.iter().zip(&iv).chain(self.k.iter().zip(&k))
+                .filter(|(text, w)| parse_word(text, word_bits(self.base)) != Some(**w))
+                .count();
+            ui.label(format!("{} of {} words changed", changed, iv.len() + k.len()));
+        });
+
+        egui::Grid::new("custom_message").show(ui, |ui| {
+            ui.label("[IN]:");
+            if ui.add_sized((520., 20.), egui::TextEdit::singleline(&mut self.message).hint_text("Input Text...")).changed() {
+                self.dirty = true;
+            }
+            ui.end_row();
+            self.result_rows(ui);
+        });
+
+        ui.separator();
+        egui::ScrollArea::vertical().max_height(300.).show(ui, |ui| {
+            ui.label("Initial hash value");
+            self.dirty |= word_grid(ui, "custom_iv", &mut self.iv, &standard_words(self.base).0, word_bits(self.base));
+            ui.label("Round constants");
+            self.dirty |= word_grid(ui, "custom_k", &mut self.k, &standard_words(self.base).1, word_bits(self.base));
+        });
+
+        ui.separator();
+        self.exchange(ui);
+
+        if self.dirty {
+            self.result = self.compute();
+            self.dirty = false;
+        }
+    }
+
+    fn result_rows(&self, ui:&mut Ui) {
+        let Some((custom, standard)) = &self.result else {
+            ui.label("[OUT]:");
+            ui.colored_label(Color32::RED, "[Error] Every word has to be valid hex");
+            ui.end_row();
+            return;
+        };
+
+        ui.label("[OUT]:");
+        ui.add(egui::Label::new(egui::RichText::new(to_hex(custom)).monospace()).selectable(true).wrap(true));
+        ui.end_row();
+        ui.label("[STD]:");
+        ui.add(egui::Label::new(egui::RichText::new(to_hex(standard)).monospace().weak()).selectable(true).wrap(true));
+        ui.end_row();
+        ui.label("");
+        if custom == standard {
+            ui.colored_label(Color32::GREEN, format!("Same as standard {}", self.base));
+        } else {
+            ui.colored_label(Color32::YELLOW, format!("⚠ Differs from standard {}", self.base));
+        }
+        ui.end_row();
+    }
+
+    fn exchange(&mut self, ui:&mut Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("Export JSON").clicked() {
+                let file = ConstantsFile { algorithm: self.base.to_string(), iv: self.iv.clone(), k: self.k.clone() };
+                self.exchange = serde_json::to_string_pretty(&file).unwrap_or_default();
+                ui.output_mut(|o| o.copied_text = self.exchange.clone());
+                self.status = Ok("Exported and copied to the clipboard".to_owned());
+            }
+            if ui.button("Export hex").clicked() {
+                self.exchange = format!("{}\n\n{}", hex_lines(&self.iv), hex_lines(&self.k));
+                ui.output_mut(|o| o.copied_text = self.exchange.clone());
+                self.status = Ok("Exported and copied to the clipboard".to_owned());
+            }
+            if ui.button("Import").clicked() {
+                self.status = self.import();
+            }
+            match &self.status {
+                Ok(text) => { ui.label(text); },
+                Err(e) => { ui.colored_label(Color32::RED, format!("[Error] {}", e)); },
+            }
+        });
+        ui.add_sized((640., 80.), egui::TextEdit::multiline(&mut self.exchange)
+            .hint_text("JSON or a list of hex words, the initial hash value followed by the round constants...")
+            .code_editor());
+    }
+
+    /// Reads JSON or a plain list of hex words from the exchange box.
+    /// The number of words in a list tells whether they are for SHA-256 or SHA-512.
+    fn import(&mut self) -> Result<String, String> {
+        let text = self.exchange.trim();
+        let (target, iv, k) = if text.starts_with('{') {
+            let file:ConstantsFile = serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {}", e))?;
+            let target = algorithm_named(&file.algorithm).ok_or(format!("Unknown algorithm {}", file.algorithm))?;
+            (target, file.iv, file.k)
+        } else {
+            let words:Vec<String> = text.split(|c:char| c.is_whitespace() || c == ',')
+                .filter(|w| !w.is_empty())
+                .map(|w| w.to_owned())
+                .collect();
+            let target = match (words.len(), self.base) {
+                (72, Algorithm::Sha256 | Algorithm::Sha224) | (88, Algorithm::Sha512 | Algorithm::Sha384) => self.base,
+                (72, _) => Algorithm::Sha256,
+                (88, _) => Algorithm::Sha512,
+                (n, _) => return Err(format!("Expected 72 words for SHA-256 or 88 for SHA-512, found {}", n)),
+            };
+            let k = words[8..].to_vec();
+            (target, words[..8].to_vec(), k)
+        };
+
+        let (standard_iv, standard_k) = standard_words(target);
+        if iv.len() != standard_iv.len() || k.len() != standard_k.len() {
+            return Err(format!("{} needs {} initial hash words and {} round constants", target, standard_iv.len(), standard_k.len()));
+        }
+        let bits = word_bits(target);
+        if let Some(bad) = iv.iter().chain(&k).find(|w| parse_word(w, bits).is_none()) {
+            return Err(format!("{} is not a {} bit hex word", bad, bits));
+        }
+
+        self.base = target;
+        self.iv = iv;
+        self.k = k;
+        self.dirty = true;
+        Ok(format!("Imported constants for {}", target))
+    }
+
+    /// The custom and the standard digest of the message
+    fn compute(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let bits = word_bits(self.base);
+        let iv = self.iv.iter().map(|w| parse_word(w, bits)).collect::<Option<Vec<u64>>>()?;
+        let k = self.k.iter().map(|w| parse_word(w, bits)).collect::<Option<Vec<u64>>>()?;
+        let (standard_iv, standard_k) = standard_words(self.base);
+        let msg = self.message.as_bytes();
+        Some((digest(self.base, &iv, &k, msg), digest(self.base, &standard_iv, &standard_k, msg)))
+    }
+}
+
+fn word_bits(alg:Algorithm) -> usize {
+    match alg {
+        Algorithm::Sha256 | Algorithm::Sha224 => 32,
+        Algorithm::Sha512 | Algorithm::Sha384 => 64
+    }
+}
+
+fn digest_bytes(alg:Algorithm) -> usize {
+    match alg {
+        Algorithm::Sha224 => 28,
+        Algorithm::Sha256 => 32,
+        Algorithm::Sha384 => 48,
+        Algorithm::Sha512 => 64
+    }
+}
+
+/// The initial hash value and round constants of a standard algorithm, widened to `u64`
+fn standard_words(alg:Algorithm) -> (Vec<u64>, Vec<u64>) {
+    fn widen<const KLEN:usize, T:Into<u64> + Copy>(c:&impl Constants<KLEN, T>) -> (Vec<u64>, Vec<u64>) {
+        (c.initial_hash().iter().map(|w| (*w).into()).collect(), c.constant_words().iter().map(|w| (*w).into()).collect())
+    }
+    match alg {
+        Algorithm::Sha256 => widen(&constants::Sha256),
+        Algorithm::Sha224 => widen(&constants::Sha224),
+        Algorithm::Sha512 => widen(&constants::Sha512),
+        Algorithm::Sha384 => widen(&constants::Sha384)
+    }
+}
+
+/// Reads a word in hex, with or without `0x`, that fits into `bits`
+fn parse_word(text:&str, bits:usize) -> Option<u64> {
+    let text = text.trim();
+    let text = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")).unwrap_or(text);
+    if text.is_empty() || text.len() > bits / 4 {
+        return None;
+    }
+    u64::from_str_radix(text, 16).ok()
+}
+
+/// Hashes the message with the given words, the result is truncated like the standard algorithm
+fn digest(alg:Algorithm, iv:&[u64], k:&[u64], msg:&[u8]) -> Vec<u8> {
+    let mut bytes:Vec<u8> = match word_bits(alg) {
+        32 => {
+            let constants = CustomConstants::<64, u32>::new(
+                std::array::from_fn(|i| k[i] as u32),
+                std::array::from_fn(|i| iv[i] as u32)
+            );
+            let hash = sha256_internal(sha256_byte_preprocessing(msg), &constants);
+            to_u32_array::<4, 8>(hash).iter().flat_map(|w| w.to_be_bytes()).collect()
+        },
+        _ => {
+            let constants = CustomConstants::<80, u64>::new(
+                std::array::from_fn(|i| k[i]),
+                std::array::from_fn(|i| iv[i])
+            );
+            let hash = sha512_internal(sha512_byte_preprocessing(msg), &constants);
+            to_u64_words(hash).iter().flat_map(|w| w.to_be_bytes()).collect()
+        }
+    };
+    bytes.truncate(digest_bytes(alg));
+    bytes
+}
+
+/// A grid of editable words, words that differ from the standard are yellow and invalid ones red.
+/// Returns whether a word was edited.
+fn word_grid(ui:&mut Ui, id:&str, words:&mut [String], standard:&[u64], bits:usize) -> bool {
+    let columns = if bits == 32 { 8 } else { 4 };
+    let mut changed = false;
+    egui::Grid::new(id).spacing((4., 4.)).show(ui, |ui| {
+        for (i, (word, std_word)) in words.iter_mut().zip(standard).enumerate() {
+            let color = match parse_word(word, bits) {
+                None => Color32::RED,
+                Some(w) if w != *std_word => Color32::YELLOW,
+                Some(_) => ui.visuals().text_color(),
+            };
+            let edit = egui::TextEdit::singleline(word)
+                .font(egui::TextStyle::Monospace)
+                .text_color(color)
+                .desired_width(bits as f32 * 2. + 8.);
+            changed |= ui.add(edit).on_hover_text(format!("{} {}", if standard.len() == 8 { "H" } else { "K" }, i)).changed();
+            if (i + 1) % columns == 0 {
+                ui.end_row();
+            }
+        }
+    });
+    changed
+}
+
+/// The words 8 per line
+fn hex_lines(words:&[String]) -> String {
+    words.chunks(8).map(|line| line.join(" ")).collect::<Vec<_>>().join("\n")
+}
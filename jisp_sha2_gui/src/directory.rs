@@ -0,0 +1,439 @@
+//! Hashing every file in a directory on several threads, with a manifest that can be saved and checked again later
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Instant;
+
+use eframe::egui::{self, Color32, Ui};
+
+use crate::{Algorithm, algorithm_combo};
+use crate::manifest::{self, Entry, Manifest, ManifestFormat};
+use crate::worker::file_digest;
+
+enum ScanMessage {
+    /// all files below the directory with their sizes, sent once before any digest
+    Files(Vec<(String, u64)>),
+    /// the digest of the file with this index in the list
+    Digest(usize, Result<String, String>),
+    Error(String)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Column {
+    Path,
+    Size,
+    Digest,
+    Status
+}
+
+/// How a file compares to the loaded manifest
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
+enum FileStatus {
+    Changed,
+    New,
+    Unchanged,
+    Pending
+}
+
+struct Row {
+    path:String,
+    size:u64,
+    digest:Option<Result<String, String>>
+}
+
+struct Scan {
+    rx:Receiver<ScanMessage>,
+    cancel:Arc<AtomicBool>,
+    started:Instant
+}
+
+pub struct DirectoryPanel {
+    folder:String,
+    rows:Vec<Row>,
+    /// indices into `rows` in the order they are displayed
+    order:Vec<usize>,
+    sort:(Column, bool),
+    hashed_alg:Algorithm,
+    scan:Option<Scan>,
+    /// how long the last finished scan took
+    duration:Option<f64>,
+
+    format:ManifestFormat,
+    manifest_path:String,
+    manifest:Option<Manifest>,
+    message:Result<String, String>
+}
+
+impl DirectoryPanel {
+    pub fn new() -> Self {
+        Self {
+            folder: "".to_owned(),
+            rows: Vec::new(),
+            order: Vec::new(),
+            sort: (Column::Path, true),
+            hashed_alg: Algorithm::Sha256,
+            scan: None,
+            duration: None,
+            format: ManifestFormat::Gnu,
+            manifest_path: "".to_owned(),
+            manifest: None,
+            message: Ok("".to_owned())
+        }
+    }
+
+    /// Whether the panel needs to be redrawn to show new results
+    pub fn is_scanning(&self) -> bool {
+        self.scan.is_some()
+    }
+
+    pub fn show(&mut self, ctx:&egui::Context, ui:&mut Ui, alg:&mut Algorithm) {
+        self.receive();
+
+        //dropping a folder onto the window picks it
+        let dropped = ctx.input(|i| i.raw.dropped_files.first().and_then(|f| f.path.clone()));
+        if let Some(path) = dropped.filter(|p| p.is_dir()) {
+            self.folder = path.display().to_string();
+            self.start(*alg);
+        }
+
+        ui.horizontal(|ui| {
+            algorithm_combo(ui, "directory_algorithm", alg);
+            ui.add_sized((400., 20.), egui::TextEdit::singleline(&mut self.folder).hint_text("Folder path or drop a folder here..."));
+            if self.scan.is_some() {
+                if ui.button("Cancel").clicked() {
+                    self.stop();
+                }
+            } else if ui.button("Hash").clicked() {
+                self.start(*alg);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("manifest_format")
+                .selected_text(format!("{}", self.format))
+                .show_ui(ui, |ui| {
+                    for format in ManifestFormat::ALL {
+                        ui.selectable_value(&mut self.format, format, format!("{}", format));
+                    }
+            });
+            ui.add_sized((300., 20.), egui::TextEdit::singleline(&mut self.manifest_path).hint_text("Manifest file..."));
+
+            let finished = self.scan.is_none() && !self.rows.is_empty();
+            if ui.add_enabled(finished, egui::Button::new("Save")).clicked() {
+                self.message = fs::write(&self.manifest_path, self.export())
+                    .map(|_| format!("Saved {} files to {}", self.rows.len(), self.manifest_path))
+                    .map_err(|e| format!("Could not write {}: {}", self.manifest_path, e));
+            }
+            if ui.add_enabled(finished, egui::Button::new("Copy")).clicked() {
+                let text = self.export();
+                ui.output_mut(|o| o.copied_text = text);
+            }
+            if ui.button("Load").clicked() {
+                self.load(alg);
+            }
+            if self.manifest.is_some() && ui.button("Unload").clicked() {
+                self.manifest = None;
+                self.sort_rows();
+            }
+        });
+
+        match &self.message {
+            Ok(text) if text.is_empty() => (),
+            Ok(text) => { ui.label(text); },
+            Err(e) => { ui.colored_label(Color32::RED, format!("[Error] {}", e)); },
+        }
+
+        self.summary(ui);
+        ui.separator();
+        self.table(ui);
+    }
+
+    fn start(&mut self, alg:Algorithm) {
+        self.stop();
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let root = PathBuf::from(&self.folder);
+        let flag = cancel.clone();
+        thread::spawn(move|| scan(root, alg, tx, flag));
+
+        self.rows.clear();
+        self.order.clear();
+        self.hashed_alg = alg;
+        self.duration = None;
+        self.message = Ok("".to_owned());
+        self.scan = Some(Scan { rx, cancel, started: Instant::now() });
+    }
+
+    fn stop(&mut self) {
+        if let Some(scan) = self.scan.take() {
+            scan.cancel.store(true, Ordering::SeqCst);
+            self.message = Ok("Cancelled".to_owned());
+        }
+    }
+
+    fn receive(&mut self) {
+        let Some(scan) = &self.scan else { return };
+        let mut changed = false;
+        let mut finished = false;
+        loop {
+            match scan.rx.try_recv() {
+                Ok(ScanMessage::Files(files)) => {
+                    self.rows = files.into_iter().map(|(path, size)| Row { path, size, digest: None }).collect();
+                    changed = true;
+                },
+                Ok(ScanMessage::Digest(i, digest)) => {
+                    self.rows[i].digest = Some(digest);
+                    changed = true;
+                },
+                Ok(ScanMessage::Error(e)) => {
+                    self.message = Err(e);
+                    finished = true;
+                    break;
+                },
+                Err(mpsc::TryRecvError::Empty) => break,
+                //all workers are done once every sender is dropped
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                },
+            }
+        }
+
+        if finished {
+            self.duration = Some(scan.started.elapsed().as_secs_f64());
+            self.scan = None;
+        }
+        if changed {
+            self.sort_rows();
+        }
+    }
+
+    fn export(&self) -> String {
+        let entries:Vec<Entry> = self.order.iter().map(|i| &self.rows[*i]).filter_map(|row| match &row.digest {
+            Some(Ok(digest)) => Some(Entry { path: row.path.clone(), size: row.size, digest: digest.clone() }),
+            _ => None,
+        }).collect();
+        manifest::export(self.format, self.hashed_alg, &entries)
+    }
+
+    /// Reads the manifest to compare with, switching to its algorithm and rehashing if needed
+    fn load(&mut self, alg:&mut Algorithm) {
+        let manifest = fs::read_to_string(&self.manifest_path)
+            .map_err(|e| format!("Could not read {}: {}", self.manifest_path, e))
+            .and_then(|text| manifest::parse(&text));
+        match manifest {
+            Ok(manifest) => {
+                self.message = Ok(format!("Loaded {} files from {}", manifest.digests.len(), self.manifest_path));
+                let rehash = manifest.algorithm.filter(|a| *a != self.hashed_alg);
+                self.manifest = Some(manifest);
+                match rehash {
+                    Some(a) if !self.folder.is_empty() => {
+                        *alg = a;
+                        self.start(a);
+                    },
+                    _ => self.sort_rows(),
+                }
+            },
+            Err(e) => self.message = Err(e),
+        }
+    }
+
+    fn status(&self, row:&Row) -> Option<FileStatus> {
+        let manifest = self.manifest.as_ref()?;
+        Some(match (&row.digest, manifest.digests.get(&row.path)) {
+            (_, None) => FileStatus::New,
+            (Some(Ok(digest)), Some(expected)) if digest == expected => FileStatus::Unchanged,
+            (Some(Ok(_)), Some(_)) => FileStatus::Changed,
+            //a file that could not be read does not match anything
+            (Some(Err(_)), Some(_)) => FileStatus::Changed,
+            (None, Some(_)) => FileStatus::Pending,
+        })
+    }
+
+    /// Files in the manifest that are not in the directory
+    fn missing(&self) -> Vec<&String> {
+        let Some(manifest) = &self.manifest else { return Vec::new() };
+        if self.scan.is_some() && self.rows.is_empty() {
+            return Vec::new();
+        }
+        let present:HashSet<&String> = self.rows.iter().map(|r| &r.path).collect();
+        let mut missing:Vec<&String> = manifest.digests.keys().filter(|p| !present.contains(p)).collect();
+        missing.sort();
+        missing
+    }
+
+    fn sort_rows(&mut self) {
+        let (column, ascending) = self.sort;
+        let mut order:Vec<usize> = (0..self.rows.len()).collect();
+        order.sort_by(|a, b| {
+            let (a, b) = (&self.rows[*a], &self.rows[*b]);
+            let ord = match column {
+                Column::Path => a.path.cmp(&b.path),
+                Column::Size => a.size.cmp(&b.size),
+                Column::Digest => digest_key(a).cmp(digest_key(b)),
+                Column::Status => self.status(a).cmp(&self.status(b)),
+            };
+            let ord = ord.then_with(|| a.path.cmp(&b.path));
+            if ascending { ord } else { ord.reverse() }
+        });
+        self.order = order;
+    }
+
+    fn summary(&self, ui:&mut Ui) {
+        let hashed = self.rows.iter().filter(|r| r.digest.is_some()).count();
+        let bytes:u64 = self.rows.iter().map(|r| r.size).sum();
+        let mut text = format!("{} of {} files hashed with {}, {} bytes", hashed, self.rows.len(), self.hashed_alg, bytes);
+        if let Some(secs) = self.duration {
+            text += &format!(" in {:.2} s", secs);
+        }
+
+        ui.horizontal(|ui| {
+            if self.scan.is_some() {
+                let fraction = if self.rows.is_empty() { 0. } else { hashed as f32 / self.rows.len() as f32 };
+                ui.add(egui::ProgressBar::new(fraction).desired_width(160.).show_percentage());
+            }
+            ui.label(text);
+        });
+
+        if self.manifest.is_some() {
+            let count = |s:FileStatus| self.rows.iter().filter(|r| self.status(r) == Some(s)).count();
+            ui.horizontal(|ui| {
+                ui.colored_label(Color32::GREEN, format!("{} unchanged", count(FileStatus::Unchanged)));
+                ui.colored_label(Color32::RED, format!("{} changed", count(FileStatus::Changed)));
+                ui.colored_label(Color32::LIGHT_BLUE, format!("{} new", count(FileStatus::New)));
+                ui.colored_label(Color32::YELLOW, format!("{} missing", self.missing().len()));
+            });
+        }
+    }
+
+    fn table(&mut self, ui:&mut Ui) {
+        let mut clicked = None;
+        let columns = if self.manifest.is_some() {
+            vec![(Column::Path, "Path"), (Column::Size, "Size"), (Column::Digest, "Digest"), (Column::Status, "Status")]
+        } else {
+            vec![(Column::Path, "Path"), (Column::Size, "Size"), (Column::Digest, "Digest")]
+        };
+
+        egui::ScrollArea::both().auto_shrink([false, false]).show(ui, |ui| {
+            egui::Grid::new("directory").striped(true).show(ui, |ui| {
+                for (column, name) in &columns {
+                    let arrow = match self.sort {
+                        (c, true) if c == *column => " ⏶",
+                        (c, false) if c == *column => " ⏷",
+                        _ => "",
+                    };
+                    if ui.selectable_label(self.sort.0 == *column, format!("{}{}", name, arrow)).clicked() {
+                        clicked = Some(*column);
+                    }
+                }
+                ui.end_row();
+
+                for i in &self.order {
+                    let row = &self.rows[*i];
+                    ui.label(&row.path);
+                    ui.label(row.size.to_string());
+                    match &row.digest {
+                        Some(Ok(digest)) => {
+                            let short = format!("{}…", &digest[..16]);
+                            let response = ui.add(egui::Label::new(egui::RichText::new(short).monospace()).sense(egui::Sense::click()));
+                            if response.on_hover_text(format!("{}\nClick to copy", digest)).clicked() {
+                                ui.output_mut(|o| o.copied_text = digest.clone());
+                            }
+                        },
+                        Some(Err(e)) => { ui.colored_label(Color32::RED, "Unreadable").on_hover_text(e); },
+                        None => { ui.spinner(); },
+                    }
+                    match self.status(row) {
+                        Some(FileStatus::Unchanged) => { ui.colored_label(Color32::GREEN, "Unchanged"); },
+                        Some(FileStatus::Changed) => { ui.colored_label(Color32::RED, "Changed"); },
+                        Some(FileStatus::New) => { ui.colored_label(Color32::LIGHT_BLUE, "New"); },
+                        Some(FileStatus::Pending) => { ui.label(""); },
+                        None => (),
+                    }
+                    ui.end_row();
+                }
+
+                for path in self.missing() {
+                    ui.label(path);
+                    ui.label("");
+                    ui.label("");
+                    ui.colored_label(Color32::YELLOW, "Missing");
+                    ui.end_row();
+                }
+            });
+        });
+
+        if let Some(column) = clicked {
+            self.sort = match self.sort {
+                (c, ascending) if c == column => (c, !ascending),
+                _ => (column, true),
+            };
+            self.sort_rows();
+        }
+    }
+}
+
+fn digest_key(row:&Row) -> &str {
+    match &row.digest {
+        Some(Ok(digest)) => digest,
+        _ => "",
+    }
+}
+
+/// Lists the files below `root` and hashes them on one thread per core.
+/// The workers take the next file from a shared counter, so a few large files do not leave the other threads idle.
+fn scan(root:PathBuf, alg:Algorithm, tx:Sender<ScanMessage>, cancel:Arc<AtomicBool>) {
+    let mut files = Vec::new();
+    if let Err(e) = list_files(&root, &root, &mut files) {
+        let _ = tx.send(ScanMessage::Error(format!("Could not list {}: {}", root.display(), e)));
+        return;
+    }
+    files.sort();
+    let _ = tx.send(ScanMessage::Files(files.iter().map(|(path, size)| (path.clone(), *size)).collect()));
+
+    let next = AtomicUsize::new(0);
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(4).min(files.len());
+    let (next, files, root, cancel) = (&next, &files, &root, &cancel);
+    thread::scope(|s| {
+        for _ in 0..threads {
+            let tx = tx.clone();
+            s.spawn(move|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    if i >= files.len() || cancel.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let digest = file_digest(alg, &root.join(&files[i].0), || cancel.load(Ordering::SeqCst));
+                    let digest = match digest {
+                        Ok(Some(digest)) => Ok(digest),
+                        Ok(None) => break,
+                        Err(e) => Err(e.to_string()),
+                    };
+                    let _ = tx.send(ScanMessage::Digest(i, digest));
+                }
+            });
+        }
+    });
+}
+
+/// Adds every file below `dir` with its path relative to `root`, symbolic links are not followed
+fn list_files(root:&Path, dir:&Path, files:&mut Vec<(String, u64)>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let kind = entry.file_type()?;
+        let path = entry.path();
+        if kind.is_dir() {
+            list_files(root, &path, files)?;
+        } else if kind.is_file() {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            let name = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+            files.push((name, entry.metadata()?.len()));
+        }
+    }
+    Ok(())
+}
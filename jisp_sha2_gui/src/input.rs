@@ -0,0 +1,157 @@
+//! The different ways the user can give the message that should be hashed
+use std::fmt;
+use std::path::PathBuf;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum InputMode {
+    Text,
+    Multiline,
+    Hex,
+    Base64,
+    File
+}
+
+impl fmt::Display for InputMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputMode::Text => write!(f, "Text (UTF-8)"),
+            InputMode::Multiline => write!(f, "Multiline Text"),
+            InputMode::Hex => write!(f, "Hex Bytes"),
+            InputMode::Base64 => write!(f, "Base64"),
+            InputMode::File => write!(f, "File")
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Cr
+}
+
+impl LineEnding {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r"
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineEnding::Lf => write!(f, "LF (\\n)"),
+            LineEnding::CrLf => write!(f, "CRLF (\\r\\n)"),
+            LineEnding::Cr => write!(f, "CR (\\r)")
+        }
+    }
+}
+
+/// What the hashing thread should read the message from
+pub enum Input {
+    Bytes(Vec<u8>),
+    File(PathBuf)
+}
+
+/// Turns the text field into the message bytes according to the selected mode.
+/// Note that the text is never trimmed, whitespace is part of the message
+pub fn message_bytes(mode:InputMode, text:&str, ending:LineEnding) -> Result<Vec<u8>, String> {
+    match mode {
+        InputMode::Text => Ok(text.as_bytes().to_vec()),
+        InputMode::Multiline => Ok(normalize_line_endings(text, ending).into_bytes()),
+        InputMode::Hex => decode_hex(text),
+        InputMode::Base64 => decode_base64(text),
+        InputMode::File => Err("A file is not a text input".to_owned())
+    }
+}
+
+/// Replaces every line ending in the text with the chosen one, regardless of what the text editor produced
+pub fn normalize_line_endings(text:&str, ending:LineEnding) -> String {
+    text.replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .replace('\n', ending.as_str())
+}
+
+/// Reads a hex byte string, whitespace is ignored and an optional `0x` prefix is allowed
+pub fn decode_hex(text:&str) -> Result<Vec<u8>, String> {
+    let trimmed = text.trim();
+    let trimmed = trimmed.strip_prefix("0x").or(trimmed.strip_prefix("0X")).unwrap_or(trimmed);
+    let digits:Vec<char> = trimmed.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(format!("Hex input has an odd number of digits: {}", digits.len()));
+    }
+
+    let mut bytes = Vec::with_capacity(digits.len() / 2);
+    for pair in digits.chunks(2) {
+        let mut byte = 0u8;
+        for c in pair {
+            let nibble = c.to_digit(16).ok_or(format!("Invalid hex digit: '{}'", c))?;
+            byte = (byte << 4) + nibble as u8;
+        }
+        bytes.push(byte);
+    }
+    Ok(bytes)
+}
+
+/// Lowercase hex without spaces, the usual way keys and MACs are written
+pub fn to_hex(bytes:&[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Reads standard padded base64, whitespace such as line breaks is ignored
+pub fn decode_base64(text:&str) -> Result<Vec<u8>, String> {
+    let stripped:String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    STANDARD.decode(stripped).map_err(|e| format!("Invalid base64: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex() {
+        assert_eq!(decode_hex("616263"), Ok(b"abc".to_vec()));
+        assert_eq!(decode_hex(" 0xDE ad\nbE ef "), Ok(vec![0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(decode_hex("0X00ff"), Ok(vec![0x00, 0xff]));
+        assert_eq!(decode_hex(""), Ok(Vec::new()));
+        assert!(decode_hex("abc").is_err());
+        assert!(decode_hex("zz").is_err());
+        assert!(decode_hex("0x0x00").is_err());
+        assert_eq!(to_hex(&[0x00, 0xab, 0x10]), "00ab10");
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(decode_base64("YWJj"), Ok(b"abc".to_vec()));
+        assert_eq!(decode_base64("YW\r\nJj ZA==\n"), Ok(b"abcd".to_vec()));
+        assert_eq!(decode_base64(""), Ok(Vec::new()));
+        assert!(decode_base64("YWJjZA").is_err());
+        assert!(decode_base64("YW*j").is_err());
+    }
+
+    #[test]
+    fn line_endings() {
+        let text = "a\r\nb\rc\nd";
+        assert_eq!(normalize_line_endings(text, LineEnding::Lf), "a\nb\nc\nd");
+        assert_eq!(normalize_line_endings(text, LineEnding::CrLf), "a\r\nb\r\nc\r\nd");
+        assert_eq!(normalize_line_endings(text, LineEnding::Cr), "a\rb\rc\rd");
+        //\r\n is one line ending, not two
+        assert_eq!(normalize_line_endings("\r\n\n", LineEnding::CrLf), "\r\n\r\n");
+    }
+
+    #[test]
+    fn messages() {
+        //text is never trimmed
+        assert_eq!(message_bytes(InputMode::Text, " a\r\n", LineEnding::Lf), Ok(b" a\r\n".to_vec()));
+        assert_eq!(message_bytes(InputMode::Multiline, " a\r\nb", LineEnding::Lf), Ok(b" a\nb".to_vec()));
+        assert_eq!(message_bytes(InputMode::Hex, "61 62", LineEnding::Lf), Ok(b"ab".to_vec()));
+        assert_eq!(message_bytes(InputMode::Base64, "YWI=", LineEnding::Lf), Ok(b"ab".to_vec()));
+        assert!(message_bytes(InputMode::File, "path", LineEnding::Lf).is_err());
+    }
+}
@@ -0,0 +1,199 @@
+//! Bookkeeping and display of the jobs sent to the hashing thread
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use eframe::egui::{self, Ui};
+
+use crate::worker::Message;
+
+/// How many finished jobs are kept in the list
+const HISTORY:usize = 10;
+
+#[derive(Clone, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed(String),
+    Cancelled
+}
+
+/// What the progress of a job counts, so the throughput can be shown in a useful unit
+#[derive(Clone, Copy)]
+pub enum WorkUnit {
+    /// message blocks of the given size in bytes
+    Blocks(u64),
+    Iterations
+}
+
+struct JobInfo {
+    id:u64,
+    label:String,
+    status:JobStatus,
+    done:u64,
+    total:u64,
+    unit:WorkUnit,
+    cancel:Arc<AtomicBool>,
+    started:Option<Instant>,
+    elapsed:Duration
+}
+
+impl JobInfo {
+    fn is_active(&self) -> bool {
+        matches!(self.status, JobStatus::Queued | JobStatus::Running)
+    }
+
+    fn elapsed(&self) -> Duration {
+        match (self.status == JobStatus::Running, self.started) {
+            (true, Some(started)) => started.elapsed(),
+            _ => self.elapsed,
+        }
+    }
+
+    fn throughput(&self) -> String {
+        let secs = self.elapsed().as_secs_f64();
+        if secs == 0. || self.done == 0 {
+            return "".to_owned();
+        }
+        match self.unit {
+            WorkUnit::Blocks(size) => format!("{:.1} MiB/s", (self.done * size) as f64 / secs / (1024. * 1024.)),
+            WorkUnit::Iterations => format!("{:.0} it/s", self.done as f64 / secs),
+        }
+    }
+
+    fn finish(&mut self, status:JobStatus) {
+        self.elapsed = self.elapsed();
+        self.status = status;
+    }
+}
+
+pub struct JobList {
+    jobs:VecDeque<JobInfo>
+}
+
+impl JobList {
+    pub fn new() -> Self {
+        Self { jobs: VecDeque::new() }
+    }
+
+    /// Registers a new job and returns the flag that cancels it
+    pub fn add(&mut self, id:u64, label:String, unit:WorkUnit) -> Arc<AtomicBool> {
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.jobs.push_back(JobInfo {
+            id,
+            label,
+            status: JobStatus::Queued,
+            done: 0,
+            total: 0,
+            unit,
+            cancel: cancel.clone(),
+            started: None,
+            elapsed: Duration::ZERO
+        });
+
+        //drop the oldest finished jobs, running ones stay so they can still be cancelled
+        while self.jobs.len() > HISTORY {
+            match self.jobs.iter().position(|j| !j.is_active()) {
+                Some(i) => { self.jobs.remove(i); },
+                None => break,
+            }
+        }
+        cancel
+    }
+
+    /// Asks the hashing thread to stop the job, it is marked as cancelled once the thread confirms it
+    pub fn cancel(&self, id:u64) {
+        if let Some(job) = self.jobs.iter().find(|j| j.id == id) {
+            job.cancel.store(true, Ordering::SeqCst);
+        }
+    }
+
+    pub fn is_active(&self, id:u64) -> bool {
+        self.jobs.iter().any(|j| j.id == id && j.is_active())
+    }
+
+    pub fn any_active(&self) -> bool {
+        self.jobs.iter().any(JobInfo::is_active)
+    }
+
+    /// Marks every unfinished job except `keep` as failed, for when the hashing thread is gone
+    pub fn fail_others(&mut self, keep:u64, reason:&str) {
+        for job in self.jobs.iter_mut().filter(|j| j.id != keep && j.is_active()) {
+            job.finish(JobStatus::Failed(reason.to_owned()));
+        }
+    }
+
+    pub fn update(&mut self, msg:&Message) {
+        let id = match msg {
+            Message::Started(id) | Message::Progress(id, _, _) | Message::Hex(id, _) | Message::Hash(id, _)
+            | Message::Comparison(id, _) | Message::Derived(id, _) | Message::Error(id, _) | Message::Cancelled(id) => *id,
+        };
+        let job = match self.jobs.iter_mut().find(|j| j.id == id) {
+            Some(job) => job,
+            None => return,
+        };
+
+        match msg {
+            Message::Started(_) => {
+                job.status = JobStatus::Running;
+                job.started = Some(Instant::now());
+            },
+            Message::Progress(_, done, total) => {
+                job.done = *done;
+                job.total = *total;
+            },
+            Message::Hex(..) => (),
+            Message::Hash(..) | Message::Comparison(..) | Message::Derived(..) => {
+                job.done = job.total;
+                job.finish(JobStatus::Done);
+            },
+            Message::Error(_, e) => job.finish(JobStatus::Failed(e.clone())),
+            Message::Cancelled(_) => job.finish(JobStatus::Cancelled),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    pub fn show(&mut self, ui:&mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Jobs");
+            if ui.button("Clear finished").clicked() {
+                self.jobs.retain(JobInfo::is_active);
+            }
+        });
+
+        egui::Grid::new("jobs").striped(true).show(ui, |ui| {
+            for job in self.jobs.iter().rev() {
+                ui.label(format!("#{}", job.id));
+                ui.label(&job.label);
+
+                match &job.status {
+                    JobStatus::Queued => { ui.label("Queued"); },
+                    JobStatus::Running => {
+                        let fraction = if job.total == 0 { 0. } else { job.done as f32 / job.total as f32 };
+                        ui.add(egui::ProgressBar::new(fraction).desired_width(160.).show_percentage());
+                    },
+                    JobStatus::Done => { ui.colored_label(egui::Color32::GREEN, "Done"); },
+                    JobStatus::Failed(e) => { ui.colored_label(egui::Color32::RED, "Failed").on_hover_text(e); },
+                    JobStatus::Cancelled => { ui.colored_label(egui::Color32::YELLOW, "Cancelled"); },
+                }
+
+                ui.label(format!("{:.2} s", job.elapsed().as_secs_f64()));
+                ui.label(job.throughput());
+
+                if job.is_active() {
+                    if ui.button("Cancel").clicked() {
+                        job.cancel.store(true, Ordering::SeqCst);
+                    }
+                } else {
+                    ui.label("");
+                }
+                ui.end_row();
+            }
+        });
+    }
+}
@@ -0,0 +1,209 @@
+//! Panels for the keyed constructions of the library: HMAC, HKDF and PBKDF2
+use eframe::egui::{self, Ui};
+use jisp_sha2 as sha;
+use sha::constants;
+use sha::hmac::hmac;
+use sha::kdf::hkdf;
+use sha::sha256::Sha256Hasher;
+use sha::sha512::Sha512Hasher;
+
+use crate::{Algorithm, algorithm_combo};
+use crate::input::{decode_hex, to_hex};
+use crate::worker::Task;
+
+/// A byte string that can be typed either as UTF-8 text or as hex
+struct ByteField {
+    text:String,
+    hex:bool
+}
+
+impl ByteField {
+    fn new(text:&str) -> Self {
+        Self { text: text.to_owned(), hex: false }
+    }
+
+    fn show(&mut self, ui:&mut Ui, label:&str, hint:&str) {
+        ui.label(label);
+        ui.add_sized((440., 20.), egui::TextEdit::singleline(&mut self.text).hint_text(hint));
+        ui.checkbox(&mut self.hex, "Hex");
+        ui.end_row();
+    }
+
+    fn bytes(&self) -> Result<Vec<u8>, String> {
+        if self.hex {
+            decode_hex(&self.text)
+        } else {
+            Ok(self.text.as_bytes().to_vec())
+        }
+    }
+}
+
+fn result_row(ui:&mut Ui, result:&Result<String, String>) {
+    ui.label("[OUT]:");
+    match result {
+        Ok(hex) => {
+            ui.add(egui::Label::new(egui::RichText::new(hex).monospace()).selectable(true).wrap(true));
+            if ui.button("Copy").clicked() {
+                ui.output_mut(|o| o.copied_text = hex.clone());
+            }
+        },
+        Err(e) if e.is_empty() => (),
+        Err(e) => {
+            ui.colored_label(egui::Color32::RED, format!("[Error] {}", e));
+        },
+    }
+    ui.end_row();
+}
+
+pub struct HmacPanel {
+    key:ByteField,
+    msg:ByteField,
+    result:Result<String, String>
+}
+
+impl HmacPanel {
+    pub fn new() -> Self {
+        Self { key: ByteField::new(""), msg: ByteField::new(""), result: Err("".to_owned()) }
+    }
+
+    pub fn show(&mut self, ui:&mut Ui, alg:&mut Algorithm) {
+        algorithm_combo(ui, "hmac_algorithm", alg);
+        egui::Grid::new("hmac").show(ui, |ui| {
+            self.key.show(ui, "[KEY]:", "Secret Key...");
+            self.msg.show(ui, "[MSG]:", "Message, e.g. the webhook payload...");
+            if ui.button("Compute").clicked() {
+                self.result = self.compute(*alg);
+            }
+            ui.end_row();
+            result_row(ui, &self.result);
+        });
+    }
+
+    fn compute(&self, alg:Algorithm) -> Result<String, String> {
+        let key = self.key.bytes()?;
+        let msg = self.msg.bytes()?;
+        let tag = match alg {
+            Algorithm::Sha256 => hmac::<Sha256Hasher<constants::Sha256>>(&key, &msg),
+            Algorithm::Sha224 => hmac::<Sha256Hasher<constants::Sha224>>(&key, &msg),
+            Algorithm::Sha512 => hmac::<Sha512Hasher<constants::Sha512>>(&key, &msg),
+            Algorithm::Sha384 => hmac::<Sha512Hasher<constants::Sha384>>(&key, &msg),
+        };
+        Ok(to_hex(&tag))
+    }
+}
+
+pub struct HkdfPanel {
+    ikm:ByteField,
+    salt:ByteField,
+    info:ByteField,
+    len:usize,
+    result:Result<String, String>
+}
+
+impl HkdfPanel {
+    pub fn new() -> Self {
+        Self {
+            ikm: ByteField::new(""),
+            salt: ByteField::new(""),
+            info: ByteField::new(""),
+            len: 32,
+            result: Err("".to_owned())
+        }
+    }
+
+    pub fn show(&mut self, ui:&mut Ui, alg:&mut Algorithm) {
+        algorithm_combo(ui, "hkdf_algorithm", alg);
+        egui::Grid::new("hkdf").show(ui, |ui| {
+            self.ikm.show(ui, "[IKM]:", "Input Key Material...");
+            self.salt.show(ui, "[SALT]:", "Optional Salt...");
+            self.info.show(ui, "[INFO]:", "Optional Context Info...");
+            ui.label("[LENGTH]:");
+            ui.add(egui::DragValue::new(&mut self.len).clamp_range(1..=255 * 64).suffix(" bytes"));
+            ui.end_row();
+            if ui.button("Derive").clicked() {
+                self.result = self.compute(*alg);
+            }
+            ui.end_row();
+            result_row(ui, &self.result);
+        });
+    }
+
+    fn compute(&self, alg:Algorithm) -> Result<String, String> {
+        let ikm = self.ikm.bytes()?;
+        let salt = self.salt.bytes()?;
+        let info = self.info.bytes()?;
+        let okm = match alg {
+            Algorithm::Sha256 => hkdf::<Sha256Hasher<constants::Sha256>>(&ikm, &salt, &info, self.len),
+            Algorithm::Sha224 => hkdf::<Sha256Hasher<constants::Sha224>>(&ikm, &salt, &info, self.len),
+            Algorithm::Sha512 => hkdf::<Sha512Hasher<constants::Sha512>>(&ikm, &salt, &info, self.len),
+            Algorithm::Sha384 => hkdf::<Sha512Hasher<constants::Sha384>>(&ikm, &salt, &info, self.len),
+        };
+        okm.map(|okm| to_hex(&okm)).ok_or(format!("{} can derive at most 255 hash lengths of key material", alg))
+    }
+}
+
+/// PBKDF2 runs on the hashing thread since a large number of iterations can take a while
+pub struct Pbkdf2Panel {
+    password:ByteField,
+    salt:ByteField,
+    iterations:u32,
+    len:usize,
+    /// finished and total iterations of the running derivation
+    pub progress:Option<(u64, u64)>,
+    pub result:Result<String, String>
+}
+
+impl Pbkdf2Panel {
+    pub fn new() -> Self {
+        Self {
+            password: ByteField::new(""),
+            salt: ByteField::new(""),
+            iterations: 600_000,
+            len: 32,
+            progress: None,
+            result: Err("".to_owned())
+        }
+    }
+
+    /// Returns the task for the hashing thread when the user starts a derivation
+    pub fn show(&mut self, ui:&mut Ui, alg:&mut Algorithm) -> Option<Task> {
+        let mut task = None;
+        algorithm_combo(ui, "pbkdf2_algorithm", alg);
+        egui::Grid::new("pbkdf2").show(ui, |ui| {
+            self.password.show(ui, "[PASSWORD]:", "Password...");
+            self.salt.show(ui, "[SALT]:", "Salt...");
+            ui.label("[ITERATIONS]:");
+            ui.add(egui::DragValue::new(&mut self.iterations).clamp_range(1..=u32::MAX).speed(1000));
+            ui.end_row();
+            ui.label("[LENGTH]:");
+            ui.add(egui::DragValue::new(&mut self.len).clamp_range(1..=1024).suffix(" bytes"));
+            ui.end_row();
+            if ui.button("Derive").clicked() {
+                match self.task(*alg) {
+                    Ok(t) => {
+                        task = Some(t);
+                        self.result = Err("".to_owned());
+                    },
+                    Err(e) => self.result = Err(e),
+                }
+            }
+            if let Some((done, total)) = self.progress {
+                let fraction = if total == 0 { 0. } else { done as f32 / total as f32 };
+                ui.add(egui::ProgressBar::new(fraction).show_percentage());
+            }
+            ui.end_row();
+            result_row(ui, &self.result);
+        });
+        task
+    }
+
+    fn task(&self, alg:Algorithm) -> Result<Task, String> {
+        Ok(Task::Pbkdf2 {
+            alg,
+            password: self.password.bytes()?,
+            salt: self.salt.bytes()?,
+            iterations: self.iterations,
+            len: self.len
+        })
+    }
+}
@@ -1,16 +1,36 @@
 use std::{thread, fmt};
+use std::path::PathBuf;
 use std::sync::mpsc::{self, Sender, Receiver};
+use std::time::{Duration, Instant};
 
 use eframe::egui;
 use eframe::epaint::FontId;
-use jisp_sha2 as sha;
-use sha::printer::{print_blocks, print_u32_word_string};
 
-fn main() {
+use avalanche::AvalanchePanel;
+use custom::CustomPanel;
+use directory::DirectoryPanel;
+use jobs::{JobList, WorkUnit};
+use input::{Input, InputMode, LineEnding, message_bytes};
+use keyed::{HkdfPanel, HmacPanel, Pbkdf2Panel};
+use pow::PowPanel;
+use verify::{algorithm_for, matches, parse_expected};
+use worker::{Job, Message, Task, hashing_thread};
+
+mod avalanche;
+mod custom;
+mod directory;
+mod input;
+mod jobs;
+mod keyed;
+mod manifest;
+mod pow;
+mod verify;
+mod worker;
+
+fn main() -> eframe::Result<()> {
     let mut native_options = eframe::NativeOptions::default();
-    let _ = native_options.viewport.inner_size.insert((660., 480.).into());
-    let _ = eframe::run_native("SHA-2", native_options, Box::new(|cc| Box::new(MultProgram::new(cc))))
-        .expect("Unexpected Error");
+    let _ = native_options.viewport.inner_size.insert((700., 640.).into());
+    eframe::run_native("SHA-2", native_options, Box::new(|cc| Box::new(MultProgram::new(cc))))
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -32,150 +52,452 @@ impl fmt::Display for Algorithm {
     }
 }
 
-enum Message {
-    Hex(String),
-    Hash(String)
+impl Algorithm {
+    const ALL:[Algorithm; 4] = [Algorithm::Sha224, Algorithm::Sha256, Algorithm::Sha384, Algorithm::Sha512];
+
+    /// The size of one message block in bytes
+    fn block_bytes(self) -> usize {
+        match self {
+            Algorithm::Sha256 | Algorithm::Sha224 => 64,
+            Algorithm::Sha512 | Algorithm::Sha384 => 128
+        }
+    }
+
+    /// The size of the length that is appended to the message by the padding, in bytes
+    fn suffix_bytes(self) -> usize {
+        match self {
+            Algorithm::Sha256 | Algorithm::Sha224 => 8,
+            Algorithm::Sha512 | Algorithm::Sha384 => 16
+        }
+    }
+}
+
+fn spawn_worker() -> (Sender<Job>, Receiver<Message>) {
+    let (tx1, rx1) = mpsc::channel();
+    let (tx2, rx2) = mpsc::channel();
+    thread::spawn(move|| hashing_thread(tx2, rx1));
+    (tx1, rx2)
+}
+
+fn algorithm_combo(ui:&mut egui::Ui, id:&str, alg:&mut Algorithm) {
+    egui::ComboBox::from_id_source(id)
+        .selected_text(format!("{}", alg))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(alg, Algorithm::Sha512, "SHA-512");
+            ui.selectable_value(alg, Algorithm::Sha384, "SHA-384");
+            ui.selectable_value(alg, Algorithm::Sha256, "SHA-256");
+            ui.selectable_value(alg, Algorithm::Sha224, "SHA-224");
+    });
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum Tab {
+    Hash,
+    Directory,
+    Avalanche,
+    Custom,
+    Hmac,
+    Hkdf,
+    Pbkdf2,
+    ProofOfWork
+}
+
+impl Tab {
+    const ALL:[Tab; 8] = [Tab::Hash, Tab::Directory, Tab::Avalanche, Tab::Custom, Tab::Hmac, Tab::Hkdf, Tab::Pbkdf2, Tab::ProofOfWork];
+}
+
+impl fmt::Display for Tab {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tab::Hash => write!(f, "Hash"),
+            Tab::Directory => write!(f, "Directory"),
+            Tab::Avalanche => write!(f, "Avalanche"),
+            Tab::Custom => write!(f, "Custom Constants"),
+            Tab::Hmac => write!(f, "HMAC"),
+            Tab::Hkdf => write!(f, "HKDF"),
+            Tab::Pbkdf2 => write!(f, "PBKDF2"),
+            Tab::ProofOfWork => write!(f, "Proof of Work")
+        }
+    }
+}
+
+/// How long the input has to stay unchanged before it is rehashed in live mode
+const DEBOUNCE:Duration = Duration::from_millis(300);
+
 struct MultProgram {
+    tab:Tab,
+    directory:DirectoryPanel,
+    avalanche:AvalanchePanel,
+    custom:CustomPanel,
+    hmac:HmacPanel,
+    hkdf:HkdfPanel,
+    pbkdf2:Pbkdf2Panel,
+    pow:PowPanel,
+
     input:String,
+    file_path:String,
+    mode:InputMode,
+    line_ending:LineEnding,
     hex:String,
     hash:String,
-    thread_active:bool,
+    comparison:Vec<(Algorithm, String)>,
+    expected:String,
+    /// the algorithm of the hash that is currently displayed, if there is one
+    hashed_alg:Option<Algorithm>,
     alg:Algorithm,
 
-    tx:Sender<(Algorithm, String)>,
+    live:bool,
+    compare:bool,
+    last_edit:Option<Instant>,
+
+    jobs:JobList,
+    job_id:u64,
+    /// the job whose result is shown in the hash tab and the one shown in the PBKDF2 tab
+    hash_job:Option<u64>,
+    pbkdf2_job:Option<u64>,
+    job_alg:Algorithm,
+    tx:Sender<Job>,
     rx:Receiver<Message>
 
 }
 
-fn hashing_thread(tx:Sender<Message>, rx:Receiver<(Algorithm, String)>) {
-    for (a, s) in rx.iter() {
-        match a {
-            Algorithm::Sha256 => {
-                let i = sha::preprocessing::sha256_preprocessing(&s);
-                let hex_text = print_blocks(&i,true);
-                tx.send(Message::Hex(hex_text)).unwrap();
-        
-                let hash = sha::sha256::sha_256(i);
-                let hash_text = print_blocks(&vec![hash],true);
-                tx.send(Message::Hash(hash_text)).unwrap();
+impl MultProgram {
+    fn new(cc:&eframe::CreationContext<'_>) -> Self {
+        cc.egui_ctx.set_visuals(egui::Visuals::dark());
+        let (tx, rx) = spawn_worker();
+        Self {
+            tab: Tab::Hash,
+            directory: DirectoryPanel::new(),
+            avalanche: AvalanchePanel::new(),
+            custom: CustomPanel::new(),
+            hmac: HmacPanel::new(),
+            hkdf: HkdfPanel::new(),
+            pbkdf2: Pbkdf2Panel::new(),
+            pow: PowPanel::new(),
+            tx, rx,
+            input: "".to_owned(),
+            file_path: "".to_owned(),
+            mode: InputMode::Text,
+            line_ending: LineEnding::Lf,
+            hex: "".to_owned(),
+            hash: "".to_owned(),
+            comparison: Vec::new(),
+            expected: "".to_owned(),
+            hashed_alg: None,
+            alg: Algorithm::Sha256,
+            live: false,
+            compare: false,
+            last_edit: None,
+            jobs: JobList::new(),
+            job_id: 0,
+            hash_job: None,
+            pbkdf2_job: None,
+            job_alg: Algorithm::Sha256,
+        }
+    }
+
+    /// Sends a new hash job to the hashing thread, cancelling the previous one if it is still running
+    fn submit(&mut self) {
+        self.last_edit = None;
+        let input = match self.mode {
+            InputMode::File => Ok(Input::File(PathBuf::from(&self.file_path))),
+            mode => message_bytes(mode, &self.input, self.line_ending).map(Input::Bytes)
+        };
+        match input {
+            Ok(input) => {
+                if let Some(id) = self.hash_job {
+                    self.jobs.cancel(id);
+                }
+                let name = if self.compare { "All algorithms".to_owned() } else { self.alg.to_string() };
+                let label = match &input {
+                    Input::File(path) => format!("{} of {}", name, path.display()),
+                    Input::Bytes(bytes) => format!("{} of {} bytes", name, bytes.len()),
+                };
+                let unit = WorkUnit::Blocks(self.alg.block_bytes() as u64);
+                self.hash_job = Some(self.start_job(Task::Hash { alg: self.alg, input, compare: self.compare }, label, unit));
+                self.job_alg = self.alg;
+                self.hex = "[Loading...]".to_owned();
+                self.hash = "[Loading...]".to_owned();
+                self.hashed_alg = None;
+                self.comparison.clear();
             },
+            Err(e) => {
+                self.hex = "".to_owned();
+                self.hash = format!("[Error] {}", e);
+                self.hashed_alg = None;
+                self.comparison.clear();
+            }
+        }
+    }
 
-            Algorithm::Sha224 => {
-                let i = sha::preprocessing::sha256_preprocessing(&s);
-                let hex_text = print_blocks(&i,true);
-                tx.send(Message::Hex(hex_text)).unwrap();
-        
-                let hash = sha::sha256::sha_224(i);
-                let hash_text = print_u32_word_string(&hash.to_vec());
-                tx.send(Message::Hash(hash_text)).unwrap();
+    /// Shows whether the hash matches the expected digest, with the comparison table any of the algorithms can match
+    fn verification(&self, ui:&mut egui::Ui) {
+        let expected = match parse_expected(&self.expected) {
+            Ok(hex) => hex,
+            Err(e) => {
+                ui.colored_label(egui::Color32::YELLOW, format!("⚠ {}", e));
+                return;
             }
+        };
+        let alg = algorithm_for(&expected);
+        let hash = match self.comparison.iter().find(|(a, _)| Some(*a) == alg) {
+            Some((_, digest)) => Some(digest),
+            None if self.hashed_alg == alg => Some(&self.hash),
+            None => None,
+        };
 
-            Algorithm::Sha512 => {
-                let i = sha::preprocessing::sha512_preprocessing(&s);
-                let hex_text = print_blocks(&i,true);
-                tx.send(Message::Hex(hex_text)).unwrap();
-        
-                let hash = sha::sha512::sha_512(i);
-                let hash_text = print_blocks(&vec![hash],true);
-                tx.send(Message::Hash(hash_text)).unwrap();
+        match hash {
+            Some(hash) if matches(hash, &expected) => {
+                ui.colored_label(egui::Color32::GREEN, "✔ Match");
             },
-
-            Algorithm::Sha384 => {
-                //same preprocessing as sha512 with slightly different algorithm
-                let i = sha::preprocessing::sha512_preprocessing(&s);
-                let hex_text = print_blocks(&i,true);
-                tx.send(Message::Hex(hex_text)).unwrap();
-        
-                let hash = sha::sha512::sha_384(i);
-                let hash_text = print_blocks(&vec![hash],true);
-                tx.send(Message::Hash(hash_text)).unwrap();
+            Some(_) => {
+                ui.colored_label(egui::Color32::RED, "✘ Mismatch");
+            },
+            None if self.hash_job.is_some_and(|id| self.jobs.is_active(id)) => (),
+            None => {
+                ui.label(format!("Submit to compare with the expected {} digest", alg.map(|a| a.to_string()).unwrap_or_default()));
             },
         }
-        
     }
-}
 
-impl MultProgram {
-    fn new(cc:&eframe::CreationContext<'_>) -> Self {
-        cc.egui_ctx.set_visuals(egui::Visuals::dark());
-        let (tx1, rx1) = mpsc::channel();
-        let (tx2, rx2) = mpsc::channel::<Message>();
-        thread::spawn(move|| hashing_thread(tx2, rx1));
-        Self {
-            tx: tx1, rx: rx2,
-            thread_active:false,
-            input: "".to_owned(),
-            hex: "".to_owned(),
-            hash: "".to_owned(),
-            alg: Algorithm::Sha256,
+    /// Queues a task on the hashing thread and returns the id of the job.
+    /// If the thread is gone a new one is started, so one failure does not break every later job.
+    fn start_job(&mut self, task:Task, label:String, unit:WorkUnit) -> u64 {
+        self.job_id += 1;
+        let cancel = self.jobs.add(self.job_id, label, unit);
+        let job = Job { id: self.job_id, task, cancel };
+        if let Err(mpsc::SendError(job)) = self.tx.send(job) {
+            //the jobs that were still queued on the old thread are lost with it
+            self.jobs.fail_others(job.id, "The hashing thread stopped");
+            (self.tx, self.rx) = spawn_worker();
+            let _ = self.tx.send(job);
+        }
+        self.job_id
+    }
+
+    fn receive(&mut self) {
+        while let Ok(msg) = self.rx.try_recv() {
+            self.jobs.update(&msg);
+            let hash_job = self.hash_job;
+            let pbkdf2_job = self.pbkdf2_job;
+            match msg {
+                Message::Hex(id, text) if Some(id) == hash_job => self.hex = text,
+                Message::Hash(id, text) if Some(id) == hash_job => {
+                    self.hash = text;
+                    self.hashed_alg = Some(self.job_alg);
+                },
+                Message::Comparison(id, digests) if Some(id) == hash_job => {
+                    self.hash = "".to_owned();
+                    self.comparison = digests;
+                },
+                Message::Error(id, text) if Some(id) == hash_job => {
+                    self.hex = "".to_owned();
+                    self.hash = format!("[Error] {}", text);
+                },
+                Message::Cancelled(id) if Some(id) == hash_job => {
+                    self.hex = "".to_owned();
+                    self.hash = "[Cancelled]".to_owned();
+                },
+
+                Message::Progress(id, done, total) if Some(id) == pbkdf2_job => self.pbkdf2.progress = Some((done, total)),
+                Message::Derived(id, key) if Some(id) == pbkdf2_job => {
+                    self.pbkdf2.progress = None;
+                    self.pbkdf2.result = Ok(key);
+                },
+                Message::Error(id, text) if Some(id) == pbkdf2_job => {
+                    self.pbkdf2.progress = None;
+                    self.pbkdf2.result = Err(text);
+                },
+                Message::Cancelled(id) if Some(id) == pbkdf2_job => {
+                    self.pbkdf2.progress = None;
+                    self.pbkdf2.result = Err("Cancelled".to_owned());
+                },
+                //progress is shown in the job list, results of replaced jobs are dropped
+                _ => (),
+            }
         }
     }
 }
 
 impl eframe::App for MultProgram {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.receive();
+        if self.jobs.any_active() || self.directory.is_scanning() || self.pow.is_searching() {
+            ctx.request_repaint_after(Duration::from_millis(50));
+        }
+
+        if !self.jobs.is_empty() {
+            egui::TopBottomPanel::bottom("jobs").show(ctx, |ui| self.jobs.show(ui));
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            if self.thread_active {
-                match self.rx.try_recv() {
-                    Ok(Message::Hex(text)) => self.hex = text,
-                    Ok(Message::Hash(text)) => {
-                        self.hash = text;
-                        self.thread_active = false;
-                    },
-                    Err(_) => (), 
+
+            ui.horizontal(|ui| {
+                for tab in Tab::ALL {
+                    ui.selectable_value(&mut self.tab, tab, format!("{}", tab));
                 }
+            });
+            ui.separator();
+
+            match self.tab {
+                Tab::Hash => self.hash_tab(ctx, ui),
+                Tab::Directory => self.directory.show(ctx, ui, &mut self.alg),
+                Tab::Avalanche => self.avalanche.show(ui, &mut self.alg),
+                Tab::Custom => self.custom.show(ui),
+                Tab::Hmac => self.hmac.show(ui, &mut self.alg),
+                Tab::Hkdf => self.hkdf.show(ui, &mut self.alg),
+                Tab::Pbkdf2 => {
+                    if let Some(task) = self.pbkdf2.show(ui, &mut self.alg) {
+                        if let Some(id) = self.pbkdf2_job {
+                            self.jobs.cancel(id);
+                        }
+                        let label = format!("PBKDF2-{}", self.alg);
+                        self.pbkdf2_job = Some(self.start_job(task, label, WorkUnit::Iterations));
+                        self.pbkdf2.progress = Some((0, 0));
+                    }
+                },
+                Tab::ProofOfWork => self.pow.show(ui),
+            }
+        });
+    }
+}
+
+impl MultProgram {
+    fn hash_tab(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        let settings = (self.alg, self.mode, self.line_ending, self.compare);
+
+        //dropping a file onto the window switches to file input
+        let dropped = ctx.input(|i| i.raw.dropped_files.first().and_then(|f| f.path.clone()));
+        if let Some(path) = dropped {
+            self.file_path = path.display().to_string();
+            self.mode = InputMode::File;
+            if self.live {
+                self.submit();
             }
+        }
 
-            egui::ComboBox::from_label("")
-                .selected_text(format!("{}", &self.alg))
-                .show_ui(ui, |ui| {
-                    ui.selectable_value(&mut self.alg, Algorithm::Sha512, "SHA-512");
-                    ui.selectable_value(&mut self.alg, Algorithm::Sha384, "SHA-384");
-                    ui.selectable_value(&mut self.alg, Algorithm::Sha256, "SHA-256");
-                    ui.selectable_value(&mut self.alg, Algorithm::Sha224, "SHA-224");
-                    
+        ui.horizontal(|ui| {
+            algorithm_combo(ui, "algorithm", &mut self.alg);
 
+            egui::ComboBox::from_id_source("input_mode")
+                .selected_text(format!("{}", &self.mode))
+                .show_ui(ui, |ui| {
+                    for mode in [InputMode::Text, InputMode::Multiline, InputMode::Hex, InputMode::Base64, InputMode::File] {
+                        ui.selectable_value(&mut self.mode, mode, format!("{}", mode));
+                    }
             });
 
-            ui.group(|ui| {
-                egui::Grid::new("stuff").show(ui, |ui| {
-                    ui.label("[IN]:");
-                    ui.add_sized((520., 20.), egui::TextEdit::singleline(&mut self.input).hint_text("Input Text..."));
-                    if ui.button("Submit").clicked() && !self.thread_active {
-                        let s = self.input.trim().to_owned();
-                        self.tx.send((self.alg, s)).unwrap();
-                        self.thread_active = true;
-                        self.hex = "[Loading...]".to_owned();
-                        self.hash = "[Loading...]".to_owned();
+            if self.mode == InputMode::Multiline {
+                egui::ComboBox::from_id_source("line_ending")
+                    .selected_text(format!("{}", &self.line_ending))
+                    .show_ui(ui, |ui| {
+                        for ending in [LineEnding::Lf, LineEnding::CrLf, LineEnding::Cr] {
+                            ui.selectable_value(&mut self.line_ending, ending, format!("{}", ending));
+                        }
+                });
+            }
+
+            ui.checkbox(&mut self.live, "Live");
+            ui.checkbox(&mut self.compare, "Compare all");
+        });
+
+        if settings != (self.alg, self.mode, self.line_ending, self.compare) && self.live {
+            self.last_edit = Some(Instant::now());
+        }
+
+        ui.group(|ui| {
+            egui::Grid::new("stuff").show(ui, |ui| {
+                ui.label("[IN]:");
+                let response = match self.mode {
+                    InputMode::Text => {
+                        ui.add_sized((520., 20.), egui::TextEdit::singleline(&mut self.input).hint_text("Input Text..."))
+                    },
+                    InputMode::Multiline => {
+                        ui.add_sized((520., 80.), egui::TextEdit::multiline(&mut self.input).hint_text("Input Text..."))
+                    },
+                    InputMode::Hex => {
+                        ui.add_sized((520., 80.), egui::TextEdit::multiline(&mut self.input).hint_text("Hex Bytes, e.g. 61 62 63...").code_editor())
+                    },
+                    InputMode::Base64 => {
+                        ui.add_sized((520., 80.), egui::TextEdit::multiline(&mut self.input).hint_text("Base64, e.g. YWJj...").code_editor())
+                    },
+                    InputMode::File => {
+                        ui.add_sized((520., 20.), egui::TextEdit::singleline(&mut self.file_path).hint_text("File path or drop a file here..."))
+                    },
+                };
+                //file paths are only hashed once they are submitted or dropped, not while typing them
+                if response.changed() && self.live && self.mode != InputMode::File {
+                    self.last_edit = Some(Instant::now());
+                }
+                if ui.button("Submit").clicked() {
+                    self.submit();
+                }
+                ui.end_row();
+
+                ui.label("[EXPECTED]:");
+                let response = ui.add_sized((520., 20.), egui::TextEdit::singleline(&mut self.expected).hint_text("Expected digest or checksum line (optional)...").code_editor());
+                //the length of the expected digest tells us which algorithm to use
+                if response.changed() {
+                    let alg = parse_expected(&self.expected).ok().and_then(|hex| algorithm_for(&hex));
+                    if let Some(alg) = alg.filter(|a| *a != self.alg) {
+                        self.alg = alg;
+                        if self.live {
+                            self.last_edit = Some(Instant::now());
+                        }
                     }
-                    ui.end_row();
-                    ui.end_row();
-                    ui.label("[HEX]:"); 
-                    ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                }
+                ui.end_row();
+                ui.end_row();
+                ui.label("[HEX]:"); 
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
 
-                        //ui.style_mut().visuals.override_text_color = Some(egui::Color32::WHITE);
-                        ui.style_mut().override_font_id = Some(FontId::monospace(12.));
-                        ui.add(egui::Label::new(&self.hex).selectable(true).wrap(true));
-                    });
+                    //ui.style_mut().visuals.override_text_color = Some(egui::Color32::WHITE);
+                    ui.style_mut().override_font_id = Some(FontId::monospace(12.));
+                    ui.add(egui::Label::new(&self.hex).selectable(true).wrap(true));
+                });
 
+                ui.end_row();
+                ui.end_row();
+                ui.label("[OUT]:"); 
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+
+                    //ui.style_mut().visuals.override_text_color = Some(egui::Color32::WHITE);
+                    ui.style_mut().override_font_id = Some(FontId::monospace(12.));
+                    ui.add(egui::Label::new(&self.hash).selectable(true).wrap(true));
+                });
+                ui.end_row();
+
+                if !self.expected.trim().is_empty() {
+                    ui.label("");
+                    self.verification(ui);
                     ui.end_row();
-                    ui.end_row();
-                    ui.label("[OUT]:"); 
-                    ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
-
-                        //ui.style_mut().visuals.override_text_color = Some(egui::Color32::WHITE);
-                        ui.style_mut().override_font_id = Some(FontId::monospace(12.));
-                        ui.add(egui::Label::new(&self.hash).selectable(true).wrap(true));
-                    })
-                    
-                    
+                }
+            });
+
+            if !self.comparison.is_empty() {
+                ui.separator();
+                egui::Grid::new("comparison").striped(true).show(ui, |ui| {
+                    for (a, digest) in &self.comparison {
+                        ui.label(format!("{}:", a));
+                        ui.add(egui::Label::new(egui::RichText::new(digest).monospace()).wrap(true));
+                        if ui.button("Copy").clicked() {
+                            ui.output_mut(|o| o.copied_text = digest.replace(' ', ""));
+                        }
+                        ui.end_row();
+                    }
                 });
-                
-            })
+            }
             
-
         });
+
+        //debounced rehash while typing
+        if let Some(edit) = self.last_edit {
+            let waited = edit.elapsed();
+            if waited >= DEBOUNCE {
+                self.submit();
+            } else {
+                ctx.request_repaint_after(DEBOUNCE - waited);
+            }
+        }
     }
 }
@@ -0,0 +1,305 @@
+//! Checksum manifests of a directory, in the formats of the usual command line tools
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Algorithm;
+use crate::verify::algorithm_for;
+
+/// One file of a manifest, `path` is relative to the hashed directory and always uses `/`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub path:String,
+    pub size:u64,
+    /// lowercase hex
+    pub digest:String
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonManifest {
+    algorithm:String,
+    files:Vec<Entry>
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ManifestFormat {
+    /// `<digest>  <path>` as written by `sha256sum`
+    Gnu,
+    /// `SHA256 (<path>) = <digest>` as written by `sha256sum --tag` and the BSD tools
+    Bsd,
+    Csv,
+    Json
+}
+
+impl ManifestFormat {
+    pub const ALL:[ManifestFormat; 4] = [ManifestFormat::Gnu, ManifestFormat::Bsd, ManifestFormat::Csv, ManifestFormat::Json];
+}
+
+impl fmt::Display for ManifestFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestFormat::Gnu => write!(f, "GNU (sha256sum)"),
+            ManifestFormat::Bsd => write!(f, "BSD (tagged)"),
+            ManifestFormat::Csv => write!(f, "CSV"),
+            ManifestFormat::Json => write!(f, "JSON")
+        }
+    }
+}
+
+/// A manifest that was read back, used to find the files that changed since it was written
+pub struct Manifest {
+    /// `None` if the format does not name it and the digests have no consistent length
+    pub algorithm:Option<Algorithm>,
+    /// digest of every path
+    pub digests:HashMap<String, String>
+}
+
+/// The name the BSD tools use in tagged lines, e.g. `SHA256`
+fn tag(a:Algorithm) -> String {
+    a.to_string().replace('-', "")
+}
+
+/// Finds the algorithm by its name, with or without the dash
+pub fn algorithm_named(name:&str) -> Option<Algorithm> {
+    Algorithm::ALL.into_iter().find(|a| tag(*a).eq_ignore_ascii_case(&name.replace('-', "")))
+}
+
+pub fn export(format:ManifestFormat, alg:Algorithm, entries:&[Entry]) -> String {
+    match format {
+        ManifestFormat::Gnu => entries.iter().map(|e| {
+            let (prefix, path) = escape(&e.path);
+            format!("{}{}  {}\n", prefix, e.digest, path)
+        }).collect(),
+
+        ManifestFormat::Bsd => entries.iter().map(|e| {
+            let (prefix, path) = escape(&e.path);
+            format!("{}{} ({}) = {}\n", prefix, tag(alg), path, e.digest)
+        }).collect(),
+
+        ManifestFormat::Csv => {
+            let mut text = format!("path,size,{}\n", tag(alg).to_ascii_lowercase());
+            for e in entries {
+                text += &format!("{},{},{}\n", csv_field(&e.path), e.size, e.digest);
+            }
+            text
+        },
+
+        ManifestFormat::Json => {
+            let manifest = JsonManifest { algorithm: alg.to_string(), files: entries.to_vec() };
+            //serialising strings and numbers can not fail
+            serde_json::to_string_pretty(&manifest).unwrap_or_default()
+        }
+    }
+}
+
+/// Like coreutils, lines with a backslash or newline in the name start with a backslash and escape them
+fn escape(path:&str) -> (&'static str, String) {
+    if path.contains(['\\', '\n']) {
+        ("\\", path.replace('\\', "\\\\").replace('\n', "\\n"))
+    } else {
+        ("", path.to_owned())
+    }
+}
+
+fn csv_field(s:&str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+/// Reads a manifest in any of the formats of [export], the format is recognized from the content
+pub fn parse(text:&str) -> Result<Manifest, String> {
+    let trimmed = text.trim_start();
+    let (algorithm, entries) = if trimmed.starts_with('{') {
+        let json:JsonManifest = serde_json::from_str(trimmed).map_err(|e| format!("Invalid JSON manifest: {}", e))?;
+        let alg = algorithm_named(&json.algorithm).ok_or(format!("Unknown algorithm {}", json.algorithm))?;
+        (Some(alg), json.files.into_iter().map(|e| (e.path, e.digest)).collect())
+    } else if trimmed.starts_with("path,size,") {
+        let mut lines = csv_records(trimmed).into_iter();
+        let header = lines.next().unwrap_or_default();
+        let alg = header.get(2).and_then(|name| algorithm_named(name));
+        let mut entries = Vec::new();
+        for (i, record) in lines.enumerate() {
+            match record.as_slice() {
+                [path, _, digest] => entries.push((path.clone(), digest.clone())),
+                _ => return Err(format!("Line {} of the CSV manifest does not have 3 fields", i + 2)),
+            }
+        }
+        (alg, entries)
+    } else {
+        let mut alg = None;
+        let mut entries = Vec::new();
+        for (i, line) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let (a, path, digest) = parse_line(line).ok_or(format!("Line {} is not a checksum line", i + 1))?;
+            if a.is_some() {
+                alg = a;
+            }
+            entries.push((path, digest));
+        }
+        (alg, entries)
+    };
+
+    let mut digests = HashMap::new();
+    for (path, digest) in entries {
+        if algorithm_for(&digest).is_none() || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("The digest of {} is not a SHA-2 digest", path));
+        }
+        digests.insert(path, digest.to_ascii_lowercase());
+    }
+
+    //without a name every digest has to have the same length to know the algorithm
+    let algorithm = algorithm.or_else(|| {
+        let first = digests.values().next()?;
+        if digests.values().all(|d| d.len() == first.len()) {
+            algorithm_for(first)
+        } else {
+            None
+        }
+    });
+    Ok(Manifest { algorithm, digests })
+}
+
+/// A GNU or BSD checksum line as algorithm, path and digest
+fn parse_line(line:&str) -> Option<(Option<Algorithm>, String, String)> {
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+    let unescaped = |path:&str| if escaped { unescape(path) } else { path.to_owned() };
+
+    //BSD: SHA256 (path) = digest
+    if let Some((rest, digest)) = line.rsplit_once(") = ") {
+        if let Some((name, path)) = rest.split_once(" (") {
+            if let Some(alg) = algorithm_named(name) {
+                return Some((Some(alg), unescaped(path), digest.trim().to_owned()));
+            }
+        }
+    }
+
+    //GNU: digest, a space, then a space for text or * for binary mode, then the path
+    let (digest, path) = line.split_once(' ')?;
+    let path = path.strip_prefix([' ', '*'])?;
+    Some((None, unescaped(path), digest.to_owned()))
+}
+
+fn unescape(s:&str) -> String {
+    let mut res = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => res.push('\n'),
+            Some(c) => res.push(c),
+            None => res.push('\\'),
+        }
+    }
+    res
+}
+
+/// Splits CSV text into records of fields, fields in double quotes may contain commas, quotes and newlines
+fn csv_records(text:&str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            },
+            ('"', _) => quoted = !quoted,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) => (),
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            },
+            (c, _) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY:&str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    const ABC:&str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            Entry { path: "empty.txt".to_owned(), size: 0, digest: EMPTY.to_owned() },
+            Entry { path: "dir/back\\slash\nname".to_owned(), size: 3, digest: ABC.to_owned() },
+            Entry { path: "a, \"quoted\" name.csv".to_owned(), size: 3, digest: ABC.to_owned() }
+        ]
+    }
+
+    #[test]
+    fn round_trips() {
+        for format in ManifestFormat::ALL {
+            let text = export(format, Algorithm::Sha256, &entries());
+            let manifest = parse(&text).unwrap();
+            assert!(manifest.algorithm == Some(Algorithm::Sha256), "{}", format);
+            assert_eq!(manifest.digests.len(), 3, "{}", format);
+            for e in entries() {
+                assert_eq!(manifest.digests.get(&e.path), Some(&e.digest), "{}", format);
+            }
+        }
+    }
+
+    #[test]
+    fn exported_lines() {
+        let gnu = export(ManifestFormat::Gnu, Algorithm::Sha256, &entries());
+        let lines:Vec<&str> = gnu.lines().collect();
+        assert_eq!(lines[0], format!("{}  empty.txt", EMPTY));
+        assert_eq!(lines[1], format!("\\{}  dir/back\\\\slash\\nname", ABC));
+
+        let bsd = export(ManifestFormat::Bsd, Algorithm::Sha256, &entries()[..2]);
+        assert_eq!(bsd, format!("SHA256 (empty.txt) = {}\n\\SHA256 (dir/back\\\\slash\\nname) = {}\n", EMPTY, ABC));
+
+        let csv = export(ManifestFormat::Csv, Algorithm::Sha256, &entries()[2..]);
+        assert_eq!(csv, format!("path,size,sha256\n\"a, \"\"quoted\"\" name.csv\",3,{}\n", ABC));
+        assert_eq!(csv_records(&csv)[1], ["a, \"quoted\" name.csv", "3", ABC]);
+    }
+
+    #[test]
+    fn other_tools() {
+        //binary mode and uppercase digests from sha256sum, and a BSD line from another algorithm
+        let manifest = parse(&format!("{} *bin/tool\n\n", ABC.to_ascii_uppercase())).unwrap();
+        assert_eq!(manifest.digests["bin/tool"], ABC);
+        assert!(manifest.algorithm == Some(Algorithm::Sha256));
+        let sha224 = "d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f";
+        let manifest = parse(&format!("SHA-224 (x) = {}", sha224)).unwrap();
+        assert!(manifest.algorithm == Some(Algorithm::Sha224));
+        assert_eq!(unescape("a\\\\b\\nc\\"), "a\\b\nc\\");
+
+        //digests of different lengths without a name leave the algorithm open
+        let mixed = parse(&format!("{}  a\n{}  b\n", ABC, sha224)).unwrap();
+        assert!(mixed.algorithm.is_none());
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(parse("not a checksum line").is_err());
+        assert!(parse(&format!("{}  ok\nnonsense\n", ABC)).is_err());
+        assert!(parse("abcd  short").is_err());
+        assert!(parse(&format!("{}  not hex", "zz".repeat(32))).is_err());
+        assert!(parse(&format!("SHA256 (x) = {}", &ABC[1..])).is_err());
+        assert!(parse(&format!("path,size,sha256\nx,3\n{},3,{}\n", "y", ABC)).is_err());
+        assert!(parse("{\"algorithm\": \"MD5\", \"files\": []}").is_err());
+        assert!(parse("{\"algorithm\": \"SHA-256\"").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! A proof of work demo, searching for a nonce that gives `sha256d(header || nonce)` a number of leading zero bits on several threads
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use eframe::egui::{self, Color32, Ui};
+use jisp_sha2::pow::{self, Progress, Solution};
+
+enum PowMessage {
+    Progress(Progress),
+    Found(Solution),
+    Error(String)
+}
+
+struct Search {
+    rx:Receiver<PowMessage>,
+    stop:Arc<AtomicBool>,
+    header:String,
+    difficulty:u32
+}
+
+pub struct PowPanel {
+    header:String,
+    difficulty:u32,
+    threads:usize,
+    search:Option<Search>,
+    progress:Option<Progress>,
+    /// the solution with the header and difficulty it was found for
+    solution:Option<(Solution, String, u32)>,
+    message:Result<String, String>
+}
+
+impl PowPanel {
+    pub fn new() -> Self {
+        Self {
+            header: "".to_owned(),
+            difficulty: 20,
+            threads: max_threads(),
+            search: None,
+            progress: None,
+            solution: None,
+            message: Ok("".to_owned())
+        }
+    }
+
+    /// Whether the panel needs to be redrawn to show new progress
+    pub fn is_searching(&self) -> bool {
+        self.search.is_some()
+    }
+
+    pub fn show(&mut self, ui:&mut Ui) {
+        self.receive();
+
+        ui.add_sized((500., 20.), egui::TextEdit::singleline(&mut self.header).hint_text("Block header..."));
+        ui.horizontal(|ui| {
+            ui.label("Leading zero bits");
+            ui.add(egui::Slider::new(&mut self.difficulty, 1..=40));
+            ui.label("Threads");
+            ui.add(egui::Slider::new(&mut self.threads, 1..=max_threads()));
+        });
+        ui.horizontal(|ui| {
+            if self.search.is_some() {
+                if ui.button("Stop").clicked() {
+                    self.stop();
+                }
+            } else if ui.button("Search").clicked() {
+                self.start();
+            }
+            ui.label(format!("about {} hashes expected", expected_hashes(self.difficulty)));
+        });
+
+        if let Some(progress) = &self.progress {
+            ui.label(format!(
+                "{} hashes in {:.1} s, {}",
+                progress.hashes, progress.elapsed.as_secs_f64(), format_hashrate(progress.hashrate())
+            ));
+        }
+        match &self.message {
+            Ok(text) if text.is_empty() => (),
+            Ok(text) => { ui.label(text); },
+            Err(e) => { ui.colored_label(Color32::RED, format!("[Error] {}", e)); },
+        }
+
+        if let Some((solution, header, difficulty)) = &self.solution {
+            ui.separator();
+            ui.label(format!("Header: {}", header));
+            ui.label(format!("Nonce: {}, appended as 8 little endian bytes", solution.nonce));
+            ui.label(format!("Leading zero bits: {} of {} asked for", pow::leading_zero_bits(&solution.hash), difficulty));
+            ui.label(egui::RichText::new(solution.hash.to_hex()).monospace());
+            let verified = pow::pow_hash(header.as_bytes(), solution.nonce) == solution.hash;
+            if verified {
+                ui.colored_label(Color32::GREEN, "Recomputed without the midstate: matches");
+            } else {
+                ui.colored_label(Color32::RED, "Recomputed without the midstate: does not match");
+            }
+        }
+    }
+
+    fn start(&mut self) {
+        self.stop();
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = stop.clone();
+        let header = self.header.clone().into_bytes();
+        let (difficulty, threads) = (self.difficulty, self.threads);
+        thread::spawn(move|| {
+            let progress = tx.clone();
+            let result = pow::search(&header, difficulty, threads, &flag, |p| { let _ = progress.send(PowMessage::Progress(p)); });
+            let _ = match result {
+                Ok(Some(solution)) => tx.send(PowMessage::Found(solution)),
+                //stopped from the panel, which already says so
+                Ok(None) => Ok(()),
+                Err(e) => tx.send(PowMessage::Error(e.to_string())),
+            };
+        });
+
+        self.progress = None;
+        self.solution = None;
+        self.message = Ok("".to_owned());
+        self.search = Some(Search { rx, stop, header: self.header.clone(), difficulty });
+    }
+
+    fn stop(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop.store(true, Ordering::SeqCst);
+            self.message = Ok("Stopped".to_owned());
+        }
+    }
+
+    fn receive(&mut self) {
+        let Some(search) = &self.search else { return };
+        let mut finished = false;
+        loop {
+            match search.rx.try_recv() {
+                Ok(PowMessage::Progress(p)) => self.progress = Some(p),
+                Ok(PowMessage::Found(solution)) => {
+                    self.solution = Some((solution, search.header.clone(), search.difficulty));
+                    finished = true;
+                },
+                Ok(PowMessage::Error(e)) => {
+                    self.message = Err(e);
+                    finished = true;
+                },
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                },
+            }
+        }
+        if finished {
+            self.search = None;
+        }
+    }
+}
+
+fn max_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+fn expected_hashes(difficulty:u32) -> String {
+    match 1u64.checked_shl(difficulty) {
+        Some(n) => n.to_string(),
+        None => format!("2^{}", difficulty),
+    }
+}
+
+fn format_hashrate(rate:f64) -> String {
+    if rate >= 1e6 {
+        format!("{:.2} MH/s", rate / 1e6)
+    } else if rate >= 1e3 {
+        format!("{:.2} kH/s", rate / 1e3)
+    } else {
+        format!("{:.0} H/s", rate)
+    }
+}
@@ -0,0 +1,85 @@
+//! Checking a computed hash against an expected digest, such as a published checksum of a download
+use crate::Algorithm;
+
+/// Reads the expected digest and returns it as lowercase hex without any spaces.
+/// Accepted are plain hex in any case, hex split up with spaces like the output of [print_blocks](jisp_sha2::printer::print_blocks)
+/// and checksum lines such as `<digest>  file.iso`, `<digest> *file.iso` or `SHA256 (file.iso) = <digest>`.
+/// Lines of file names with a backslash or newline start with a backslash, as `sha256sum` writes them.
+pub fn parse_expected(text:&str) -> Result<String, String> {
+    let text = text.trim();
+    let text = text.strip_prefix('\\').unwrap_or(text);
+    //a BSD checksum line ends with the digest
+    let text = match text.rsplit_once(") = ") {
+        Some((name, digest)) if name.contains(" (") => digest.trim(),
+        _ => text,
+    };
+    let mut tokens = text.split_whitespace();
+    let first = tokens.next().ok_or("No digest given".to_owned())?;
+
+    //a GNU checksum line starts with the complete digest followed by the file name
+    let hex = if tokens.next().is_some() && is_hex(first) && algorithm_for(first).is_some() {
+        first.to_owned()
+    } else {
+        text.split_whitespace().collect::<String>()
+    };
+
+    if !is_hex(&hex) {
+        return Err("The expected digest is not a hex string".to_owned());
+    }
+    if algorithm_for(&hex).is_none() {
+        return Err(format!("{} hex digits does not match the length of any SHA-2 digest", hex.len()));
+    }
+    Ok(hex.to_ascii_lowercase())
+}
+
+/// Each of the algorithms has a different digest length, so the length alone tells which one was used
+pub fn algorithm_for(hex:&str) -> Option<Algorithm> {
+    match hex.len() {
+        56 => Some(Algorithm::Sha224),
+        64 => Some(Algorithm::Sha256),
+        96 => Some(Algorithm::Sha384),
+        128 => Some(Algorithm::Sha512),
+        _ => None
+    }
+}
+
+/// Compares a printed hash with the parsed expected digest
+pub fn matches(hash_text:&str, expected:&str) -> bool {
+    let hash:String = hash_text.split_whitespace().collect();
+    hash.eq_ignore_ascii_case(expected)
+}
+
+fn is_hex(s:&str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ABC:&str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn bare_hex() {
+        assert_eq!(parse_expected(&format!("  {}\n", ABC.to_ascii_uppercase())), Ok(ABC.to_owned()));
+        assert_eq!(parse_expected("ba7816bf 8f01cfea 414140de 5dae2223 b00361a3 96177a9c b410ff61 f20015ad"), Ok(ABC.to_owned()));
+        assert!(parse_expected("").is_err());
+        assert!(parse_expected(&ABC[2..]).is_err());
+        assert!(parse_expected(&ABC.replace('b', "g")).is_err());
+    }
+
+    #[test]
+    fn checksum_lines() {
+        assert_eq!(parse_expected(&format!("{}  file.iso", ABC)), Ok(ABC.to_owned()));
+        assert_eq!(parse_expected(&format!("{} *file name.iso\n", ABC)), Ok(ABC.to_owned()));
+        assert_eq!(parse_expected(&format!("SHA256 (file.iso) = {}", ABC)), Ok(ABC.to_owned()));
+        assert_eq!(parse_expected(&format!("SHA256 (a (1) = b.iso) = {}", ABC)), Ok(ABC.to_owned()));
+        assert!(parse_expected(&format!("SHA256 (file.iso) = {}", &ABC[1..])).is_err());
+    }
+
+    #[test]
+    fn escaped_lines() {
+        assert_eq!(parse_expected(&format!("\\{}  dir\\\\file\\n.iso", ABC)), Ok(ABC.to_owned()));
+        assert_eq!(parse_expected(&format!("\\SHA256 (dir\\\\file.iso) = {}", ABC)), Ok(ABC.to_owned()));
+    }
+}
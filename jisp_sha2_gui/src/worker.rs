@@ -0,0 +1,278 @@
+//! The background thread that does the actual hashing, so the interface never freezes on large inputs
+use std::any::Any;
+use std::fs::File;
+use std::io::{self, Read};
+use std::ops::ControlFlow;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Sender, Receiver};
+use std::time::{Duration, Instant};
+
+use jisp_sha2 as sha;
+use sha::constants;
+use sha::kdf::pbkdf2_with_progress;
+use sha::conversions::{u32_words_from_be_bytes, u64_words_from_be_bytes};
+use sha::printer::{print_blocks, print_u32_word_string, print_word_string};
+use sha::sha256::Sha256Hasher;
+use sha::sha512::Sha512Hasher;
+
+use crate::Algorithm;
+use crate::input::{Input, to_hex};
+
+/// A request for the hashing thread, setting `cancel` stops the job before or while it runs
+pub struct Job {
+    pub id:u64,
+    pub task:Task,
+    pub cancel:Arc<AtomicBool>
+}
+
+pub enum Task {
+    /// `compare` hashes the input with every algorithm instead of just `alg`
+    Hash { alg:Algorithm, input:Input, compare:bool },
+    Pbkdf2 { alg:Algorithm, password:Vec<u8>, salt:Vec<u8>, iterations:u32, len:usize }
+}
+
+/// Every message carries the id of the job it belongs to.
+/// A job always ends with exactly one of `Hash`, `Comparison`, `Derived`, `Error` or `Cancelled`.
+pub enum Message {
+    Started(u64),
+    /// finished and total work of a job, message blocks for hashing and iterations for key derivation
+    Progress(u64, u64, u64),
+    Hex(u64, String),
+    Hash(u64, String),
+    Comparison(u64, Vec<(Algorithm, String)>),
+    Derived(u64, String),
+    Error(u64, String),
+    Cancelled(u64)
+}
+
+enum JobError {
+    Cancelled,
+    Failed(String)
+}
+
+/// Minimum time between two progress messages, so a fast job does not flood the channel
+const PROGRESS_INTERVAL:Duration = Duration::from_millis(50);
+
+/// Handles the jobs in the order they were sent.
+/// A panic inside a job is caught and reported as an error of that job, so the thread keeps serving the queue.
+pub fn hashing_thread(tx:Sender<Message>, rx:Receiver<Job>) {
+    for job in rx.iter() {
+        let id = job.id;
+        if job.cancel.load(Ordering::SeqCst) {
+            let _ = tx.send(Message::Cancelled(id));
+            continue;
+        }
+
+        let _ = tx.send(Message::Started(id));
+        let msg = match panic::catch_unwind(AssertUnwindSafe(|| run_job(&tx, job))) {
+            Ok(Ok(())) => continue,
+            Ok(Err(JobError::Cancelled)) => Message::Cancelled(id),
+            Ok(Err(JobError::Failed(e))) => Message::Error(id, e),
+            Err(panic) => Message::Error(id, format!("The hashing thread panicked: {}", panic_text(&panic))),
+        };
+        //the receiver is only gone when the window was closed, then nobody is waiting for the result anyway
+        let _ = tx.send(msg);
+    }
+}
+
+fn run_job(tx:&Sender<Message>, job:Job) -> Result<(), JobError> {
+    let id = job.id;
+    let cancelled = || job.cancel.load(Ordering::SeqCst);
+    let mut last_progress = Instant::now();
+    let mut progress = |done:u64, total:u64| {
+        if done == total || last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            let _ = tx.send(Message::Progress(id, done, total));
+        }
+    };
+
+    match job.task {
+        Task::Hash { alg, input, compare } => {
+            let algorithms = if compare { Algorithm::ALL.to_vec() } else { vec![alg] };
+            let digests = match input {
+                Input::Bytes(bytes) => {
+                    let _ = tx.send(Message::Hex(id, padded_text(alg, &bytes)));
+                    hash_stream(&algorithms, alg, &bytes[..], bytes.len() as u64, cancelled, progress)?
+                },
+                Input::File(path) => {
+                    let failed = |e:io::Error| JobError::Failed(format!("Could not read {}: {}", path.display(), e));
+                    let file = File::open(&path).map_err(failed)?;
+                    let len = file.metadata().map_err(failed)?.len();
+                    let _ = tx.send(Message::Hex(id, format!("[{} bytes streamed from {}]", len, path.display())));
+                    hash_stream(&algorithms, alg, file, len, cancelled, progress)?
+                }
+            };
+
+            let msg = if compare {
+                Message::Comparison(id, digests)
+            } else {
+                Message::Hash(id, digests[0].1.clone())
+            };
+            let _ = tx.send(msg);
+        },
+
+        Task::Pbkdf2 { alg, password, salt, iterations, len } => {
+            let callback = |done:u64, total:u64| {
+                if cancelled() {
+                    return ControlFlow::Break(());
+                }
+                progress(done, total);
+                ControlFlow::Continue(())
+            };
+            let key = match alg {
+                Algorithm::Sha256 => pbkdf2_with_progress::<Sha256Hasher<constants::Sha256>>(&password, &salt, iterations, len, callback),
+                Algorithm::Sha224 => pbkdf2_with_progress::<Sha256Hasher<constants::Sha224>>(&password, &salt, iterations, len, callback),
+                Algorithm::Sha512 => pbkdf2_with_progress::<Sha512Hasher<constants::Sha512>>(&password, &salt, iterations, len, callback),
+                Algorithm::Sha384 => pbkdf2_with_progress::<Sha512Hasher<constants::Sha384>>(&password, &salt, iterations, len, callback),
+            };
+            match key {
+                Some(key) => {
+                    let _ = tx.send(Message::Derived(id, to_hex(&key)));
+                },
+                None if cancelled() => return Err(JobError::Cancelled),
+                None => return Err(JobError::Failed("Iterations and length must be at least 1 and the length at most 2^32 - 1 hash blocks".to_owned())),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn panic_text(panic:&Box<dyn Any + Send>) -> String {
+    if let Some(s) = panic.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = panic.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown cause".to_owned()
+    }
+}
+
+/// The preprocessed message blocks as printable text
+pub fn padded_text(a:Algorithm, bytes:&[u8]) -> String {
+    match a {
+        Algorithm::Sha256 | Algorithm::Sha224 => {
+            let i = sha::preprocessing::sha256_byte_preprocessing(bytes);
+            print_blocks(&i,true)
+        },
+        //same preprocessing as sha512 for sha384
+        Algorithm::Sha512 | Algorithm::Sha384 => {
+            let i = sha::preprocessing::sha512_byte_preprocessing(bytes);
+            print_blocks(&i,true)
+        },
+    }
+}
+
+/// One of the incremental hashers, so a stream can be fed to any of the algorithms
+enum AlgHasher {
+    Sha256(Sha256Hasher<constants::Sha256>),
+    Sha224(Sha256Hasher<constants::Sha224>),
+    Sha512(Sha512Hasher<constants::Sha512>),
+    Sha384(Sha512Hasher<constants::Sha384>)
+}
+
+impl AlgHasher {
+    fn new(a:Algorithm) -> Self {
+        match a {
+            Algorithm::Sha256 => AlgHasher::Sha256(Sha256Hasher::new()),
+            Algorithm::Sha224 => AlgHasher::Sha224(Sha256Hasher::new()),
+            Algorithm::Sha512 => AlgHasher::Sha512(Sha512Hasher::new()),
+            Algorithm::Sha384 => AlgHasher::Sha384(Sha512Hasher::new())
+        }
+    }
+
+    fn update(&mut self, bytes:&[u8]) {
+        match self {
+            AlgHasher::Sha256(h) => h.update(bytes),
+            AlgHasher::Sha224(h) => h.update(bytes),
+            AlgHasher::Sha512(h) => h.update(bytes),
+            AlgHasher::Sha384(h) => h.update(bytes)
+        }
+    }
+
+    /// The hash printed with a space between every word
+    fn finalize(self) -> String {
+        match self {
+            AlgHasher::Sha256(h) => {
+                let words:[u32;8] = u32_words_from_be_bytes(h.finalize().as_bytes());
+                print_u32_word_string(&words.to_vec())
+            },
+            AlgHasher::Sha224(h) => {
+                let words:[u32;8] = u32_words_from_be_bytes(h.finalize().as_bytes());
+                print_u32_word_string(&words[..7].to_vec())
+            },
+            AlgHasher::Sha512(h) => {
+                let words:[u64;8] = u64_words_from_be_bytes(h.finalize().as_bytes());
+                print_word_string(&words.to_vec(), true)
+            },
+            AlgHasher::Sha384(h) => {
+                let words:[u64;8] = u64_words_from_be_bytes(h.finalize().as_bytes());
+                print_word_string(&words[..6].to_vec(), true)
+            }
+        }
+    }
+}
+
+/// Streams the message through the incremental hashers so it never has to be in memory all at once.
+/// The message is only read once, no matter how many algorithms are used.
+/// Progress is counted in message blocks of `alg`, including the blocks added by the padding.
+fn hash_stream(
+    algorithms:&[Algorithm],
+    alg:Algorithm,
+    mut reader:impl Read,
+    len:u64,
+    cancelled:impl Fn() -> bool,
+    mut progress:impl FnMut(u64, u64)
+) -> Result<Vec<(Algorithm, String)>, JobError> {
+    let mut hashers:Vec<AlgHasher> = algorithms.iter().map(|a| AlgHasher::new(*a)).collect();
+    let block = alg.block_bytes() as u64;
+    let total_blocks = (len + 1 + alg.suffix_bytes() as u64).div_ceil(block);
+    let mut buffer = vec![0u8; 1 << 16];
+    let mut read = 0u64;
+    loop {
+        if cancelled() {
+            return Err(JobError::Cancelled);
+        }
+        let n = match reader.read(&mut buffer) {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            n => n.map_err(|e| JobError::Failed(format!("Could not read the input: {}", e)))?,
+        };
+        if n == 0 {
+            break;
+        }
+        for h in hashers.iter_mut() {
+            h.update(&buffer[..n]);
+        }
+        read += n as u64;
+        //the final blocks are only done once the padding is hashed
+        progress((read / block).min(total_blocks - 1), total_blocks);
+    }
+
+    let digests = algorithms.iter().copied().zip(hashers.into_iter().map(AlgHasher::finalize)).collect();
+    progress(total_blocks, total_blocks);
+    Ok(digests)
+}
+
+/// The hash of a file as lowercase hex, for hashing many files on other threads than the hashing thread.
+/// Returns `None` once `cancelled` is true, reads interrupted by a signal are retried.
+pub fn file_digest(alg:Algorithm, path:&Path, cancelled:impl Fn() -> bool) -> io::Result<Option<String>> {
+    let mut hasher = AlgHasher::new(alg);
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0u8; 1 << 16];
+    loop {
+        if cancelled() {
+            return Ok(None);
+        }
+        let n = match file.read(&mut buffer) {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            n => n?,
+        };
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(Some(hasher.finalize().split_whitespace().collect()))
+}